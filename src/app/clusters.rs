@@ -0,0 +1,234 @@
+//! Message templates mined from the entries, Drain-style: messages with the
+//! same number of tokens and the same first token are compared position by
+//! position, and ones that mostly agree share a template in which the
+//! differing tokens become `<*>`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use crate::{model::LogEntry, timestamps::TimeZoneSpec};
+
+const WILDCARD: &str = "<*>";
+const IP: &str = "<IP>";
+
+/// Share of positions that must agree for a message to join a template.
+const SIMILARITY: f64 = 0.5;
+
+/// The message's tokens, with addresses as `<IP>` and anything containing a
+/// digit (ids, counts, durations) as `<*>` up front.
+fn tokens(message: &str) -> Vec<String> {
+    message
+        .split_whitespace()
+        .map(|token| {
+            if is_ip(token) {
+                IP.to_string()
+            } else if token.chars().any(|c| c.is_ascii_digit()) {
+                WILDCARD.to_string()
+            } else {
+                token.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Templates are only compared with others of the same length and first
+/// token, which absorbing never widens.
+fn group_key(tokens: &[String]) -> (usize, String) {
+    (tokens.len(), tokens.first().cloned().unwrap_or_default())
+}
+
+/// IPv4 addresses, optionally with a port or trailing punctuation.
+fn is_ip(token: &str) -> bool {
+    let token = token.trim_end_matches([',', ';', ')', ']']);
+    let host = token.split(':').next().unwrap_or(token);
+    let parts: Vec<&str> = host.split('.').collect();
+    parts.len() == 4 && parts.iter().all(|part| part.parse::<u8>().is_ok())
+}
+
+/// A template, e.g. `User <*> logged in from <IP>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    tokens: Vec<String>,
+}
+
+impl Template {
+    /// Share of positions where `tokens` agrees with the template, and how
+    /// many wildcards it has (more is a looser fit).
+    fn similarity(&self, tokens: &[String]) -> (f64, usize) {
+        if tokens.is_empty() {
+            return (1.0, 0);
+        }
+        let same = self
+            .tokens
+            .iter()
+            .zip(tokens)
+            .filter(|(want, got)| *want != WILDCARD && want == got)
+            .count();
+        let wildcards = self.tokens.iter().filter(|t| *t == WILDCARD).count();
+        (same as f64 / tokens.len() as f64, wildcards)
+    }
+
+    fn absorb(&mut self, tokens: &[String]) {
+        for (want, got) in self.tokens.iter_mut().zip(tokens) {
+            if want != got {
+                *want = WILDCARD.to_string();
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tokens.join(" "))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cluster {
+    pub template: Template,
+    pub count: usize,
+    /// Entries per (uppercased) level.
+    pub levels: BTreeMap<String, usize>,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    /// How many buffered entries are at each instant, so `first` and `last`
+    /// can be found again as entries leave.
+    times: BTreeMap<DateTime<Utc>, usize>,
+    /// Appeared after mining started over the buffer, and not looked at in
+    /// the template list yet.
+    pub new: bool,
+}
+
+/// The templates of the buffered entries, by id in the order they appeared.
+#[derive(Clone, Debug, Default)]
+pub struct Clusters {
+    pub clusters: BTreeMap<usize, Cluster>,
+    /// Clusters by token count and first token.
+    groups: HashMap<(usize, String), Vec<usize>>,
+    /// Each buffered entry's cluster and instant, aligned with `entries`, so
+    /// filtering goes by membership and evicted entries can be taken back
+    /// out.
+    members: VecDeque<(usize, Option<DateTime<Utc>>)>,
+    next_id: usize,
+    /// Set once the buffer has been mined, so later templates are new.
+    live: bool,
+}
+
+impl Clusters {
+    pub fn mine<'a>(entries: impl Iterator<Item = &'a LogEntry>, zone: &TimeZoneSpec) -> Self {
+        let mut clusters = Self::default();
+        for entry in entries {
+            clusters.add(entry, zone);
+        }
+        clusters.live = true;
+        clusters
+    }
+
+    /// Files `entry` under its template, returning the cluster when it is a
+    /// new one.
+    pub fn add(&mut self, entry: &LogEntry, zone: &TimeZoneSpec) -> Option<usize> {
+        let tokens = tokens(&entry.message);
+        let group = self.groups.entry(group_key(&tokens)).or_default();
+        let best = group
+            .iter()
+            .map(|&id| (id, self.clusters[&id].template.similarity(&tokens)))
+            .filter(|(_, (similarity, _))| *similarity >= SIMILARITY)
+            .max_by(|(_, a), (_, b)| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)))
+            .map(|(id, _)| id);
+        let (id, created) = match best {
+            Some(id) => {
+                if let Some(cluster) = self.clusters.get_mut(&id) {
+                    cluster.template.absorb(&tokens);
+                }
+                (id, false)
+            }
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+                group.push(id);
+                self.clusters.insert(
+                    id,
+                    Cluster {
+                        template: Template { tokens },
+                        count: 0,
+                        levels: BTreeMap::new(),
+                        first: None,
+                        last: None,
+                        times: BTreeMap::new(),
+                        new: self.live,
+                    },
+                );
+                (id, true)
+            }
+        };
+        let at = entry.time.and_then(|t| t.instant(zone));
+        self.members.push_back((id, at));
+        let cluster = self.clusters.get_mut(&id).expect("cluster just filed");
+        cluster.count += 1;
+        *cluster
+            .levels
+            .entry(entry.level.to_ascii_uppercase())
+            .or_default() += 1;
+        if let Some(at) = at {
+            *cluster.times.entry(at).or_default() += 1;
+            cluster.first = Some(cluster.first.map_or(at, |first| first.min(at)));
+            cluster.last = Some(cluster.last.map_or(at, |last| last.max(at)));
+        }
+        created.then_some(id)
+    }
+
+    /// Takes the oldest entry, `entry`, back out of its cluster, and drops
+    /// the cluster once no buffered entry is left in it. Returns the dropped
+    /// cluster.
+    pub fn evict_first(&mut self, entry: &LogEntry) -> Option<usize> {
+        let (id, at) = self.members.pop_front()?;
+        let cluster = self.clusters.get_mut(&id)?;
+        cluster.count = cluster.count.saturating_sub(1);
+        if let Some(at) = at
+            && let Some(count) = cluster.times.get_mut(&at)
+        {
+            *count -= 1;
+            if *count == 0 {
+                cluster.times.remove(&at);
+                cluster.first = cluster.times.keys().next().copied();
+                cluster.last = cluster.times.keys().next_back().copied();
+            }
+        }
+        let level = entry.level.to_ascii_uppercase();
+        if let Some(count) = cluster.levels.get_mut(&level) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                cluster.levels.remove(&level);
+            }
+        }
+        if cluster.count > 0 {
+            return None;
+        }
+        let cluster = self.clusters.remove(&id)?;
+        let key = group_key(&cluster.template.tokens);
+        if let Some(group) = self.groups.get_mut(&key) {
+            group.retain(|&other| other != id);
+            if group.is_empty() {
+                self.groups.remove(&key);
+            }
+        }
+        Some(id)
+    }
+
+    /// The cluster of buffer index `idx`.
+    pub fn cluster_of(&self, idx: usize) -> Option<usize> {
+        self.members.get(idx).map(|&(id, _)| id)
+    }
+
+    /// Clusters by descending count, then in the order they appeared.
+    pub fn by_count(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = self.clusters.keys().copied().collect();
+        order.sort_by_key(|id| std::cmp::Reverse(self.clusters[id].count));
+        order
+    }
+
+    pub fn mark_seen(&mut self) {
+        self.clusters.values_mut().for_each(|c| c.new = false);
+    }
+}
@@ -1,8 +1,73 @@
+use std::{str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{jq, model::LogEntry, timestamps::TimeContext};
+
+/// How much horizontal space a column gets in the list table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnWidth {
+    /// Sized to the widest recent value, capped so one long value cannot
+    /// push everything else off screen.
+    Auto,
+    /// Exactly this many cells; longer values are truncated with `…`.
+    Fixed(u16),
+    /// Takes whatever the other columns leave, growing with its content so
+    /// long values can still be panned with `h/l`.
+    Flex,
+}
+
+impl ColumnWidth {
+    pub const DEFAULT_FIXED: u16 = 20;
+
+    /// `w` in the column selector: auto → flex → fixed → auto.
+    pub fn cycled(self) -> Self {
+        match self {
+            ColumnWidth::Auto => ColumnWidth::Flex,
+            ColumnWidth::Flex => ColumnWidth::Fixed(Self::DEFAULT_FIXED),
+            ColumnWidth::Fixed(_) => ColumnWidth::Auto,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            ColumnWidth::Auto => "auto".into(),
+            ColumnWidth::Fixed(n) => format!("{n}"),
+            ColumnWidth::Flex => "flex".into(),
+        }
+    }
+}
+
+impl FromStr for ColumnWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColumnWidth::Auto),
+            "flex" => Ok(ColumnWidth::Flex),
+            _ => s
+                .parse::<u16>()
+                .ok()
+                .filter(|n| (3..=200).contains(n))
+                .map(ColumnWidth::Fixed)
+                .ok_or_else(|| format!("invalid width '{s}' (auto, flex or 3..200 cells)")),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ColumnDef {
     pub name: String,
     pub path: Vec<String>,
     pub enabled: bool,
+    /// Computed columns derive their value from the raw JSON with a jq
+    /// expression instead of looking up `path`.
+    pub expr: Option<Arc<jq::Filter>>,
+    pub width: ColumnWidth,
+    /// Sticky columns are drawn first and stay in place while panning.
+    pub sticky: bool,
 }
 
 impl ColumnDef {
@@ -11,30 +76,144 @@ impl ColumnDef {
             name,
             path,
             enabled: false,
+            expr: None,
+            width: ColumnWidth::Auto,
+            sticky: false,
+        }
+    }
+
+    pub fn computed(name: String, expr: jq::Filter) -> Self {
+        Self {
+            enabled: true,
+            expr: Some(Arc::new(expr)),
+            ..Self::new(name, Vec::new())
         }
     }
+
+    /// Whether this is the plain `timestamp`, `level` or `message` column.
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.expr.is_none() && self.path.len() == 1 && self.path[0] == name
+    }
+
+    /// The text shown in this column for `entry`.
+    pub fn value(&self, entry: &LogEntry) -> Option<String> {
+        match &self.expr {
+            Some(expr) => expr.render(&entry.raw),
+            None => entry_field_or_raw(entry, &self.path),
+        }
+    }
+
+    /// Grows or shrinks the column by `delta` cells, switching it to a fixed
+    /// width starting from `current` (its rendered width).
+    pub fn resize(&mut self, current: usize, delta: i32) {
+        let base = match self.width {
+            ColumnWidth::Fixed(n) => i32::from(n),
+            _ => current as i32,
+        };
+        self.width = ColumnWidth::Fixed((base + delta).clamp(3, 200) as u16);
+    }
+}
+
+/// The cells of `entry` for the columns `cols`, timestamps formatted per
+/// `time`.
+pub fn row_cells_for(entry: &LogEntry, cols: &[&ColumnDef], time: &TimeContext) -> Vec<String> {
+    cols.iter()
+        .map(|col| {
+            if col.is_builtin("timestamp") {
+                time.format(&entry.timestamp, entry.time.as_ref())
+            } else {
+                col.value(entry).unwrap_or_default()
+            }
+        })
+        .collect()
+}
+
+/// Parses the column prompt: either `name = expr` or a bare expression, which
+/// then also serves as the column name.
+pub fn parse_computed_column(input: &str) -> Result<ColumnDef, String> {
+    let input = input.trim();
+    let (name, source) = match input.split_once('=') {
+        Some((name, rest))
+            if !rest.starts_with('=')
+                && !name.trim().is_empty()
+                && name
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-') =>
+        {
+            (name.trim(), rest.trim())
+        }
+        _ => (input, input),
+    };
+    if source.is_empty() {
+        return Err("usage: <name> = <jq expression>".into());
+    }
+    let expr = jq::Filter::parse(source).map_err(|err| err.to_string())?;
+    Ok(ColumnDef::computed(name.to_string(), expr))
+}
+
+fn extract_field_string(value: &Value, path: &[String]) -> Option<String> {
+    if let Value::String(s) = value
+        && path.len() == 1
+    {
+        match path[0].as_str() {
+            "timestamp" => return Some("-".into()),
+            "level" => return Some("TEXT".into()),
+            "message" => return Some(s.clone()),
+            _ => {}
+        }
+    }
+
+    let mut current = value;
+    for key in path {
+        current = current.get(key)?;
+    }
+    match current {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null => Some("null".into()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn entry_field_or_raw(entry: &LogEntry, path: &[String]) -> Option<String> {
+    if path.len() == 1 {
+        match path[0].as_str() {
+            "timestamp" => return Some(entry.timestamp.clone()),
+            "level" => return Some(entry.level.clone()),
+            "message" => return Some(entry.message.clone()),
+            _ => {}
+        }
+    }
+    extract_field_string(&entry.raw, path)
 }
 
 pub fn default_columns() -> Vec<ColumnDef> {
     vec![
         ColumnDef {
-            name: "timestamp".into(),
-            path: vec!["timestamp".into()],
             enabled: true,
+            sticky: true,
+            ..ColumnDef::new("timestamp".into(), vec!["timestamp".into()])
         },
         ColumnDef {
-            name: "level".into(),
-            path: vec!["level".into()],
             enabled: true,
+            sticky: true,
+            ..ColumnDef::new("level".into(), vec!["level".into()])
         },
         ColumnDef {
-            name: "message".into(),
-            path: vec!["message".into()],
             enabled: true,
+            width: ColumnWidth::Flex,
+            ..ColumnDef::new("message".into(), vec!["message".into()])
         },
     ]
 }
 
+/// The default column called `name`, if there is one.
+pub fn default_column(name: &str) -> Option<ColumnDef> {
+    default_columns().into_iter().find(|c| c.name == name)
+}
+
 pub fn is_reserved_column(key: &str) -> bool {
     matches!(key, "timestamp" | "level" | "message" | "instant" | "data")
 }
@@ -0,0 +1,329 @@
+//! The `:` command line: parsing and tab completion. Commands that have a
+//! key run through the same [`Action`]s as the key handlers.
+
+use std::{fs, path::PathBuf};
+
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::timestamps::{TimeZoneSpec, Timestamp, TimestampMode};
+
+use super::{
+    dedupe::DedupeBy,
+    export::{ExportFormat, ExportSpec},
+    keymap::{Action, KeyMode},
+    state::App,
+};
+
+/// Commands beyond the plain action names, with their usage.
+pub const COMMANDS: [(&str, &str); 10] = [
+    ("filter", "filter <pattern> (empty clears)"),
+    ("columns", "columns add <path | name = jq> | remove <name>"),
+    ("goto", "goto <row | HH:MM[:SS] | timestamp>"),
+    (
+        "set",
+        "set [no]wrap | [no]field_wrap | [no]autoscroll | timestamps=<mode> | tz=<zone>",
+    ),
+    ("source", "source add <file> | list"),
+    (
+        "mark",
+        "mark [letter] (without a letter, toggles a bookmark)",
+    ),
+    (
+        "reference",
+        "reference (measures delta timestamps from the selected entry)",
+    ),
+    (
+        "export",
+        "export [range] <raw | json | jsonl | csv | text | timeline> <file>",
+    ),
+    ("note", "note [text] (without text, opens the note prompt)"),
+    (
+        "dedupe",
+        "dedupe [message | <field> ...] (without arguments, toggles folding by message)",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Anything a key can do, by its action name (`:zoom`, `:save_session`).
+    Action(Action),
+    Filter(String),
+    AddColumn(String),
+    RemoveColumn(String),
+    Goto(GotoTarget),
+    Set(Setting),
+    AddSource(PathBuf),
+    ListSources,
+    Export(ExportSpec),
+    Note(String),
+    /// Sets the named mark on the selected entry.
+    Mark(char),
+    Dedupe(DedupeBy),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GotoTarget {
+    /// 1-based row of the filtered list.
+    Row(usize),
+    /// Wall-clock time in the display zone, on the selected entry's day.
+    TimeOfDay(NaiveTime),
+    Instant(Timestamp),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flag {
+    On,
+    Off,
+    Toggle,
+}
+
+impl Flag {
+    pub fn apply(self, current: bool) -> bool {
+        match self {
+            Flag::On => true,
+            Flag::Off => false,
+            Flag::Toggle => !current,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Setting {
+    Wrap(Flag),
+    FieldWrap(Flag),
+    Autoscroll(Flag),
+    Timestamps(TimestampMode),
+    TimeZone(TimeZoneSpec),
+}
+
+const FLAGS: [&str; 3] = ["wrap", "field_wrap", "autoscroll"];
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim().trim_start_matches(':').trim_start();
+    let (name, rest) = input
+        .split_once(char::is_whitespace)
+        .map_or((input, ""), |(name, rest)| (name, rest.trim()));
+    let (sub, arg) = rest
+        .split_once(char::is_whitespace)
+        .map_or((rest, ""), |(sub, arg)| (sub, arg.trim()));
+    let usage = |name: &str| {
+        let usage = COMMANDS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or("", |c| c.1);
+        Err(format!("usage: :{usage}"))
+    };
+    match name {
+        "" => Err("empty command".into()),
+        "filter" => Ok(Command::Filter(rest.to_string())),
+        "columns" => match sub {
+            "" => Ok(Command::Action(Action::Columns)),
+            "add" if !arg.is_empty() => Ok(Command::AddColumn(arg.to_string())),
+            "remove" if !arg.is_empty() => Ok(Command::RemoveColumn(arg.to_string())),
+            _ => usage(name),
+        },
+        "goto" if !rest.is_empty() => parse_goto(rest).map(Command::Goto),
+        "set" if !rest.is_empty() => parse_setting(rest).map(Command::Set),
+        "source" => match sub {
+            "" | "list" => Ok(Command::ListSources),
+            "add" if !arg.is_empty() => Ok(Command::AddSource(PathBuf::from(arg))),
+            _ => usage(name),
+        },
+        "mark" if rest.is_empty() => Ok(Command::Action(Action::Bookmark)),
+        "mark" => match rest.chars().collect::<Vec<_>>()[..] {
+            [name] if name.is_ascii_alphabetic() => Ok(Command::Mark(name)),
+            _ => usage(name),
+        },
+        "reference" if rest.is_empty() => Ok(Command::Action(Action::TimeReference)),
+        "note" if rest.is_empty() => Ok(Command::Action(Action::Note)),
+        "note" => Ok(Command::Note(rest.to_string())),
+        "dedupe" if rest.is_empty() => Ok(Command::Action(Action::Dedupe)),
+        "dedupe" if rest == "message" => Ok(Command::Dedupe(DedupeBy::Message)),
+        "dedupe" => Ok(Command::Dedupe(DedupeBy::Fields(
+            rest.split_whitespace()
+                .map(|field| field.split('.').map(str::to_string).collect())
+                .collect(),
+        ))),
+        "export" if !rest.is_empty() => parse_export(rest).map(Command::Export),
+        "q" => Ok(Command::Action(Action::Quit)),
+        _ if COMMANDS.iter().any(|(n, _)| *n == name) => usage(name),
+        _ => match name.parse::<Action>() {
+            Ok(action) if KeyMode::Normal.actions().contains(&action) => {
+                if rest.is_empty() {
+                    Ok(Command::Action(action))
+                } else {
+                    Err(format!(":{name} takes no arguments"))
+                }
+            }
+            _ => Err(format!("unknown command '{name}'")),
+        },
+    }
+}
+
+fn parse_goto(arg: &str) -> Result<GotoTarget, String> {
+    if let Ok(row) = arg.parse::<usize>() {
+        return Ok(GotoTarget::Row(row));
+    }
+    let time = ["%H:%M", "%H:%M:%S", "%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(arg, format).ok());
+    if let Some(time) = time {
+        return Ok(GotoTarget::TimeOfDay(time));
+    }
+    Timestamp::parse(arg)
+        .map(GotoTarget::Instant)
+        .ok_or_else(|| format!("cannot read '{arg}' as a row, time or timestamp"))
+}
+
+fn parse_setting(arg: &str) -> Result<Setting, String> {
+    if let Some((name, value)) = arg.split_once('=') {
+        return match name.trim() {
+            "timestamps" => value.trim().parse().map(Setting::Timestamps),
+            "tz" => value.trim().parse().map(Setting::TimeZone),
+            other => Err(format!("unknown option '{other}'")),
+        };
+    }
+    let (name, flag) = if let Some(name) = arg.strip_suffix('!') {
+        (name, Flag::Toggle)
+    } else if let Some(name) = arg.strip_prefix("no") {
+        (name, Flag::Off)
+    } else {
+        (arg, Flag::On)
+    };
+    match name {
+        "wrap" => Ok(Setting::Wrap(flag)),
+        "field_wrap" => Ok(Setting::FieldWrap(flag)),
+        "autoscroll" => Ok(Setting::Autoscroll(flag)),
+        _ => Err(format!("unknown option '{arg}'")),
+    }
+}
+
+fn parse_export(arg: &str) -> Result<ExportSpec, String> {
+    let (range, arg) = match arg.split_once(char::is_whitespace) {
+        Some(("range", rest)) => (true, rest.trim_start()),
+        _ => (false, arg),
+    };
+    let Some((format, path)) = arg.split_once(char::is_whitespace) else {
+        return Err("usage: :export [range] <format> <file>".into());
+    };
+    Ok(ExportSpec {
+        format: format.parse()?,
+        range,
+        path: PathBuf::from(path.trim()),
+    })
+}
+
+/// When the target of `:goto` is, given the selected entry's time for
+/// `HH:MM` targets.
+pub fn goto_instant(
+    target: &GotoTarget,
+    zone: &TimeZoneSpec,
+    selected: Option<DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    match target {
+        GotoTarget::Row(_) => None,
+        GotoTarget::Instant(ts) => ts.instant(zone),
+        GotoTarget::TimeOfDay(time) => {
+            let day = zone.localize(selected?).date_naive();
+            zone.resolve(&day.and_time(*time))
+        }
+    }
+}
+
+/// Tab completion state: the word being completed and its candidates.
+#[derive(Clone, Debug)]
+pub struct Completion {
+    /// Byte offset of the completed word in the buffer.
+    pub start: usize,
+    pub candidates: Vec<String>,
+    pub index: usize,
+    /// The buffer as completion left it; any edit starts over.
+    pub applied: String,
+}
+
+/// Candidates for the last word of `input`, and where that word starts.
+pub fn complete(app: &App, input: &str) -> (usize, Vec<String>) {
+    let start = input.rfind(' ').map_or(0, |i| i + 1);
+    let word = &input[start..];
+    let words: Vec<&str> = input[..start].split_whitespace().collect();
+    let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let candidates: Vec<String> = match words.as_slice() {
+        [] => COMMANDS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .chain(
+                KeyMode::Normal
+                    .actions()
+                    .iter()
+                    .map(|a| a.name().to_string()),
+            )
+            .collect(),
+        ["columns"] => owned(&["add", "remove"]),
+        ["dedupe", ..] => std::iter::once("message".to_string())
+            .chain(app.columns.iter().map(|c| c.name.clone()))
+            .collect(),
+        ["columns", "add"] => app
+            .columns
+            .iter()
+            .filter(|c| !c.enabled && c.expr.is_none())
+            .map(|c| c.name.clone())
+            .collect(),
+        ["columns", "remove"] => app
+            .columns
+            .iter()
+            .filter(|c| c.enabled)
+            .map(|c| c.name.clone())
+            .collect(),
+        ["set"] => FLAGS
+            .iter()
+            .flat_map(|flag| [flag.to_string(), format!("no{flag}"), format!("{flag}!")])
+            .chain(
+                TimestampMode::ALL
+                    .iter()
+                    .map(|mode| format!("timestamps={}", mode.label().to_lowercase())),
+            )
+            .chain(["tz=local".to_string(), "tz=UTC".to_string()])
+            .collect(),
+        ["source"] => owned(&["add", "list"]),
+        ["source", "add"] => complete_path(word),
+        ["export"] => ExportFormat::ALL
+            .iter()
+            .map(|format| format.name().to_string())
+            .chain(["range".to_string()])
+            .collect(),
+        ["export", "range"] => ExportFormat::ALL
+            .iter()
+            .map(|format| format.name().to_string())
+            .collect(),
+        ["export", _] | ["export", "range", _] => complete_path(word),
+        _ => Vec::new(),
+    };
+    let mut matching: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(word))
+        .collect();
+    matching.sort();
+    matching.dedup();
+    (start, matching)
+}
+
+/// Files and directories starting with `partial`; directories end in `/`.
+fn complete_path(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => (&partial[..=i], &partial[i + 1..]),
+        None => ("", partial),
+    };
+    let Ok(read) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    read.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect()
+}
@@ -0,0 +1,114 @@
+//! Following a correlation id (trace, request or correlation id) across the
+//! buffer, with a trail of the views it was followed from.
+
+use serde_json::Value;
+
+use crate::model::LogEntry;
+
+use super::{
+    filters::{FilterLayer, FilterModes, Query},
+    levels::LevelFilter,
+    sort::SortOrder,
+};
+
+/// Where ids are looked for, in order, unless `correlation_fields` is set in
+/// the config.
+pub const DEFAULT_FIELDS: [&str; 6] = [
+    "traceId",
+    "trace_id",
+    "requestId",
+    "request_id",
+    "correlationId",
+    "correlation_id",
+];
+
+/// The first of `fields` set on `value`, as its name and the id. A path of a
+/// single name matches that key at any depth; longer ones only where they say.
+pub fn find_id(value: &Value, fields: &[Vec<String>]) -> Option<(String, String)> {
+    fields.iter().find_map(|path| {
+        let id = id_text(lookup(value, path)?)?;
+        Some((path.join("."), id))
+    })
+}
+
+/// The value at `path`, or of the first key named like a single-name path.
+pub fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    match path {
+        [name] => find_key(value, name),
+        _ => path.iter().try_fold(value, |v, key| v.get(key)),
+    }
+}
+
+/// The value of the first `name` key in `value`, outer objects first.
+fn find_key<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .get(name)
+            .or_else(|| map.values().find_map(|v| find_key(v, name))),
+        Value::Array(items) => items.iter().find_map(|v| find_key(v, name)),
+        _ => None,
+    }
+}
+
+/// An id as text: non-empty strings and numbers.
+pub fn id_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The filter that follows an id: entries carrying `id` in any of the
+/// correlation fields, whichever one the entry's source uses.
+#[derive(Clone, Debug)]
+pub struct Correlation {
+    /// The field the id was found in, for the breadcrumb.
+    pub field: String,
+    pub id: String,
+    fields: Vec<Vec<String>>,
+    /// Whether the id appears verbatim in serialized JSON, so entries whose
+    /// search text lacks it can be skipped without walking them.
+    plain: bool,
+}
+
+impl Correlation {
+    pub fn new(field: String, id: String, fields: Vec<Vec<String>>) -> Self {
+        let plain = serde_json::to_string(&id).is_ok_and(|quoted| quoted.len() == id.len() + 2);
+        Self {
+            field,
+            id,
+            fields,
+            plain,
+        }
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.plain && !entry.search_text.contains(self.id.as_str()) {
+            return false;
+        }
+        self.fields.iter().any(|path| {
+            lookup(&entry.raw, path).and_then(id_text).as_deref() == Some(self.id.as_str())
+        })
+    }
+}
+
+/// The filters and sort in place before an id was followed.
+#[derive(Clone, Debug)]
+pub struct SavedView {
+    pub filter_query: String,
+    pub filter_compiled: Option<Query>,
+    pub filter_query_modes: FilterModes,
+    pub filter_stack: Vec<FilterLayer>,
+    pub level_filter: LevelFilter,
+    pub sort: Option<SortOrder>,
+    /// Absolute sequence number of the entry the id was followed from.
+    pub selected: Option<u64>,
+}
+
+/// One step of the breadcrumb: a followed id and the view to go back to.
+#[derive(Clone, Debug)]
+pub struct Breadcrumb {
+    pub correlation: Correlation,
+    pub view: SavedView,
+}
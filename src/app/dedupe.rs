@@ -0,0 +1,242 @@
+//! Folding runs of repeated entries, such as a retry loop logging the same
+//! line over and over, into one row of the list.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    ops::Range,
+};
+
+use crate::model::LogEntry;
+
+use super::correlation::lookup;
+
+/// What makes consecutive entries repeats of each other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DedupeBy {
+    Message,
+    /// The values at these paths; a single name is found at any depth.
+    Fields(Vec<Vec<String>>),
+}
+
+impl DedupeBy {
+    /// Entries fold when their keys are equal. Entries without any of the
+    /// fields have no key and never fold.
+    fn key(&self, entry: &LogEntry) -> Option<String> {
+        match self {
+            DedupeBy::Message => Some(entry.message.clone()),
+            DedupeBy::Fields(paths) => {
+                let values: Vec<Option<String>> = paths
+                    .iter()
+                    .map(|path| lookup(&entry.raw, path).map(|v| v.to_string()))
+                    .collect();
+                values.iter().any(Option::is_some).then(|| {
+                    values
+                        .iter()
+                        .map(|v| v.as_deref().unwrap_or(""))
+                        .collect::<Vec<_>>()
+                        .join("\u{1f}")
+                })
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            DedupeBy::Message => "message".to_string(),
+            DedupeBy::Fields(paths) => paths
+                .iter()
+                .map(|path| path.join("."))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Dedupe {
+    pub by: DedupeBy,
+    /// Fold key of every buffered entry, aligned with `entries`.
+    keys: VecDeque<Option<String>>,
+    /// The filtered entries before folding, in display order.
+    pub matched: Vec<usize>,
+    /// Runs of repeats, as ranges of `matched`.
+    pub runs: Vec<Range<usize>>,
+    /// The run each visible row belongs to, aligned with `filtered_indices`.
+    pub row_runs: Vec<usize>,
+    /// Expanded runs, by the sequence number of their first entry.
+    expanded: HashSet<u64>,
+}
+
+impl Dedupe {
+    pub fn new(by: DedupeBy, entries: &[LogEntry]) -> Self {
+        Self {
+            keys: entries.iter().map(|entry| by.key(entry)).collect(),
+            by,
+            matched: Vec::new(),
+            runs: Vec::new(),
+            row_runs: Vec::new(),
+            expanded: HashSet::new(),
+        }
+    }
+
+    pub fn push(&mut self, entry: &LogEntry) {
+        self.keys.push_back(self.by.key(entry));
+    }
+
+    /// Drops buffer index 0 and shifts the other indices down in `matched`,
+    /// the runs and `rows`, the visible rows, without folding the rest
+    /// again. `evicted` counts the dropped entry.
+    pub fn evict_first(&mut self, rows: &mut Vec<usize>, evicted: u64) {
+        self.keys.pop_front();
+        // Sequence numbers outlive the shift, so only the dropped entry's
+        // own one goes.
+        let seq = |idx: usize| evicted - 1 + idx as u64;
+        let head_expanded = self.expanded.remove(&seq(0));
+        let mut refold = false;
+        if let Some(pos) = self.matched.iter().position(|&idx| idx == 0) {
+            let run = self.runs.partition_point(|run| run.end <= pos);
+            let range = self.runs[run].clone();
+            let first_row = self.row_of_run(run);
+            let expanded = if pos == range.start {
+                head_expanded
+            } else {
+                self.expanded.contains(&seq(self.matched[range.start]))
+            };
+            if range.len() == 1 {
+                let row = first_row.expect("a single entry run has a row");
+                rows.remove(row);
+                self.row_runs.remove(row);
+                self.row_runs
+                    .iter_mut()
+                    .filter(|r| **r > run)
+                    .for_each(|r| *r -= 1);
+                self.runs.remove(run);
+                // Dropping a run between two repeats of each other joins
+                // them. The keys have already shifted down.
+                if run > 0 && run < self.runs.len() {
+                    let key = |run: usize| &self.keys[self.matched[self.runs[run].start] - 1];
+                    refold = key(run).is_some() && key(run) == key(run - 1);
+                }
+            } else {
+                let first_row = first_row.expect("a run's first entry has a row");
+                if pos == range.start && !expanded {
+                    rows[first_row] = self.matched[pos + 1];
+                } else if expanded {
+                    rows.remove(first_row + pos - range.start);
+                    self.row_runs.remove(first_row + pos - range.start);
+                    if pos == range.start {
+                        self.expanded.insert(seq(self.matched[pos + 1]));
+                    }
+                }
+                self.runs[run].end -= 1;
+            }
+            for range in &mut self.runs[run..] {
+                if range.start > pos {
+                    range.start -= 1;
+                    range.end -= 1;
+                }
+            }
+            self.matched.remove(pos);
+        }
+        self.matched.iter_mut().for_each(|idx| *idx -= 1);
+        rows.iter_mut().for_each(|idx| *idx -= 1);
+        if refold {
+            let matched = std::mem::take(&mut self.matched);
+            *rows = self.fold(matched, evicted);
+        }
+    }
+
+    /// Adds buffer index `idx` after the last matched entry, extending the
+    /// last run when it repeats it, without folding the rest again. Returns
+    /// whether that adds a row to the list.
+    pub fn append(&mut self, idx: usize, evicted: u64) -> bool {
+        let pos = self.matched.len();
+        let key = self.keys[idx].as_ref();
+        let extends = key.is_some()
+            && self
+                .runs
+                .last()
+                .is_some_and(|run| self.keys[self.matched[run.start]].as_ref() == key);
+        self.matched.push(idx);
+        if extends {
+            let run = self.runs.len() - 1;
+            self.runs[run].end = pos + 1;
+            let shown = self.is_expanded(run, evicted);
+            if shown {
+                self.row_runs.push(run);
+            }
+            shown
+        } else {
+            self.runs.push(pos..pos + 1);
+            self.row_runs.push(self.runs.len() - 1);
+            true
+        }
+    }
+
+    /// Splits `matched` into runs and returns the visible rows: the first
+    /// entry of each folded run, and every entry of the expanded ones.
+    /// `evicted` turns buffer indices into sequence numbers.
+    pub fn fold(&mut self, matched: Vec<usize>, evicted: u64) -> Vec<usize> {
+        self.runs.clear();
+        self.row_runs.clear();
+        let mut start = 0;
+        for pos in 1..=matched.len() {
+            let key = |pos: usize| self.keys[matched[pos]].as_ref();
+            if pos == matched.len() || key(pos).is_none() || key(pos) != key(start) {
+                self.runs.push(start..pos);
+                start = pos;
+            }
+        }
+        let mut rows = Vec::with_capacity(self.runs.len());
+        for (run, range) in self.runs.iter().enumerate() {
+            let members = &matched[range.clone()];
+            let shown = if self.expanded.contains(&(evicted + members[0] as u64)) {
+                members
+            } else {
+                &members[..1]
+            };
+            rows.extend_from_slice(shown);
+            self.row_runs.extend(std::iter::repeat_n(run, shown.len()));
+        }
+        self.matched = matched;
+        rows
+    }
+
+    /// The entries of the run shown at `row`.
+    pub fn members(&self, row: usize) -> &[usize] {
+        self.row_runs
+            .get(row)
+            .map_or(&[], |&run| &self.matched[self.runs[run].clone()])
+    }
+
+    /// The run that has buffer index `idx` among its entries.
+    pub fn run_of(&self, idx: usize) -> Option<usize> {
+        let pos = self.matched.iter().position(|&i| i == idx)?;
+        Some(self.runs.partition_point(|run| run.end <= pos))
+    }
+
+    /// The first row of `run`.
+    pub fn row_of_run(&self, run: usize) -> Option<usize> {
+        let row = self.row_runs.partition_point(|&r| r < run);
+        (self.row_runs.get(row) == Some(&run)).then_some(row)
+    }
+
+    pub fn is_expanded(&self, run: usize, evicted: u64) -> bool {
+        self.expanded
+            .contains(&(evicted + self.matched[self.runs[run].start] as u64))
+    }
+
+    /// Expands or folds `run`. Runs of a single entry have nothing to expand.
+    pub fn set_expanded(&mut self, run: usize, expanded: bool, evicted: u64) -> bool {
+        let range = self.runs[run].clone();
+        if range.len() < 2 {
+            return false;
+        }
+        let seq = evicted + self.matched[range.start] as u64;
+        if expanded {
+            self.expanded.insert(seq)
+        } else {
+            self.expanded.remove(&seq)
+        }
+    }
+}
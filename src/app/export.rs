@@ -0,0 +1,314 @@
+//! Writing the filtered entries to a file. Exports run on a worker thread
+//! over a snapshot of the rows and report progress back to the status bar.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    model::LogEntry,
+    timestamps::{TimeContext, TimeZoneSpec, TimestampMode},
+};
+
+use super::columns::{ColumnDef, row_cells_for};
+
+/// Progress is reported after every this many rows.
+const PROGRESS_EVERY: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One line per entry, exactly as it was read.
+    Raw,
+    /// A pretty-printed JSON array.
+    Json,
+    Jsonl,
+    /// The enabled columns, with their names as the header row.
+    Csv,
+    /// The rows as the list shows them, columns padded to align.
+    Text,
+    /// A Markdown table of the annotated entries and their notes.
+    Timeline,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Raw,
+        ExportFormat::Json,
+        ExportFormat::Jsonl,
+        ExportFormat::Csv,
+        ExportFormat::Text,
+        ExportFormat::Timeline,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Raw => "raw",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Text => "text",
+            ExportFormat::Timeline => "timeline",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExportFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown export format '{s}' (raw, json, jsonl, csv, text or timeline)")
+            })
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What `:export` was asked to write.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportSpec {
+    pub format: ExportFormat,
+    /// Only the rows from the first bookmarked or named-marked entry to the
+    /// last.
+    pub range: bool,
+    pub path: PathBuf,
+}
+
+/// How cells are rendered for the CSV and text formats, mirroring the list.
+#[derive(Clone, Debug)]
+pub struct TableSpec {
+    pub columns: Vec<ColumnDef>,
+    pub mode: TimestampMode,
+    pub zone: TimeZoneSpec,
+    /// The delta reference instant; deltas are otherwise to the previous row.
+    pub reference: Option<DateTime<Utc>>,
+    /// The note of each row, for the timeline.
+    pub notes: Vec<String>,
+}
+
+impl TableSpec {
+    fn cells(&self, rows: &[LogEntry]) -> Vec<Vec<String>> {
+        let cols: Vec<&ColumnDef> = self.columns.iter().collect();
+        let mut time = TimeContext {
+            mode: self.mode,
+            zone: &self.zone,
+            now: Utc::now(),
+            base: None,
+        };
+        let mut previous = None;
+        rows.iter()
+            .map(|entry| {
+                time.base = self.reference.or(previous);
+                let cells = row_cells_for(entry, &cols, &time);
+                previous = entry.time.and_then(|t| t.instant(&self.zone)).or(previous);
+                cells
+            })
+            .collect()
+    }
+}
+
+/// Writes `rows` in `format`, calling `progress` with the number of rows
+/// written so far every [`PROGRESS_EVERY`] rows.
+pub fn write_rows(
+    out: &mut impl Write,
+    format: ExportFormat,
+    rows: &[LogEntry],
+    table: &TableSpec,
+    mut progress: impl FnMut(usize),
+) -> io::Result<()> {
+    let mut report = |written: usize| {
+        if written.is_multiple_of(PROGRESS_EVERY) {
+            progress(written);
+        }
+    };
+    match format {
+        ExportFormat::Raw | ExportFormat::Jsonl => {
+            for (i, entry) in rows.iter().enumerate() {
+                match (&*entry.raw, format) {
+                    // Text lines are quoted to stay valid JSON.
+                    (raw @ Value::String(_), ExportFormat::Jsonl) => writeln!(out, "{raw}")?,
+                    _ => writeln!(out, "{}", entry.original_line())?,
+                }
+                report(i + 1);
+            }
+        }
+        ExportFormat::Json => {
+            write!(out, "[")?;
+            for (i, entry) in rows.iter().enumerate() {
+                let pretty = serde_json::to_string_pretty(&*entry.raw)?;
+                let separator = if i == 0 { "" } else { "," };
+                write!(out, "{separator}\n  {}", pretty.replace('\n', "\n  "))?;
+                report(i + 1);
+            }
+            writeln!(out, "{}]", if rows.is_empty() { "" } else { "\n" })?;
+        }
+        ExportFormat::Csv => {
+            let header: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+            write_csv_record(out, &header)?;
+            for (i, cells) in table.cells(rows).iter().enumerate() {
+                let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+                write_csv_record(out, &cells)?;
+                report(i + 1);
+            }
+        }
+        ExportFormat::Text => {
+            let header: Vec<String> = table.columns.iter().map(|c| c.name.clone()).collect();
+            let cells = table.cells(rows);
+            let widths: Vec<usize> = (0..header.len())
+                .map(|col| {
+                    std::iter::once(&header)
+                        .chain(&cells)
+                        .map(|row| one_line(&row[col]).width())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            write_text_row(out, &header, &widths)?;
+            for (i, row) in cells.iter().enumerate() {
+                write_text_row(out, row, &widths)?;
+                report(i + 1);
+            }
+        }
+        ExportFormat::Timeline => {
+            // Relative times would be stale by the time anyone reads it.
+            let mode = match table.mode {
+                TimestampMode::Relative | TimestampMode::Delta => TimestampMode::Original,
+                mode => mode,
+            };
+            let time = TimeContext {
+                mode,
+                zone: &table.zone,
+                now: Utc::now(),
+                base: None,
+            };
+            writeln!(out, "# Timeline\n")?;
+            writeln!(out, "| Time | Level | Message | Note |")?;
+            writeln!(out, "| --- | --- | --- | --- |")?;
+            for (i, (entry, note)) in rows.iter().zip(&table.notes).enumerate() {
+                let cells = [
+                    time.format(&entry.timestamp, entry.time.as_ref()),
+                    entry.level.clone(),
+                    entry.message.clone(),
+                    note.clone(),
+                ];
+                let cells: Vec<String> = cells
+                    .iter()
+                    .map(|cell| one_line(cell).replace('|', "\\|"))
+                    .collect();
+                writeln!(out, "| {} |", cells.join(" | "))?;
+                report(i + 1);
+            }
+        }
+    }
+    out.flush()
+}
+
+fn write_csv_record(out: &mut impl Write, fields: &[&str]) -> io::Result<()> {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    write!(out, "{}\r\n", fields.join(","))
+}
+
+fn write_text_row(out: &mut impl Write, cells: &[String], widths: &[usize]) -> io::Result<()> {
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        let cell = one_line(cell);
+        line.push_str(&cell);
+        if i + 1 < cells.len() {
+            line.push_str(&" ".repeat(width - cell.width() + 2));
+        }
+    }
+    writeln!(out, "{}", line.trim_end())
+}
+
+/// Line breaks and tabs become spaces so a row stays on one line.
+fn one_line(text: &str) -> String {
+    text.chars()
+        .map(|ch| if ch.is_control() { ' ' } else { ch })
+        .collect()
+}
+
+enum ExportEvent {
+    Progress(usize),
+    Done,
+    Failed(String),
+}
+
+pub enum ExportPoll {
+    Pending,
+    /// More rows were written since the last poll.
+    Progress(usize),
+    Done,
+    Failed(String),
+}
+
+/// An export running on a worker thread.
+pub struct ExportJob {
+    pub path: PathBuf,
+    pub total: usize,
+    rx: Receiver<ExportEvent>,
+}
+
+impl ExportJob {
+    pub fn spawn(file: File, spec: &ExportSpec, rows: Vec<LogEntry>, table: TableSpec) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let format = spec.format;
+        let total = rows.len();
+        thread::spawn(move || {
+            let mut out = BufWriter::new(file);
+            let result = write_rows(&mut out, format, &rows, &table, |written| {
+                let _ = tx.send(ExportEvent::Progress(written));
+            });
+            let _ = tx.send(match result {
+                Ok(()) => ExportEvent::Done,
+                Err(err) => ExportEvent::Failed(err.to_string()),
+            });
+        });
+        Self {
+            path: spec.path.clone(),
+            total,
+            rx,
+        }
+    }
+
+    pub fn poll(&self) -> ExportPoll {
+        let mut progress = None;
+        loop {
+            match self.rx.try_recv() {
+                Ok(ExportEvent::Progress(written)) => progress = Some(written),
+                Ok(ExportEvent::Done) => return ExportPoll::Done,
+                Ok(ExportEvent::Failed(err)) => return ExportPoll::Failed(err),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return ExportPoll::Failed("the export thread stopped".into());
+                }
+            }
+        }
+        progress.map_or(ExportPoll::Pending, ExportPoll::Progress)
+    }
+}
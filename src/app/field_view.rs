@@ -4,9 +4,23 @@ use serde_json::Value;
 #[derive(Clone)]
 pub struct FieldEntry {
     pub path: String,
+    /// The same location as a jq path (`.data["user-id"][0]`), unambiguous
+    /// even when keys contain dots.
+    pub jq_path: String,
     pub value: Value,
 }
 
+impl FieldEntry {
+    /// A jq predicate selecting entries where this field has exactly this value.
+    pub fn equals_predicate(&self) -> String {
+        format!("{} == {}", self.jq_path, self.value)
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        !matches!(&self.value, Value::Object(_) | Value::Array(_))
+    }
+}
+
 pub struct FieldViewState {
     pub fields: Vec<FieldEntry>,
     pub filtered_indices: Vec<usize>,
@@ -48,7 +62,7 @@ pub enum FieldZoom {
 }
 
 pub fn collect_fields(value: &Value) -> Vec<FieldEntry> {
-    fn walk(value: &Value, path: String, out: &mut Vec<FieldEntry>) {
+    fn walk(value: &Value, path: String, jq_path: String, out: &mut Vec<FieldEntry>) {
         let display_path = if path.is_empty() {
             "(root)".to_string()
         } else {
@@ -56,6 +70,11 @@ pub fn collect_fields(value: &Value) -> Vec<FieldEntry> {
         };
         out.push(FieldEntry {
             path: display_path,
+            jq_path: if jq_path.is_empty() {
+                ".".to_string()
+            } else {
+                jq_path.clone()
+            },
             value: value.clone(),
         });
         match value {
@@ -66,7 +85,7 @@ pub fn collect_fields(value: &Value) -> Vec<FieldEntry> {
                     } else {
                         format!("{path}.{key}")
                     };
-                    walk(v, next, out);
+                    walk(v, next, jq_child(&jq_path, key), out);
                 }
             }
             Value::Array(arr) => {
@@ -76,7 +95,12 @@ pub fn collect_fields(value: &Value) -> Vec<FieldEntry> {
                     } else {
                         format!("{path}[{idx}]")
                     };
-                    walk(v, next, out);
+                    let jq_next = if jq_path.is_empty() {
+                        format!(".[{idx}]")
+                    } else {
+                        format!("{jq_path}[{idx}]")
+                    };
+                    walk(v, next, jq_next, out);
                 }
             }
             _ => {}
@@ -84,10 +108,25 @@ pub fn collect_fields(value: &Value) -> Vec<FieldEntry> {
     }
 
     let mut out = Vec::new();
-    walk(value, String::new(), &mut out);
+    walk(value, String::new(), String::new(), &mut out);
     out
 }
 
+/// `parent.key` for identifier-like keys, `parent["key"]` otherwise.
+fn jq_child(parent: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let quoted = Value::String(key.to_string());
+    match (plain, parent.is_empty()) {
+        (true, _) => format!("{parent}.{key}"),
+        (false, true) => format!(".[{quoted}]"),
+        (false, false) => format!("{parent}[{quoted}]"),
+    }
+}
+
 pub(crate) fn field_value_for_filter(entry: &FieldEntry) -> String {
     match &entry.value {
         Value::String(s) => s.clone(),
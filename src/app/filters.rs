@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{jq, model::LogEntry};
+
+use super::{clusters::Clusters, correlation::Correlation, levels::LevelFilter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterKind {
+    Include,
+    Exclude,
+}
+
+impl FilterKind {
+    pub fn toggled(self) -> Self {
+        match self {
+            FilterKind::Include => FilterKind::Exclude,
+            FilterKind::Exclude => FilterKind::Include,
+        }
+    }
+}
+
+/// How a typed pattern is turned into a regex; toggled from the `/` prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterModes {
+    /// Case-insensitive unless the pattern contains an uppercase letter.
+    pub smart_case: bool,
+    /// Match the pattern as a fixed string instead of a regex.
+    pub literal: bool,
+    /// Only match at word boundaries.
+    pub whole_word: bool,
+    /// Treat the pattern as a jq expression over the entry's JSON; the other
+    /// modes are ignored.
+    pub jq: bool,
+}
+
+impl FilterModes {
+    /// Compiles `pattern` into a query; errors are ready for the status line.
+    pub fn compile_query(&self, pattern: &str) -> Result<Query, String> {
+        if self.jq {
+            jq::Filter::parse(pattern)
+                .map(|filter| Query::Jq(Arc::new(filter)))
+                .map_err(|err| err.to_string())
+        } else {
+            self.compile(pattern)
+                .map(Query::Regex)
+                .map_err(|err| err.to_string())
+        }
+    }
+
+    pub fn compile(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let mut source = if self.literal {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        if self.whole_word {
+            source = format!(r"\b(?:{source})\b");
+        }
+        RegexBuilder::new(&source)
+            .case_insensitive(self.ignores_case(pattern))
+            .build()
+    }
+
+    fn ignores_case(&self, pattern: &str) -> bool {
+        self.smart_case && !pattern.chars().any(char::is_uppercase)
+    }
+
+    /// Names of the enabled modes, e.g. `["smart-case", "literal"]`.
+    pub fn labels(&self) -> Vec<&'static str> {
+        if self.jq {
+            return vec!["jq"];
+        }
+        let mut labels = Vec::new();
+        if self.smart_case {
+            labels.push("smart-case");
+        }
+        if self.literal {
+            labels.push("literal");
+        }
+        if self.whole_word {
+            labels.push("word");
+        }
+        labels
+    }
+}
+
+/// A compiled filter: a regex over the entry's search text, or a jq
+/// expression over its raw JSON.
+#[derive(Clone, Debug)]
+pub enum Query {
+    Regex(Regex),
+    Jq(Arc<jq::Filter>),
+}
+
+impl Query {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.matches_parts(&entry.search_text, &entry.raw)
+    }
+
+    fn matches_parts(&self, hay: &str, raw: &Value) -> bool {
+        match self {
+            Query::Regex(re) => re.is_match(hay),
+            Query::Jq(filter) => filter.matches(raw),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FilterLayer {
+    pub pattern: String,
+    pub query: Query,
+    pub modes: FilterModes,
+    pub kind: FilterKind,
+    pub enabled: bool,
+}
+
+impl FilterLayer {
+    pub fn new(pattern: String, query: Query, modes: FilterModes, kind: FilterKind) -> Self {
+        Self {
+            pattern,
+            query,
+            modes,
+            kind,
+            enabled: true,
+        }
+    }
+
+    /// Whether `entry` passes this layer. Disabled layers let everything through.
+    pub fn allows(&self, entry: &LogEntry) -> bool {
+        !self.enabled || self.applies(self.query.matches(entry))
+    }
+
+    fn allows_parts(&self, hay: &str, raw: &Value) -> bool {
+        !self.enabled || self.applies(self.query.matches_parts(hay, raw))
+    }
+
+    fn applies(&self, matched: bool) -> bool {
+        match self.kind {
+            FilterKind::Include => matched,
+            FilterKind::Exclude => !matched,
+        }
+    }
+}
+
+/// Evaluates the main pattern and then the stack top to bottom against an
+/// entry's search text and raw JSON; an entry must pass every enabled layer.
+/// Levels, correlation and templates are not checked (see
+/// `EntryFilter::runs_on_snapshot`).
+pub fn snapshot_matches(
+    main: Option<&Query>,
+    layers: &[FilterLayer],
+    hay: &str,
+    raw: &Value,
+) -> bool {
+    if let Some(query) = main
+        && !query.matches_parts(hay, raw)
+    {
+        return false;
+    }
+    layers.iter().all(|layer| layer.allows_parts(hay, raw))
+}
+
+/// A borrowed, thread-safe view of every active predicate, so rebuilds can be
+/// evaluated with rayon without sharing the whole `App`.
+#[derive(Clone, Copy)]
+pub struct EntryFilter<'a> {
+    pub main: Option<&'a Query>,
+    pub layers: &'a [FilterLayer],
+    pub levels: &'a LevelFilter,
+    /// The id being followed, if any.
+    pub correlation: Option<&'a Correlation>,
+    /// Only the entries filed under this cluster.
+    pub cluster: Option<(&'a Clusters, usize)>,
+}
+
+impl EntryFilter<'_> {
+    /// Whether `entry`, at buffer index `idx`, passes.
+    pub fn matches(&self, idx: usize, entry: &LogEntry) -> bool {
+        self.levels.allows(&entry.level)
+            && self.main.is_none_or(|query| query.matches(entry))
+            && self.layers.iter().all(|layer| layer.allows(entry))
+            && self.correlation.is_none_or(|c| c.matches(entry))
+            && self
+                .cluster
+                .is_none_or(|(clusters, id)| clusters.cluster_of(idx) == Some(id))
+    }
+
+    /// Whether everything but the levels can be evaluated from a snapshot of
+    /// the search texts and raw JSON (see `snapshot_matches`).
+    pub fn runs_on_snapshot(&self) -> bool {
+        self.correlation.is_none() && self.cluster.is_none()
+    }
+}
+
+/// Whether every text matching `new` also matches `old` (both compiled with
+/// `modes`), so the previous result can be refined instead of rescanning the
+/// buffer. Only plain literals can be compared; anything with regex syntax, or
+/// word boundaries, is assumed to widen; so is any jq expression.
+pub fn is_narrowing(old: Option<&str>, new: &str, modes: FilterModes) -> bool {
+    let Some(old) = old.filter(|p| !p.is_empty()) else {
+        return true;
+    };
+    if modes.jq {
+        return false;
+    }
+    let comparable = modes.literal || (is_literal(old) && is_literal(new));
+    if modes.whole_word || !comparable {
+        return false;
+    }
+    if modes.ignores_case(old) {
+        new.to_lowercase().contains(old)
+    } else {
+        new.contains(old)
+    }
+}
+
+fn is_literal(pattern: &str) -> bool {
+    regex::escape(pattern) == pattern
+}
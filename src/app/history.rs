@@ -0,0 +1,143 @@
+use std::{env, fs, io, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::filters::FilterModes;
+
+const MAX_HISTORY: usize = 200;
+const STATE_FILE: &str = "state.json";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub name: String,
+    pub pattern: String,
+    /// The modes the pattern was compiled with when it was saved.
+    #[serde(default)]
+    pub modes: FilterModes,
+}
+
+/// A pattern from the `/` prompt with the modes it was typed under.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredEntry")]
+pub struct HistoryEntry {
+    pub pattern: String,
+    pub modes: FilterModes,
+}
+
+/// State files written before modes were recorded hold bare patterns.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Pattern(String),
+    Entry {
+        pattern: String,
+        #[serde(default)]
+        modes: FilterModes,
+    },
+}
+
+impl From<StoredEntry> for HistoryEntry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Pattern(pattern) => Self {
+                pattern,
+                modes: FilterModes::default(),
+            },
+            StoredEntry::Entry { pattern, modes } => Self { pattern, modes },
+        }
+    }
+}
+
+/// Filter patterns remembered between sessions. Without a backing path (as in
+/// tests) everything stays in memory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FilterHistory {
+    #[serde(default)]
+    pub entries: Vec<HistoryEntry>,
+    #[serde(default)]
+    pub saved: Vec<SavedFilter>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl FilterHistory {
+    pub fn load_default() -> Result<Self> {
+        match state_dir() {
+            Some(dir) => Self::load(dir.join(STATE_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the state file at `path`; a missing file starts empty. A file
+    /// that can't be read or parsed is an error rather than an empty history,
+    /// which the next save would write over.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let mut history = match fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str::<FilterHistory>(&s)
+                .with_context(|| format!("parsing {path:?}"))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err).with_context(|| format!("reading {path:?}")),
+        };
+        history.path = Some(path);
+        Ok(history)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("creating {parent:?}"))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents).with_context(|| format!("writing {path:?}"))
+    }
+
+    /// Appends `pattern` as the newest entry, dropping an older duplicate.
+    pub fn record(&mut self, pattern: &str, modes: FilterModes) {
+        if pattern.is_empty() {
+            return;
+        }
+        self.entries
+            .retain(|e| e.pattern != pattern || e.modes != modes);
+        self.entries.push(HistoryEntry {
+            pattern: pattern.to_string(),
+            modes,
+        });
+        if self.entries.len() > MAX_HISTORY {
+            let excess = self.entries.len() - MAX_HISTORY;
+            self.entries.drain(..excess);
+        }
+    }
+
+    pub fn save_named(&mut self, name: &str, pattern: &str, modes: FilterModes) {
+        if let Some(existing) = self.saved.iter_mut().find(|f| f.name == name) {
+            existing.pattern = pattern.to_string();
+            existing.modes = modes;
+        } else {
+            self.saved.push(SavedFilter {
+                name: name.to_string(),
+                pattern: pattern.to_string(),
+                modes,
+            });
+        }
+    }
+
+    /// Newest history index below `before` whose pattern contains `query`.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let end = before.min(self.entries.len());
+        self.entries[..end]
+            .iter()
+            .rposition(|e| e.pattern.contains(query))
+    }
+}
+
+/// `$XDG_STATE_HOME/logtui`, falling back to `~/.local/state/logtui`.
+pub(crate) fn state_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(base.join("logtui"))
+}
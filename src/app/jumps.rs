@@ -0,0 +1,51 @@
+//! The vim-style jump list behind `Ctrl+O` and `Ctrl+I`.
+
+/// Vim keeps a hundred jumps; so do we.
+const MAX_JUMPS: usize = 100;
+
+/// Entries the selection jumped away from, oldest first. Entries are held by
+/// absolute sequence number (evicted entries plus buffer index), so filter
+/// changes, sorting and eviction do not shift them.
+#[derive(Clone, Debug, Default)]
+pub struct JumpList {
+    seqs: Vec<u64>,
+    /// Position while moving through the list; `seqs.len()` when not.
+    cursor: usize,
+}
+
+impl JumpList {
+    /// Records `seq` as the place a jump leaves. Jumps ahead of the cursor
+    /// are dropped, as in a browser history.
+    pub fn record(&mut self, seq: u64) {
+        self.seqs.truncate(self.cursor);
+        if self.seqs.last() != Some(&seq) {
+            self.seqs.push(seq);
+        }
+        if self.seqs.len() > MAX_JUMPS {
+            self.seqs.remove(0);
+        }
+        self.cursor = self.seqs.len();
+    }
+
+    /// The entry before the cursor that `usable` accepts, leaving `current`.
+    pub fn back(&mut self, current: u64, usable: impl Fn(u64) -> bool) -> Option<u64> {
+        if self.cursor == self.seqs.len() {
+            // Remember where we came from so Ctrl+I can return to it.
+            self.record(current);
+            self.cursor = self.seqs.len() - 1;
+        }
+        let pos = (0..self.cursor)
+            .rev()
+            .find(|&pos| self.seqs[pos] != current && usable(self.seqs[pos]))?;
+        self.cursor = pos;
+        Some(self.seqs[pos])
+    }
+
+    /// The entry after the cursor that `usable` accepts, leaving `current`.
+    pub fn forward(&mut self, current: u64, usable: impl Fn(u64) -> bool) -> Option<u64> {
+        let pos = (self.cursor + 1..self.seqs.len())
+            .find(|&pos| self.seqs[pos] != current && usable(self.seqs[pos]))?;
+        self.cursor = pos;
+        Some(self.seqs[pos])
+    }
+}
@@ -0,0 +1,1025 @@
+//! What each key does, per mode. The key handlers look keys up here and run
+//! the resulting actions, and the help overlay is generated from it, so
+//! rebinding a key in the config file changes both.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::keys::KeyChord;
+
+/// The modes with their own bindings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyMode {
+    /// The list and detail panes.
+    Normal,
+    /// The `/` prompt; the column and command prompts use the same keys.
+    FilterInput,
+    ColumnSelect,
+    FieldView,
+    LevelSelect,
+    FieldFilter,
+    SavedFilters,
+    FilterStack,
+    /// The action palette; other characters are typed into its query.
+    Palette,
+    Bookmarks,
+    TraceView,
+    Clusters,
+}
+
+impl KeyMode {
+    pub const ALL: [KeyMode; 12] = [
+        KeyMode::Normal,
+        KeyMode::FilterInput,
+        KeyMode::ColumnSelect,
+        KeyMode::FieldView,
+        KeyMode::LevelSelect,
+        KeyMode::FieldFilter,
+        KeyMode::SavedFilters,
+        KeyMode::FilterStack,
+        KeyMode::Palette,
+        KeyMode::Bookmarks,
+        KeyMode::TraceView,
+        KeyMode::Clusters,
+    ];
+
+    /// Name of the mode's table in the config file (`[keys.<name>]`).
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyMode::Normal => "normal",
+            KeyMode::FilterInput => "filter_input",
+            KeyMode::ColumnSelect => "column_select",
+            KeyMode::FieldView => "field_view",
+            KeyMode::LevelSelect => "level_select",
+            KeyMode::FieldFilter => "field_filter",
+            KeyMode::SavedFilters => "saved_filters",
+            KeyMode::FilterStack => "filter_stack",
+            KeyMode::Palette => "palette",
+            KeyMode::Bookmarks => "bookmarks",
+            KeyMode::TraceView => "trace_view",
+            KeyMode::Clusters => "clusters",
+        }
+    }
+
+    /// Heading in the help overlay.
+    pub fn title(self) -> &'static str {
+        match self {
+            KeyMode::Normal => "List and detail",
+            KeyMode::FilterInput => "Filter prompt",
+            KeyMode::ColumnSelect => "Column selector",
+            KeyMode::FieldView => "Field viewer",
+            KeyMode::LevelSelect => "Level checklist",
+            KeyMode::FieldFilter => "Field filter picker",
+            KeyMode::SavedFilters => "Saved filters",
+            KeyMode::FilterStack => "Filter stack",
+            KeyMode::Palette => "Action palette",
+            KeyMode::Bookmarks => "Bookmarks",
+            KeyMode::TraceView => "Trace view",
+            KeyMode::Clusters => "Templates",
+        }
+    }
+
+    /// The actions that mean something in this mode, in help order.
+    pub fn actions(self) -> &'static [Action] {
+        use Action::*;
+        match self {
+            KeyMode::Normal => &[
+                Quit,
+                Help,
+                Down,
+                Up,
+                HalfPageDown,
+                HalfPageUp,
+                Top,
+                Bottom,
+                ScrollLeft,
+                ScrollRight,
+                LineStart,
+                LineEnd,
+                NextEntry,
+                PrevEntry,
+                FocusDetail,
+                FocusList,
+                ToggleFocus,
+                Zoom,
+                Wrap,
+                Filter,
+                SavedFilters,
+                FilterStack,
+                IncludeField,
+                ExcludeField,
+                CycleMinLevel,
+                LevelSelect,
+                Columns,
+                FieldView,
+                TimestampMode,
+                TimeReference,
+                Bookmark,
+                NextBookmark,
+                PrevBookmark,
+                Bookmarks,
+                SetMark,
+                JumpToMark,
+                JumpBack,
+                JumpForward,
+                FollowCorrelation,
+                CorrelationBack,
+                TraceView,
+                Dedupe,
+                ToggleRepeats,
+                Clusters,
+                ClearTemplate,
+                Note,
+                Autoscroll,
+                Pause,
+                SaveSession,
+                Export,
+                Yank,
+                YankRange,
+                OpenEditor,
+                Redraw,
+                CommandLine,
+                Palette,
+            ],
+            KeyMode::FilterInput => &[
+                Close,
+                Submit,
+                HistoryPrev,
+                HistoryNext,
+                HistorySearch,
+                SavedFilters,
+                DeleteChar,
+                ClearInput,
+                Complete,
+                ToggleSmartCase,
+                ToggleLiteral,
+                ToggleWholeWord,
+                ToggleJq,
+                NextEntry,
+                PrevEntry,
+                FieldView,
+                Zoom,
+                OpenEditor,
+            ],
+            KeyMode::ColumnSelect => &[
+                Quit, Help, Close, Down, Up, Top, Bottom, Toggle, MoveDown, MoveUp, AddColumn,
+                Remove, CycleWidth, Narrower, Wider, Pin, CycleSort, NextEntry, PrevEntry,
+                FieldView, Zoom, OpenEditor,
+            ],
+            KeyMode::FieldView => &[
+                Quit,
+                Close,
+                FieldView,
+                Down,
+                Up,
+                HalfPageDown,
+                HalfPageUp,
+                ScrollLeft,
+                ScrollRight,
+                LineStart,
+                LineEnd,
+                Wrap,
+                Zoom,
+                Filter,
+                IncludeField,
+                ExcludeField,
+                DeleteChar,
+                ClearInput,
+                Yank,
+                YankPath,
+                OpenEditor,
+            ],
+            KeyMode::LevelSelect => &[Close, Down, Up, Toggle, CycleMinLevel, ResetLevels],
+            KeyMode::FieldFilter => &[Close, Down, Up, Submit, IncludeField, ExcludeField],
+            KeyMode::SavedFilters => &[Close, Down, Up, Submit, Remove],
+            KeyMode::FilterStack => &[
+                Help, Close, Down, Up, Toggle, AddInclude, AddExclude, FlipKind, Remove, MoveDown,
+                MoveUp,
+            ],
+            KeyMode::Palette => &[Close, Down, Up, Submit, DeleteChar],
+            KeyMode::Bookmarks => &[Close, Down, Up, Submit, Remove],
+            KeyMode::TraceView => &[
+                Close, Down, Up, Top, Bottom, Submit, Toggle, Collapse, Expand,
+            ],
+            KeyMode::Clusters => &[Close, Down, Up, Top, Bottom, Submit],
+        }
+    }
+}
+
+/// Something a key can do. What it acts on depends on the mode and, in
+/// normal mode, on the focused pane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    Down,
+    Up,
+    HalfPageDown,
+    HalfPageUp,
+    Top,
+    Bottom,
+    ScrollLeft,
+    ScrollRight,
+    LineStart,
+    LineEnd,
+    NextEntry,
+    PrevEntry,
+    FocusDetail,
+    FocusList,
+    ToggleFocus,
+    Zoom,
+    Wrap,
+    Filter,
+    SavedFilters,
+    FilterStack,
+    IncludeField,
+    ExcludeField,
+    CycleMinLevel,
+    LevelSelect,
+    Columns,
+    FieldView,
+    TimestampMode,
+    TimeReference,
+    Bookmark,
+    NextBookmark,
+    PrevBookmark,
+    Bookmarks,
+    SetMark,
+    JumpToMark,
+    JumpBack,
+    JumpForward,
+    FollowCorrelation,
+    CorrelationBack,
+    TraceView,
+    Dedupe,
+    ToggleRepeats,
+    Clusters,
+    ClearTemplate,
+    Note,
+    Autoscroll,
+    Pause,
+    SaveSession,
+    Export,
+    Yank,
+    YankRange,
+    YankPath,
+    OpenEditor,
+    Redraw,
+    CommandLine,
+    Palette,
+    Close,
+    Submit,
+    HistoryPrev,
+    HistoryNext,
+    HistorySearch,
+    DeleteChar,
+    ClearInput,
+    Complete,
+    ToggleSmartCase,
+    ToggleLiteral,
+    ToggleWholeWord,
+    ToggleJq,
+    Toggle,
+    MoveDown,
+    MoveUp,
+    AddColumn,
+    Remove,
+    CycleWidth,
+    Narrower,
+    Wider,
+    Pin,
+    CycleSort,
+    ResetLevels,
+    AddInclude,
+    AddExclude,
+    FlipKind,
+    Collapse,
+    Expand,
+}
+
+impl Action {
+    pub const ALL: [Action; 85] = {
+        use Action::*;
+        [
+            Quit,
+            Help,
+            Down,
+            Up,
+            HalfPageDown,
+            HalfPageUp,
+            Top,
+            Bottom,
+            ScrollLeft,
+            ScrollRight,
+            LineStart,
+            LineEnd,
+            NextEntry,
+            PrevEntry,
+            FocusDetail,
+            FocusList,
+            ToggleFocus,
+            Zoom,
+            Wrap,
+            Filter,
+            SavedFilters,
+            FilterStack,
+            IncludeField,
+            ExcludeField,
+            CycleMinLevel,
+            LevelSelect,
+            Columns,
+            FieldView,
+            TimestampMode,
+            TimeReference,
+            Bookmark,
+            NextBookmark,
+            PrevBookmark,
+            Bookmarks,
+            SetMark,
+            JumpToMark,
+            JumpBack,
+            JumpForward,
+            FollowCorrelation,
+            CorrelationBack,
+            TraceView,
+            Dedupe,
+            ToggleRepeats,
+            Clusters,
+            ClearTemplate,
+            Note,
+            Autoscroll,
+            Pause,
+            SaveSession,
+            Export,
+            Yank,
+            YankRange,
+            YankPath,
+            OpenEditor,
+            Redraw,
+            CommandLine,
+            Palette,
+            Close,
+            Submit,
+            HistoryPrev,
+            HistoryNext,
+            HistorySearch,
+            DeleteChar,
+            ClearInput,
+            Complete,
+            ToggleSmartCase,
+            ToggleLiteral,
+            ToggleWholeWord,
+            ToggleJq,
+            Toggle,
+            MoveDown,
+            MoveUp,
+            AddColumn,
+            Remove,
+            CycleWidth,
+            Narrower,
+            Wider,
+            Pin,
+            CycleSort,
+            ResetLevels,
+            AddInclude,
+            AddExclude,
+            FlipKind,
+            Collapse,
+            Expand,
+        ]
+    };
+
+    /// Name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::LineStart => "line_start",
+            Action::LineEnd => "line_end",
+            Action::NextEntry => "next_entry",
+            Action::PrevEntry => "prev_entry",
+            Action::FocusDetail => "focus_detail",
+            Action::FocusList => "focus_list",
+            Action::ToggleFocus => "toggle_focus",
+            Action::Zoom => "zoom",
+            Action::Wrap => "wrap",
+            Action::Filter => "filter",
+            Action::SavedFilters => "saved_filters",
+            Action::FilterStack => "filter_stack",
+            Action::IncludeField => "include_field",
+            Action::ExcludeField => "exclude_field",
+            Action::CycleMinLevel => "cycle_min_level",
+            Action::LevelSelect => "level_select",
+            Action::Columns => "columns",
+            Action::FieldView => "field_view",
+            Action::TimestampMode => "timestamp_mode",
+            Action::TimeReference => "time_reference",
+            Action::Bookmark => "bookmark",
+            Action::NextBookmark => "next_bookmark",
+            Action::PrevBookmark => "prev_bookmark",
+            Action::Bookmarks => "bookmarks",
+            Action::SetMark => "set_mark",
+            Action::JumpToMark => "jump_to_mark",
+            Action::JumpBack => "jump_back",
+            Action::JumpForward => "jump_forward",
+            Action::FollowCorrelation => "follow_correlation",
+            Action::CorrelationBack => "correlation_back",
+            Action::TraceView => "trace_view",
+            Action::Dedupe => "dedupe",
+            Action::ToggleRepeats => "toggle_repeats",
+            Action::Clusters => "clusters",
+            Action::ClearTemplate => "clear_template",
+            Action::Note => "note",
+            Action::Autoscroll => "autoscroll",
+            Action::Pause => "pause",
+            Action::SaveSession => "save_session",
+            Action::Export => "export",
+            Action::Yank => "yank",
+            Action::YankRange => "yank_range",
+            Action::YankPath => "yank_path",
+            Action::OpenEditor => "open_editor",
+            Action::Redraw => "redraw",
+            Action::CommandLine => "command_line",
+            Action::Palette => "palette",
+            Action::Close => "close",
+            Action::Submit => "submit",
+            Action::HistoryPrev => "history_prev",
+            Action::HistoryNext => "history_next",
+            Action::HistorySearch => "history_search",
+            Action::DeleteChar => "delete_char",
+            Action::ClearInput => "clear_input",
+            Action::Complete => "complete",
+            Action::ToggleSmartCase => "toggle_smart_case",
+            Action::ToggleLiteral => "toggle_literal",
+            Action::ToggleWholeWord => "toggle_whole_word",
+            Action::ToggleJq => "toggle_jq",
+            Action::Toggle => "toggle",
+            Action::MoveDown => "move_down",
+            Action::MoveUp => "move_up",
+            Action::AddColumn => "add_column",
+            Action::Remove => "remove",
+            Action::CycleWidth => "cycle_width",
+            Action::Narrower => "narrower",
+            Action::Wider => "wider",
+            Action::Pin => "pin",
+            Action::CycleSort => "cycle_sort",
+            Action::ResetLevels => "reset_levels",
+            Action::AddInclude => "add_include",
+            Action::AddExclude => "add_exclude",
+            Action::FlipKind => "flip_kind",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+        }
+    }
+
+    /// Help text for the action in `mode`.
+    pub fn description(self, mode: KeyMode) -> &'static str {
+        match (mode, self) {
+            (KeyMode::FieldView, Action::Down) => "Next field",
+            (KeyMode::FieldView, Action::Up) => "Previous field",
+            (KeyMode::FieldView, Action::HalfPageDown) => {
+                "Half-page down (list) or scroll detail when zoomed"
+            }
+            (KeyMode::FieldView, Action::HalfPageUp) => {
+                "Half-page up (list) or scroll detail when zoomed"
+            }
+            (KeyMode::FieldView, Action::Close | Action::FieldView) => "Close the field viewer",
+            (KeyMode::FieldView, Action::Filter) => "Close and prefill / filter with the value",
+            (KeyMode::FieldView, Action::IncludeField) => {
+                "Show only entries where the selected field has this value"
+            }
+            (KeyMode::FieldView, Action::ExcludeField) => {
+                "Hide entries where the selected field has this value"
+            }
+            (KeyMode::FieldView, Action::OpenEditor) => "Open selected field in $EDITOR",
+            (KeyMode::FieldView, Action::Yank) => "Copy the field value to the clipboard",
+            (KeyMode::FieldView, Action::Zoom) => "Zoom the field detail",
+            (KeyMode::FieldView, Action::ClearInput) => "Clear the field filter",
+            (KeyMode::FieldView, Action::DeleteChar) => "Delete the last filter character",
+            (KeyMode::ColumnSelect, Action::Down) => "Next column",
+            (KeyMode::ColumnSelect, Action::Up) => "Previous column",
+            (KeyMode::ColumnSelect, Action::Top) => "First column",
+            (KeyMode::ColumnSelect, Action::Bottom) => "Last column",
+            (KeyMode::ColumnSelect, Action::Close) => "Close the column selector",
+            (KeyMode::FilterInput, Action::Close) => "Cancel (restores the previous filter)",
+            (KeyMode::LevelSelect, Action::Down) => "Next level",
+            (KeyMode::LevelSelect, Action::Up) => "Previous level",
+            (KeyMode::LevelSelect, Action::Toggle) => "Show/hide level",
+            (KeyMode::LevelSelect, Action::Close) => "Close the level checklist",
+            (KeyMode::FieldFilter, Action::Down) => "Next field",
+            (KeyMode::FieldFilter, Action::Up) => "Previous field",
+            (KeyMode::FieldFilter, Action::Submit) => "Show only or hide the value, as opened",
+            (KeyMode::FieldFilter, Action::IncludeField) => "Show only entries with this value",
+            (KeyMode::FieldFilter, Action::ExcludeField) => "Hide entries with this value",
+            (KeyMode::FieldFilter, Action::Close) => "Close the field picker",
+            (KeyMode::SavedFilters, Action::Down) => "Next saved filter",
+            (KeyMode::SavedFilters, Action::Up) => "Previous saved filter",
+            (KeyMode::SavedFilters, Action::Submit) => "Apply the saved filter",
+            (KeyMode::SavedFilters, Action::Remove) => "Delete the saved filter",
+            (KeyMode::SavedFilters, Action::Close) => "Close saved filters",
+            (KeyMode::FilterStack, Action::Down) => "Next layer",
+            (KeyMode::FilterStack, Action::Up) => "Previous layer",
+            (KeyMode::FilterStack, Action::Toggle) => "Toggle layer on/off",
+            (KeyMode::FilterStack, Action::Remove) => "Remove layer",
+            (KeyMode::FilterStack, Action::MoveDown) => "Move layer down",
+            (KeyMode::FilterStack, Action::MoveUp) => "Move layer up",
+            (KeyMode::FilterStack, Action::Close) => "Close the filter stack",
+            (KeyMode::Palette, Action::Down) => "Next action",
+            (KeyMode::Palette, Action::Up) => "Previous action",
+            (KeyMode::Palette, Action::Submit) => "Run the action",
+            (KeyMode::Palette, Action::DeleteChar) => "Delete the last query character",
+            (KeyMode::Palette, Action::Close) => "Close the palette",
+            (KeyMode::Bookmarks, Action::Down) => "Next bookmark or mark",
+            (KeyMode::Bookmarks, Action::Up) => "Previous bookmark or mark",
+            (KeyMode::Bookmarks, Action::Submit) => "Jump to the entry",
+            (KeyMode::Bookmarks, Action::Remove) => "Remove the bookmark or mark",
+            (KeyMode::Bookmarks, Action::Close) => "Close the bookmark list",
+            (KeyMode::TraceView, Action::Down) => "Next row",
+            (KeyMode::TraceView, Action::Up) => "Previous row",
+            (KeyMode::TraceView, Action::Top) => "First row",
+            (KeyMode::TraceView, Action::Bottom) => "Last row",
+            (KeyMode::TraceView, Action::Submit) => {
+                "Fold or unfold the span, or show the entry in the list"
+            }
+            (KeyMode::TraceView, Action::Toggle) => "Fold or unfold the span",
+            (KeyMode::TraceView, Action::Close) => "Close the trace view",
+            (KeyMode::Clusters, Action::Down) => "Next template",
+            (KeyMode::Clusters, Action::Up) => "Previous template",
+            (KeyMode::Clusters, Action::Top) => "First template",
+            (KeyMode::Clusters, Action::Bottom) => "Last template",
+            (KeyMode::Clusters, Action::Submit) => "Show only the template's entries",
+            (KeyMode::Clusters, Action::Close) => "Close the template list",
+            (_, action) => action.default_description(),
+        }
+    }
+
+    fn default_description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Toggle help",
+            Action::Down => "Move selection / scroll details down",
+            Action::Up => "Move selection / scroll details up",
+            Action::HalfPageDown => "Half-page down",
+            Action::HalfPageUp => "Half-page up",
+            Action::Top => "Jump to top",
+            Action::Bottom => "Jump to bottom",
+            Action::ScrollLeft => "Pan left (wrap off)",
+            Action::ScrollRight => "Pan right (wrap off)",
+            Action::LineStart => "Jump to start horizontally",
+            Action::LineEnd => "Jump to end horizontally",
+            Action::NextEntry => "Next log (any pane)",
+            Action::PrevEntry => "Previous log (any pane)",
+            Action::FocusDetail => "Focus details",
+            Action::FocusList => "Back to list",
+            Action::ToggleFocus => "Switch between list and details",
+            Action::Zoom => "Zoom focused pane",
+            Action::Wrap => "Toggle wrap",
+            Action::Filter => "Filter logs (regex)",
+            Action::SavedFilters => "Open saved filters",
+            Action::FilterStack => "Open filter stack (include/exclude layers)",
+            Action::IncludeField => "Pick a field to show only its current value",
+            Action::ExcludeField => "Pick a field to hide its current value",
+            Action::CycleMinLevel => "Cycle minimum severity (DEBUG → … → CRITICAL → off)",
+            Action::LevelSelect => "Open level checklist",
+            Action::Columns => "Open column selector",
+            Action::FieldView => "Open field viewer",
+            Action::TimestampMode => {
+                "Cycle timestamp format (original/local/UTC/time/relative/delta)"
+            }
+            Action::TimeReference => "Mark/unmark entry as delta reference",
+            Action::Bookmark => "Bookmark/unbookmark the entry",
+            Action::NextBookmark => "Next bookmark",
+            Action::PrevBookmark => "Previous bookmark",
+            Action::Bookmarks => "List bookmarks and named marks",
+            Action::SetMark => "Set a named mark (then a letter)",
+            Action::JumpToMark => "Jump to a named mark (then a letter)",
+            Action::JumpBack => "Back to the entry selected before the last jump",
+            Action::JumpForward => "Forward again after Ctrl+O (Ctrl+I, which arrives as Tab)",
+            Action::FollowCorrelation => "Show every entry sharing the entry's trace/request id",
+            Action::CorrelationBack => "Back to the view before following the id",
+            Action::TraceView => "Show the entry's trace as a span tree",
+            Action::Dedupe => "Fold runs of repeated messages into one row",
+            Action::ToggleRepeats => "Expand or fold the repeats of the selected row",
+            Action::Clusters => "List the message templates with their counts",
+            Action::ClearTemplate => "Stop showing only one template's entries",
+            Action::Note => "Add or edit a note on the entry",
+            Action::Autoscroll => "Toggle autoscroll",
+            Action::Pause => "Toggle input stream (pause/resume)",
+            Action::SaveSession => "Save session (--session file or ./logtui-session.json)",
+            Action::Export => "Export the filtered entries to a file (:export)",
+            Action::Yank => "Copy the entry JSON to the clipboard",
+            Action::YankRange => "Copy the bookmarked and marked entries, one line each",
+            Action::YankPath => "Copy the field's jq path to the clipboard",
+            Action::OpenEditor => "Open entry in $EDITOR",
+            Action::Redraw => "Force redraw",
+            Action::CommandLine => "Command line (:goto, :set, :columns add, ...)",
+            Action::Palette => "Search all actions",
+            Action::Close => "Close",
+            Action::Submit => "Apply",
+            Action::HistoryPrev => "Previous filter from history",
+            Action::HistoryNext => "Next filter from history",
+            Action::HistorySearch => "Reverse search filter history",
+            Action::DeleteChar => "Delete the last character",
+            Action::ClearInput => "Clear the input",
+            Action::Complete => "Complete the command (: prompt)",
+            Action::ToggleSmartCase => "Toggle smart-case",
+            Action::ToggleLiteral => "Toggle literal (fixed-string) matching",
+            Action::ToggleWholeWord => "Toggle whole-word matching",
+            Action::ToggleJq => "Toggle jq expression mode (e.g. select(.data.items | length > 3))",
+            Action::Toggle => "Show/hide column",
+            Action::MoveDown => "Move column down",
+            Action::MoveUp => "Move column up",
+            Action::AddColumn => "Add a computed column from a jq expression",
+            Action::Remove => "Delete the selected computed column",
+            Action::CycleWidth => "Cycle width mode (auto / flex / fixed)",
+            Action::Narrower => "Narrow the column (fixed width)",
+            Action::Wider => "Widen the column (fixed width)",
+            Action::Pin => "Pin the column so it stays visible while panning",
+            Action::CycleSort => "Sort by the column (ascending → descending → arrival order)",
+            Action::ResetLevels => "Reset level filters",
+            Action::AddInclude => "Add an include layer",
+            Action::AddExclude => "Add an exclude layer",
+            Action::FlipKind => "Flip layer between include and exclude",
+            Action::Collapse => "Fold the span (from an entry, the span it is in)",
+            Action::Expand => "Unfold the span",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action '{s}'"))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A key and the actions it runs. Several actions are tried in order until
+/// one applies (`Ctrl+U` clears the field filter, or pages up when empty).
+type Binding = (&'static str, &'static [Action]);
+
+/// Default bindings per mode.
+const DEFAULTS: &[(KeyMode, &[Binding])] = {
+    use Action::*;
+    &[
+        (
+            KeyMode::Normal,
+            &[
+                ("q", &[Quit]),
+                ("?", &[Help]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("ctrl+d", &[HalfPageDown]),
+                ("ctrl+u", &[HalfPageUp]),
+                ("g", &[Top]),
+                ("G", &[Bottom]),
+                ("h", &[ScrollLeft]),
+                ("l", &[ScrollRight]),
+                ("0", &[LineStart]),
+                ("$", &[LineEnd]),
+                ("ctrl+n", &[NextEntry]),
+                ("ctrl+p", &[PrevEntry]),
+                ("enter", &[FocusDetail]),
+                ("right", &[FocusDetail]),
+                ("esc", &[FocusList]),
+                ("left", &[FocusList]),
+                ("tab", &[JumpForward, ToggleFocus]),
+                ("z", &[Zoom]),
+                ("ctrl+z", &[Zoom]),
+                ("w", &[Wrap]),
+                ("/", &[Filter]),
+                ("f", &[SavedFilters]),
+                ("F", &[FilterStack]),
+                ("=", &[IncludeField]),
+                ("!", &[ExcludeField]),
+                ("v", &[CycleMinLevel]),
+                ("V", &[LevelSelect]),
+                ("c", &[Columns]),
+                ("ctrl+t", &[FieldView]),
+                ("t", &[TimestampMode]),
+                ("T", &[TimeReference]),
+                ("b", &[Bookmark]),
+                ("]", &[NextBookmark]),
+                ("[", &[PrevBookmark]),
+                ("B", &[Bookmarks]),
+                ("m", &[SetMark]),
+                ("'", &[JumpToMark]),
+                ("ctrl+o", &[JumpBack]),
+                ("*", &[FollowCorrelation]),
+                ("backspace", &[CorrelationBack, ClearTemplate]),
+                ("W", &[TraceView]),
+                ("D", &[Dedupe]),
+                ("space", &[ToggleRepeats]),
+                ("C", &[Clusters]),
+                ("n", &[Note]),
+                ("a", &[Autoscroll]),
+                ("s", &[Pause]),
+                ("S", &[SaveSession]),
+                ("E", &[Export]),
+                ("y", &[Yank]),
+                ("Y", &[YankRange]),
+                ("ctrl+e", &[OpenEditor]),
+                ("ctrl+l", &[Redraw]),
+                (":", &[CommandLine]),
+                ("alt+x", &[Palette]),
+            ],
+        ),
+        (
+            KeyMode::FilterInput,
+            &[
+                ("esc", &[Close]),
+                ("enter", &[Submit]),
+                ("up", &[HistoryPrev]),
+                ("down", &[HistoryNext]),
+                ("ctrl+r", &[HistorySearch]),
+                ("ctrl+f", &[SavedFilters]),
+                ("backspace", &[DeleteChar]),
+                ("ctrl+u", &[ClearInput]),
+                ("tab", &[Complete]),
+                ("alt+c", &[ToggleSmartCase]),
+                ("alt+l", &[ToggleLiteral]),
+                ("alt+w", &[ToggleWholeWord]),
+                ("alt+j", &[ToggleJq]),
+                ("ctrl+n", &[NextEntry]),
+                ("ctrl+p", &[PrevEntry]),
+                ("ctrl+t", &[FieldView]),
+                ("ctrl+z", &[Zoom]),
+                ("ctrl+e", &[OpenEditor]),
+            ],
+        ),
+        (
+            KeyMode::ColumnSelect,
+            &[
+                ("q", &[Quit]),
+                ("?", &[Help]),
+                ("esc", &[Close]),
+                ("c", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("g", &[Top]),
+                ("G", &[Bottom]),
+                ("space", &[Toggle]),
+                ("enter", &[Toggle]),
+                ("J", &[MoveDown]),
+                ("K", &[MoveUp]),
+                ("+", &[AddColumn]),
+                ("d", &[Remove]),
+                ("delete", &[Remove]),
+                ("w", &[CycleWidth]),
+                ("<", &[Narrower]),
+                (">", &[Wider]),
+                ("p", &[Pin]),
+                ("o", &[CycleSort]),
+                ("ctrl+n", &[NextEntry]),
+                ("ctrl+p", &[PrevEntry]),
+                ("ctrl+t", &[FieldView]),
+                ("z", &[Zoom]),
+                ("ctrl+z", &[Zoom]),
+                ("ctrl+e", &[OpenEditor]),
+            ],
+        ),
+        (
+            KeyMode::FieldView,
+            &[
+                ("q", &[Quit]),
+                ("esc", &[Close]),
+                ("ctrl+t", &[FieldView]),
+                ("down", &[Down]),
+                ("ctrl+j", &[Down]),
+                ("ctrl+n", &[Down]),
+                ("up", &[Up]),
+                ("ctrl+k", &[Up]),
+                ("ctrl+p", &[Up]),
+                ("ctrl+d", &[HalfPageDown]),
+                ("ctrl+u", &[ClearInput, HalfPageUp]),
+                ("h", &[ScrollLeft]),
+                ("l", &[ScrollRight]),
+                ("0", &[LineStart]),
+                ("$", &[LineEnd]),
+                ("w", &[Wrap]),
+                ("ctrl+z", &[Zoom]),
+                ("/", &[Filter]),
+                ("=", &[IncludeField]),
+                ("!", &[ExcludeField]),
+                ("backspace", &[DeleteChar]),
+                ("ctrl+y", &[Yank]),
+                ("alt+y", &[YankPath]),
+                ("ctrl+e", &[OpenEditor]),
+            ],
+        ),
+        (
+            KeyMode::LevelSelect,
+            &[
+                ("esc", &[Close]),
+                ("V", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("space", &[Toggle]),
+                ("enter", &[Toggle]),
+                ("v", &[CycleMinLevel]),
+                ("r", &[ResetLevels]),
+            ],
+        ),
+        (
+            KeyMode::FieldFilter,
+            &[
+                ("esc", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("enter", &[Submit]),
+                ("=", &[IncludeField]),
+                ("!", &[ExcludeField]),
+                ("x", &[ExcludeField]),
+            ],
+        ),
+        (
+            KeyMode::SavedFilters,
+            &[
+                ("esc", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("enter", &[Submit]),
+                ("d", &[Remove]),
+                ("delete", &[Remove]),
+            ],
+        ),
+        (
+            KeyMode::FilterStack,
+            &[
+                ("?", &[Help]),
+                ("esc", &[Close]),
+                ("F", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("space", &[Toggle]),
+                ("enter", &[Toggle]),
+                ("i", &[AddInclude]),
+                ("x", &[AddExclude]),
+                ("t", &[FlipKind]),
+                ("d", &[Remove]),
+                ("delete", &[Remove]),
+                ("J", &[MoveDown]),
+                ("K", &[MoveUp]),
+            ],
+        ),
+        (
+            KeyMode::Palette,
+            &[
+                ("esc", &[Close]),
+                ("enter", &[Submit]),
+                ("down", &[Down]),
+                ("ctrl+n", &[Down]),
+                ("up", &[Up]),
+                ("ctrl+p", &[Up]),
+                ("backspace", &[DeleteChar]),
+            ],
+        ),
+        (
+            KeyMode::Bookmarks,
+            &[
+                ("esc", &[Close]),
+                ("B", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("enter", &[Submit]),
+                ("d", &[Remove]),
+                ("delete", &[Remove]),
+            ],
+        ),
+        (
+            KeyMode::TraceView,
+            &[
+                ("esc", &[Close]),
+                ("W", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("g", &[Top]),
+                ("G", &[Bottom]),
+                ("enter", &[Submit]),
+                ("space", &[Toggle]),
+                ("h", &[Collapse]),
+                ("left", &[Collapse]),
+                ("l", &[Expand]),
+                ("right", &[Expand]),
+            ],
+        ),
+        (
+            KeyMode::Clusters,
+            &[
+                ("esc", &[Close]),
+                ("C", &[Close]),
+                ("j", &[Down]),
+                ("down", &[Down]),
+                ("k", &[Up]),
+                ("up", &[Up]),
+                ("g", &[Top]),
+                ("G", &[Bottom]),
+                ("enter", &[Submit]),
+            ],
+        ),
+    ]
+};
+
+/// Key bindings for every [`KeyMode`].
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    modes: HashMap<KeyMode, Vec<(KeyChord, Action)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut modes = HashMap::new();
+        for &(mode, bindings) in DEFAULTS {
+            let list: &mut Vec<_> = modes.entry(mode).or_default();
+            for &(key, actions) in bindings {
+                let chord: KeyChord = key.parse().expect("default key binding");
+                list.extend(actions.iter().map(|&action| (chord, action)));
+            }
+        }
+        Self { modes }
+    }
+}
+
+impl Keymap {
+    /// Actions bound to `chord` in `mode`, in the order they are tried.
+    pub fn actions(&self, mode: KeyMode, chord: KeyChord) -> Vec<Action> {
+        self.bindings(mode)
+            .iter()
+            .filter(|(bound, _)| *bound == chord)
+            .map(|&(_, action)| action)
+            .collect()
+    }
+
+    /// Makes `chord` run `actions` in `mode`, replacing what it did before.
+    /// An empty list unbinds the key.
+    pub fn bind(
+        &mut self,
+        mode: KeyMode,
+        chord: KeyChord,
+        actions: &[Action],
+    ) -> Result<(), String> {
+        if let Some(action) = actions.iter().find(|a| !mode.actions().contains(a)) {
+            return Err(format!(
+                "action '{action}' is not available in keys.{}",
+                mode.name()
+            ));
+        }
+        let list = self.modes.entry(mode).or_default();
+        list.retain(|(bound, _)| *bound != chord);
+        list.extend(actions.iter().map(|&action| (chord, action)));
+        Ok(())
+    }
+
+    /// Keys bound to each action of `mode`, in help order. Unbound actions
+    /// are left out.
+    pub fn help(&self, mode: KeyMode) -> Vec<(Action, Vec<KeyChord>)> {
+        mode.actions()
+            .iter()
+            .filter_map(|&action| {
+                let keys: Vec<KeyChord> = self
+                    .bindings(mode)
+                    .iter()
+                    .filter(|(_, bound)| *bound == action)
+                    .map(|&(chord, _)| chord)
+                    .collect();
+                (!keys.is_empty()).then_some((action, keys))
+            })
+            .collect()
+    }
+
+    fn bindings(&self, mode: KeyMode) -> &[(KeyChord, Action)] {
+        self.modes.get(&mode).map(Vec::as_slice).unwrap_or_default()
+    }
+}
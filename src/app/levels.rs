@@ -0,0 +1,96 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Thresholds cycled by `v`, lowest first.
+const THRESHOLDS: [&str; 5] = ["DEBUG", "INFO", "WARN", "ERROR", "CRITICAL"];
+
+const RANKS: [(&str, u8); 8] = [
+    ("TRACE", 0),
+    ("DEBUG", 1),
+    ("INFO", 2),
+    ("WARN", 3),
+    ("WARNING", 3),
+    ("ERROR", 4),
+    ("CRITICAL", 5),
+    ("FATAL", 5),
+];
+
+/// Rank of a level name, or `None` for non-severity levels such as `TEXT` or `PARSE`.
+pub fn severity(level: &str) -> Option<u8> {
+    RANKS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(level))
+        .map(|&(_, rank)| rank)
+}
+
+/// One-key level controls that combine with the text filters. Levels are
+/// compared case-insensitively; entries without a known severity are only
+/// hidden through the checklist, never by the threshold.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelFilter {
+    pub min_severity: Option<u8>,
+    pub hidden: BTreeSet<String>,
+}
+
+impl LevelFilter {
+    pub fn allows(&self, level: &str) -> bool {
+        if let Some(min) = self.min_severity
+            && severity(level).is_some_and(|s| s < min)
+        {
+            return false;
+        }
+        self.hidden.is_empty() || !self.hidden.contains(&level.to_ascii_uppercase())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.min_severity.is_some() || !self.hidden.is_empty()
+    }
+
+    /// Advances the threshold (none → DEBUG → … → CRITICAL → none). Returns
+    /// true when the new threshold can only hide more entries.
+    pub fn cycle_min_severity(&mut self) -> bool {
+        let next = match self.min_severity {
+            None => THRESHOLDS.first().copied(),
+            Some(current) => THRESHOLDS
+                .iter()
+                .copied()
+                .find(|name| severity(name).is_some_and(|s| s > current)),
+        };
+        self.min_severity = next.and_then(severity);
+        self.min_severity.is_some()
+    }
+
+    /// Shows or hides `level`. Returns true when the level is now hidden.
+    pub fn toggle_hidden(&mut self, level: &str) -> bool {
+        let key = level.to_ascii_uppercase();
+        if self.hidden.remove(&key) {
+            false
+        } else {
+            self.hidden.insert(key);
+            true
+        }
+    }
+
+    pub fn min_label(&self) -> Option<&'static str> {
+        let min = self.min_severity?;
+        THRESHOLDS
+            .iter()
+            .copied()
+            .find(|name| severity(name) == Some(min))
+    }
+
+    /// Short summary for the list title, e.g. `≥WARN, -DEBUG`.
+    pub fn describe(&self) -> Option<String> {
+        if !self.is_active() {
+            return None;
+        }
+        let mut parts = Vec::new();
+        if let Some(min) = self.min_label() {
+            parts.push(format!("≥{min}"));
+        }
+        parts.extend(self.hidden.iter().map(|level| format!("-{level}")));
+        Some(parts.join(", "))
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
+
+use super::filters::{FilterLayer, FilterModes, Query, snapshot_matches};
+use rayon::prelude::*;
+use serde_json::Value;
+
+/// How long typing has to pause before the prompt buffer is re-evaluated.
+pub const LIVE_FILTER_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Buffers at least this large are filtered on a worker thread while typing.
+pub const BACKGROUND_FILTER_MIN_ENTRIES: usize = 20_000;
+
+/// The pattern currently typed in the `/` prompt, shown in place of the
+/// committed filter until Enter or Esc. `query` is `None` for an empty buffer.
+#[derive(Clone, Debug)]
+pub struct LivePreview {
+    pub pattern: String,
+    pub query: Option<Query>,
+    pub modes: FilterModes,
+}
+
+pub enum JobPoll {
+    Pending,
+    Done(Vec<usize>),
+    Failed,
+}
+
+/// A preview evaluated off the UI thread over a snapshot of the buffer (or of
+/// the current matches, when the new pattern only narrows). `evicted` and `len`
+/// describe the snapshot so the result can be rebased onto whatever arrived or
+/// was evicted in the meantime.
+pub struct LiveFilterJob {
+    pub preview: LivePreview,
+    pub evicted: u64,
+    pub len: usize,
+    rx: Receiver<Vec<usize>>,
+}
+
+impl LiveFilterJob {
+    pub fn spawn(
+        preview: LivePreview,
+        layers: Vec<FilterLayer>,
+        candidates: Vec<(usize, Arc<str>, Arc<Value>)>,
+        evicted: u64,
+        len: usize,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let query = preview.query.clone();
+        thread::spawn(move || {
+            let matches: Vec<usize> = candidates
+                .par_iter()
+                .filter(|(_, text, raw)| snapshot_matches(query.as_ref(), &layers, text, raw))
+                .map(|(idx, _, _)| *idx)
+                .collect();
+            let _ = tx.send(matches);
+        });
+        Self {
+            preview,
+            evicted,
+            len,
+            rx,
+        }
+    }
+
+    pub fn poll(&self) -> JobPoll {
+        match self.rx.try_recv() {
+            Ok(matches) => JobPoll::Done(matches),
+            Err(TryRecvError::Empty) => JobPoll::Pending,
+            Err(TryRecvError::Disconnected) => JobPoll::Failed,
+        }
+    }
+}
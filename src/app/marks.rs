@@ -0,0 +1,198 @@
+//! Bookmarks, vim-style named marks and notes on entries.
+
+use std::collections::BTreeMap;
+
+use crate::model::{EntryKey, LogEntry};
+
+/// What the key after `m` or `'` does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkPrompt {
+    Set,
+    Jump,
+}
+
+/// A marked entry: its absolute sequence number (evicted entries plus buffer
+/// index), which filters and eviction leave alone, and its key so sessions
+/// can find it again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marked {
+    pub seq: u64,
+    pub key: EntryKey,
+}
+
+/// A row of the bookmark popup: an entry with a bookmark, named marks, a
+/// note, or several of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkRow {
+    pub seq: u64,
+    pub bookmarked: bool,
+    pub names: Vec<char>,
+    pub note: Option<String>,
+}
+
+/// One kind of mark, as stored in sessions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkKind {
+    Bookmark,
+    Named(char),
+    Note(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Marks {
+    bookmarks: BTreeMap<u64, EntryKey>,
+    named: BTreeMap<char, Marked>,
+    notes: BTreeMap<u64, (EntryKey, String)>,
+    /// Restored from a session and waiting for their entries to arrive.
+    pending: Vec<(MarkKind, EntryKey)>,
+}
+
+impl Marks {
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty() && self.named.is_empty() && self.notes.is_empty()
+    }
+
+    /// Puts a mark of `kind` on the entry at `seq`.
+    pub fn place(&mut self, kind: MarkKind, seq: u64, key: EntryKey) {
+        match kind {
+            MarkKind::Bookmark => {
+                self.bookmarks.insert(seq, key);
+            }
+            MarkKind::Named(name) => self.set(name, seq, key),
+            MarkKind::Note(text) => self.set_note(seq, key, &text),
+        }
+    }
+
+    /// Bookmarks or unbookmarks the entry; returns whether it is now bookmarked.
+    pub fn toggle_bookmark(&mut self, seq: u64, key: EntryKey) -> bool {
+        if self.bookmarks.remove(&seq).is_some() {
+            return false;
+        }
+        self.bookmarks.insert(seq, key);
+        true
+    }
+
+    pub fn is_bookmarked(&self, seq: u64) -> bool {
+        self.bookmarks.contains_key(&seq)
+    }
+
+    pub fn set(&mut self, name: char, seq: u64, key: EntryKey) {
+        self.named.insert(name, Marked { seq, key });
+    }
+
+    pub fn get(&self, name: char) -> Option<u64> {
+        self.named.get(&name).map(|mark| mark.seq)
+    }
+
+    /// Attaches `text` to the entry, replacing its note; blank text removes it.
+    pub fn set_note(&mut self, seq: u64, key: EntryKey, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.notes.remove(&seq);
+        } else {
+            self.notes.insert(seq, (key, text.to_string()));
+        }
+    }
+
+    pub fn note(&self, seq: u64) -> Option<&str> {
+        self.notes.get(&seq).map(|(_, text)| text.as_str())
+    }
+
+    /// Annotated entries in buffer order.
+    pub fn notes(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.notes
+            .iter()
+            .map(|(&seq, (_, text))| (seq, text.as_str()))
+    }
+
+    /// The first name of a mark on the entry, for the list gutter.
+    pub fn name_of(&self, seq: u64) -> Option<char> {
+        self.named
+            .iter()
+            .find(|(_, mark)| mark.seq == seq)
+            .map(|(&name, _)| name)
+    }
+
+    /// Drops the bookmark, named marks and note of the entry.
+    pub fn remove(&mut self, seq: u64) {
+        self.bookmarks.remove(&seq);
+        self.named.retain(|_, mark| mark.seq != seq);
+        self.notes.remove(&seq);
+    }
+
+    /// Drops marks on entries that left the buffer.
+    pub fn evict_before(&mut self, seq: u64) {
+        if self
+            .bookmarks
+            .first_key_value()
+            .is_some_and(|(&s, _)| s < seq)
+        {
+            self.bookmarks = self.bookmarks.split_off(&seq);
+        }
+        if self.notes.first_key_value().is_some_and(|(&s, _)| s < seq) {
+            self.notes = self.notes.split_off(&seq);
+        }
+        self.named.retain(|_, mark| mark.seq >= seq);
+    }
+
+    /// Marked entries in buffer order.
+    pub fn rows(&self) -> Vec<MarkRow> {
+        let mut rows: BTreeMap<u64, MarkRow> = BTreeMap::new();
+        let row = |seq: u64| MarkRow {
+            seq,
+            bookmarked: false,
+            names: Vec::new(),
+            note: None,
+        };
+        for &seq in self.bookmarks.keys() {
+            rows.entry(seq).or_insert_with(|| row(seq)).bookmarked = true;
+        }
+        for (&name, mark) in &self.named {
+            let seq = mark.seq;
+            rows.entry(seq).or_insert_with(|| row(seq)).names.push(name);
+        }
+        for (&seq, (_, text)) in &self.notes {
+            rows.entry(seq).or_insert_with(|| row(seq)).note = Some(text.clone());
+        }
+        rows.into_values().collect()
+    }
+
+    pub fn bookmark_keys(&self) -> Vec<EntryKey> {
+        self.bookmarks.values().cloned().collect()
+    }
+
+    pub fn named_keys(&self) -> BTreeMap<char, EntryKey> {
+        self.named
+            .iter()
+            .map(|(&name, mark)| (name, mark.key.clone()))
+            .collect()
+    }
+
+    pub fn note_keys(&self) -> Vec<(EntryKey, String)> {
+        self.notes.values().cloned().collect()
+    }
+
+    /// Queues a mark from a session until [`Marks::resolve`] sees its entry.
+    pub fn restore_later(&mut self, kind: MarkKind, key: EntryKey) {
+        self.pending.push((kind, key));
+    }
+
+    /// Places pending marks that belong to `entry`, stored at `seq`.
+    pub fn resolve(&mut self, entry: &LogEntry, seq: u64) {
+        if !self
+            .pending
+            .iter()
+            .any(|(_, key)| key.timestamp == entry.timestamp)
+        {
+            return;
+        }
+        let key = entry.key();
+        let (found, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(_, pending)| *pending == key);
+        self.pending = rest;
+        for (kind, key) in found {
+            self.place(kind, seq, key);
+        }
+    }
+}
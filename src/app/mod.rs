@@ -1,24 +1,54 @@
-use std::{sync::mpsc, time::Duration};
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{Terminal, backend::Backend};
 use regex::escape;
 
 use crate::{
     editor::{open_entry_in_editor, open_value_in_editor},
+    input::{InputEvent, SourceSpec},
+    keys::KeyChord,
     ui,
 };
 
+mod clusters;
 mod columns;
+mod commands;
+mod correlation;
+mod dedupe;
+mod export;
 mod field_view;
+mod filters;
+mod history;
+mod jumps;
+mod keymap;
+mod levels;
+mod live_filter;
+mod marks;
+mod palette;
+mod session;
+mod sort;
 mod state;
+mod trace;
 
-pub use columns::ColumnDef;
+pub use columns::{ColumnDef, ColumnWidth, default_column, row_cells_for};
+pub use dedupe::Dedupe;
 pub use field_view::{FieldEntry, FieldViewState, FieldZoom};
+pub use filters::FilterKind;
+pub use history::FilterHistory;
+pub use keymap::{Action, KeyMode, Keymap};
+pub use session::Session;
 pub use state::{App, Focus, InputMode};
+pub use trace::{TraceRow, TraceView, format_duration};
 
+use commands::Command;
 use field_view::field_value_for_filter;
+use marks::MarkPrompt;
+use state::Yank;
 
 fn move_field_selection(app: &mut App, delta: isize) {
     let Some(fv) = app.field_view.as_mut() else {
@@ -51,12 +81,32 @@ fn move_field_half_page(app: &mut App, delta: isize) {
 }
 
 fn move_field_value_to_filter(app: &mut App, entry: &FieldEntry) {
-    let literal = escape(&field_value_for_filter(entry));
+    let value = field_value_for_filter(entry);
+    let literal = if app.filter_query_modes.literal {
+        value
+    } else {
+        escape(&value)
+    };
+    app.exit_field_view();
+    app.focus = Focus::List;
+    app.begin_filter_input(literal);
+}
+
+/// Stacks an equals/excludes predicate for the field viewer's selection and
+/// returns to the list, like `/` does for text filters.
+fn filter_by_selected_field(app: &mut App, kind: FilterKind) {
+    let Some(selected) = app
+        .field_view
+        .as_ref()
+        .and_then(|fv| fv.selected_field())
+        .filter(|field| field.is_leaf())
+        .cloned()
+    else {
+        return;
+    };
     app.exit_field_view();
     app.focus = Focus::List;
-    app.filter_buffer = literal;
-    app.filter_error = None;
-    app.input_mode = InputMode::FilterInput;
+    app.add_field_predicate(&selected, kind);
 }
 
 fn cycle_field_zoom(app: &mut App) {
@@ -66,15 +116,920 @@ fn cycle_field_zoom(app: &mut App) {
     };
 }
 
+/// What running an action amounted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Handled,
+    /// The action does not apply right now; the next action bound to the
+    /// key is tried, and plain characters are then typed into prompts.
+    Unhandled,
+    Quit,
+}
+
+/// Actions of the normal keymap that also work while the list popups (level
+/// checklist, filter stack, ...) are open.
+const POPUP_GLOBALS: [Action; 6] = [
+    Action::Quit,
+    Action::NextEntry,
+    Action::PrevEntry,
+    Action::FieldView,
+    Action::Zoom,
+    Action::OpenEditor,
+];
+
+fn handle_key<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    key: KeyEvent,
+) -> Result<Outcome> {
+    // Remaps stand in for other keys, so prompts keep taking the keys as typed.
+    let remappable = app.mark_prompt.is_none()
+        && !matches!(
+            app.input_mode,
+            InputMode::FilterInput
+                | InputMode::ColumnInput
+                | InputMode::Command
+                | InputMode::Palette
+                | InputMode::NoteInput
+                | InputMode::FieldView
+        );
+    let key = if remappable { app.remap_key(key) } else { key };
+    let chord = KeyChord::from_event(&key);
+    // Plain characters no binding claims are typed into the prompts.
+    let typed = match key.code {
+        KeyCode::Char(c)
+            if !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            Some(c)
+        }
+        _ => None,
+    };
+
+    if app.show_help
+        && matches!(
+            app.input_mode,
+            InputMode::Normal | InputMode::ColumnSelect | InputMode::FilterStack
+        )
+    {
+        let mode = match app.input_mode {
+            InputMode::ColumnSelect => KeyMode::ColumnSelect,
+            InputMode::FilterStack => KeyMode::FilterStack,
+            _ => KeyMode::Normal,
+        };
+        let actions = app.keymap.actions(mode, chord);
+        let quit = match mode {
+            KeyMode::FilterStack => app.keymap.actions(KeyMode::Normal, chord),
+            _ => actions.clone(),
+        };
+        if quit.contains(&Action::Quit) {
+            return Ok(Outcome::Quit);
+        }
+        if key.code == KeyCode::Esc || actions.contains(&Action::Help) {
+            app.show_help = false;
+        }
+        return Ok(Outcome::Handled);
+    }
+
+    if let Some(prompt) = app.mark_prompt.take() {
+        if let Some(c) = typed {
+            app.finish_mark_prompt(prompt, c);
+        }
+        return Ok(Outcome::Handled);
+    }
+
+    match app.input_mode {
+        InputMode::Normal => run_bound(terminal, app, KeyMode::Normal, chord, normal_action),
+        InputMode::FieldView => {
+            let outcome = run_bound(terminal, app, KeyMode::FieldView, chord, field_view_action)?;
+            if outcome == Outcome::Unhandled
+                && let Some(c) = typed
+            {
+                edit_field_filter(app, |filter| filter.push(c));
+            }
+            Ok(outcome)
+        }
+        InputMode::FilterInput => {
+            let buffer_before = app.filter_buffer.clone();
+            let outcome = if app.history_search.is_some() {
+                history_search_key(app, key, chord, typed);
+                Outcome::Handled
+            } else {
+                let outcome = run_bound(
+                    terminal,
+                    app,
+                    KeyMode::FilterInput,
+                    chord,
+                    filter_input_action,
+                )?;
+                if outcome == Outcome::Unhandled
+                    && let Some(c) = typed
+                {
+                    app.filter_buffer.push(c);
+                }
+                outcome
+            };
+            if matches!(app.input_mode, InputMode::FilterInput)
+                && app.filter_buffer != buffer_before
+            {
+                app.schedule_live_filter();
+            }
+            Ok(outcome)
+        }
+        InputMode::ColumnInput => {
+            let outcome = run_bound(
+                terminal,
+                app,
+                KeyMode::FilterInput,
+                chord,
+                column_input_action,
+            )?;
+            if outcome == Outcome::Unhandled
+                && let Some(c) = typed
+            {
+                app.column_buffer.push(c);
+            }
+            Ok(outcome)
+        }
+        InputMode::NoteInput => {
+            let outcome = run_bound(
+                terminal,
+                app,
+                KeyMode::FilterInput,
+                chord,
+                note_input_action,
+            )?;
+            if outcome == Outcome::Unhandled
+                && let Some(c) = typed
+            {
+                app.note_buffer.push(c);
+            }
+            Ok(outcome)
+        }
+        InputMode::ColumnSelect => run_bound(
+            terminal,
+            app,
+            KeyMode::ColumnSelect,
+            chord,
+            column_select_action,
+        ),
+        InputMode::Command => {
+            if app
+                .keymap
+                .actions(KeyMode::FilterInput, chord)
+                .contains(&Action::Submit)
+            {
+                let input = app.take_command();
+                return run_command(terminal, app, &input);
+            }
+            let outcome = run_bound(
+                terminal,
+                app,
+                KeyMode::FilterInput,
+                chord,
+                command_input_action,
+            )?;
+            if outcome == Outcome::Unhandled
+                && let Some(c) = typed
+            {
+                app.command_buffer.push(c);
+            }
+            Ok(outcome)
+        }
+        InputMode::Palette => {
+            if app
+                .keymap
+                .actions(KeyMode::Palette, chord)
+                .contains(&Action::Submit)
+            {
+                return match app.take_palette_action() {
+                    Some(action) => perform(terminal, app, action),
+                    None => Ok(Outcome::Handled),
+                };
+            }
+            let outcome = run_bound(terminal, app, KeyMode::Palette, chord, palette_action)?;
+            if outcome == Outcome::Unhandled
+                && let Some(c) = typed
+            {
+                app.palette_edit(Some(c));
+            }
+            Ok(outcome)
+        }
+        InputMode::LevelSelect => run_popup(
+            terminal,
+            app,
+            KeyMode::LevelSelect,
+            chord,
+            level_select_action,
+        ),
+        InputMode::FieldFilter => run_popup(
+            terminal,
+            app,
+            KeyMode::FieldFilter,
+            chord,
+            field_filter_action,
+        ),
+        InputMode::SavedFilters => run_popup(
+            terminal,
+            app,
+            KeyMode::SavedFilters,
+            chord,
+            saved_filters_action,
+        ),
+        InputMode::FilterStack => run_popup(
+            terminal,
+            app,
+            KeyMode::FilterStack,
+            chord,
+            filter_stack_action,
+        ),
+        InputMode::Bookmarks => {
+            run_popup(terminal, app, KeyMode::Bookmarks, chord, bookmarks_action)
+        }
+        InputMode::TraceView => {
+            run_popup(terminal, app, KeyMode::TraceView, chord, trace_view_action)
+        }
+        InputMode::Clusters => run_popup(terminal, app, KeyMode::Clusters, chord, clusters_action),
+    }
+}
+
+/// Runs the actions bound to `chord` in `mode` until one applies.
+fn run_bound<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mode: KeyMode,
+    chord: KeyChord,
+    mut handler: impl FnMut(&mut App, Action) -> Outcome,
+) -> Result<Outcome> {
+    for action in app.keymap.actions(mode, chord) {
+        let outcome = match action {
+            Action::OpenEditor => {
+                open_in_editor(terminal, app)?;
+                Outcome::Handled
+            }
+            action => handler(app, action),
+        };
+        if outcome != Outcome::Unhandled {
+            return Ok(outcome);
+        }
+    }
+    Ok(Outcome::Unhandled)
+}
+
+/// Runs an action of the list and detail panes, as the palette and the
+/// command line do.
+fn perform<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    action: Action,
+) -> Result<Outcome> {
+    if action == Action::OpenEditor {
+        open_in_editor(terminal, app)?;
+        return Ok(Outcome::Handled);
+    }
+    Ok(normal_action(app, action))
+}
+
+/// Runs `chord` in a list popup's own keymap, then as one of the
+/// [`POPUP_GLOBALS`] of the normal keymap.
+fn run_popup<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mode: KeyMode,
+    chord: KeyChord,
+    handler: impl FnMut(&mut App, Action) -> Outcome,
+) -> Result<Outcome> {
+    let outcome = run_bound(terminal, app, mode, chord, handler)?;
+    if outcome != Outcome::Unhandled {
+        return Ok(outcome);
+    }
+    run_bound(terminal, app, KeyMode::Normal, chord, |app, action| {
+        if POPUP_GLOBALS.contains(&action) {
+            common_action(app, action)
+        } else {
+            Outcome::Unhandled
+        }
+    })
+}
+
+/// Parses and runs a `:` command; problems go to the status bar.
+fn run_command<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    input: &str,
+) -> Result<Outcome> {
+    let command = match commands::parse(input) {
+        Ok(command) => command,
+        Err(err) => {
+            app.notify_error(err);
+            return Ok(Outcome::Handled);
+        }
+    };
+    let result = match command {
+        Command::Action(action) => return perform(terminal, app, action),
+        Command::Filter(pattern) => {
+            app.begin_filter_input(pattern);
+            app.submit_filter_input();
+            Ok(())
+        }
+        Command::AddColumn(spec) => app.add_column_spec(&spec),
+        Command::RemoveColumn(name) => app.remove_column_named(&name),
+        Command::Goto(target) => app.goto(&target),
+        Command::Set(setting) => {
+            app.apply_setting(setting);
+            Ok(())
+        }
+        Command::AddSource(path) => {
+            let name = path.display().to_string();
+            app.add_source(path)
+                .map(|()| app.notify(format!("Reading {name}")))
+        }
+        Command::ListSources => {
+            let sources: Vec<String> = app
+                .sources
+                .iter()
+                .map(|source| match source {
+                    SourceSpec::Stdin => "stdin".to_string(),
+                    SourceSpec::File(path) => path.display().to_string(),
+                })
+                .collect();
+            app.notify(format!("Sources: {}", sources.join(", ")));
+            Ok(())
+        }
+        Command::Export(spec) => app.export(&spec),
+        Command::Note(text) => {
+            app.annotate(&text);
+            Ok(())
+        }
+        Command::Mark(name) => {
+            app.finish_mark_prompt(MarkPrompt::Set, name);
+            Ok(())
+        }
+        Command::Dedupe(by) => {
+            app.notify(format!("Folding repeats by {}", by.describe()));
+            app.set_dedupe(Some(by));
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        app.notify_error(err);
+    }
+    Ok(Outcome::Handled)
+}
+
+fn open_in_editor<B: Backend>(terminal: &mut Terminal<B>, app: &App) -> Result<()> {
+    if matches!(app.input_mode, InputMode::FieldView) {
+        if let Some(sel) = app.field_view.as_ref().and_then(|fv| fv.selected_field()) {
+            open_value_in_editor(terminal, &sel.path, &sel.value)?;
+        }
+    } else if let Some(entry) = app.current_entry() {
+        open_entry_in_editor(terminal, &entry)?;
+    }
+    Ok(())
+}
+
+/// Actions that mean the same in every mode they are bound in.
+fn common_action(app: &mut App, action: Action) -> Outcome {
+    let field_view = matches!(app.input_mode, InputMode::FieldView);
+    match action {
+        Action::Quit => return Outcome::Quit,
+        Action::Help => app.show_help = !app.show_help,
+        Action::NextEntry => app.next(),
+        Action::PrevEntry => app.previous(),
+        Action::FieldView if field_view => app.exit_field_view(),
+        Action::FieldView => app.enter_field_view(),
+        Action::Zoom if field_view => cycle_field_zoom(app),
+        Action::Zoom => {
+            app.zoom = match app.zoom {
+                Some(zoomed) if zoomed == app.focus => None,
+                _ => Some(app.focus),
+            };
+        }
+        Action::Redraw => app.force_redraw = true,
+        _ => return Outcome::Unhandled,
+    }
+    Outcome::Handled
+}
+
+fn normal_action(app: &mut App, action: Action) -> Outcome {
+    let list = matches!(app.focus, Focus::List);
+    match action {
+        Action::Down if list => app.next(),
+        Action::Down => app.detail_down(1),
+        Action::Up if list => app.previous(),
+        Action::Up => app.detail_up(1),
+        Action::HalfPageDown if list => app.page_down(),
+        Action::HalfPageDown => {
+            let half = (app.last_detail_height.max(1) / 2).max(1);
+            app.detail_down(half);
+        }
+        Action::HalfPageUp if list => app.page_up(),
+        Action::HalfPageUp => {
+            let half = (app.last_detail_height.max(1) / 2).max(1);
+            app.detail_up(half);
+        }
+        Action::Top if list => app.select_first(),
+        Action::Top => app.detail_top(),
+        Action::Bottom if list => app.select_last(),
+        Action::Bottom => app.detail_bottom(),
+        Action::ScrollLeft if list => {
+            let step = (app.last_list_width / 4).max(4);
+            app.horiz_offset = app.horiz_offset.saturating_sub(step);
+            app.clamp_offset();
+        }
+        Action::ScrollLeft => {
+            let step = (app.last_detail_width / 4).max(4);
+            app.detail_horiz_offset = app.detail_horiz_offset.saturating_sub(step);
+            app.clamp_detail_horiz_offset();
+        }
+        Action::ScrollRight if list => {
+            let step = (app.last_list_width / 4).max(4);
+            app.horiz_offset = app.horiz_offset.saturating_add(step);
+            app.clamp_offset();
+        }
+        Action::ScrollRight => {
+            let step = (app.last_detail_width / 4).max(4);
+            app.detail_horiz_offset = app.detail_horiz_offset.saturating_add(step);
+            app.clamp_detail_horiz_offset();
+        }
+        Action::LineStart if list => app.horiz_offset = 0,
+        Action::LineStart => app.detail_horiz_offset = 0,
+        Action::LineEnd if list => {
+            app.horiz_offset = app.max_row_width.saturating_sub(app.last_list_width);
+            app.clamp_offset();
+        }
+        Action::LineEnd => {
+            app.detail_horiz_offset = app
+                .detail_max_line_width
+                .saturating_sub(app.last_detail_width);
+            app.clamp_detail_horiz_offset();
+        }
+        Action::FocusDetail => app.focus = Focus::Detail,
+        Action::FocusList => app.focus = Focus::List,
+        Action::ToggleFocus => {
+            app.focus = if list { Focus::Detail } else { Focus::List };
+        }
+        Action::Wrap => {
+            app.detail_wrap = !app.detail_wrap;
+            app.reset_detail_position();
+            app.force_redraw = true;
+        }
+        Action::Filter => app.begin_filter_input(app.filter_query.clone()),
+        Action::SavedFilters => app.open_saved_filters(),
+        Action::FilterStack => app.open_filter_stack(),
+        Action::IncludeField => app.open_field_filter(FilterKind::Include),
+        Action::ExcludeField => app.open_field_filter(FilterKind::Exclude),
+        Action::CycleMinLevel => app.cycle_min_severity(),
+        Action::LevelSelect => app.open_level_select(),
+        Action::Columns => {
+            app.input_mode = InputMode::ColumnSelect;
+            if app.column_select_state.selected().is_none() && !app.columns.is_empty() {
+                app.column_select_state.select(Some(0));
+            }
+        }
+        Action::TimestampMode => app.cycle_timestamp_mode(),
+        Action::TimeReference => app.toggle_time_reference(),
+        Action::Autoscroll => app.toggle_autoscroll(),
+        Action::Pause => app.toggle_input_pause(),
+        Action::SaveSession => app.save_session(),
+        Action::Export => {
+            app.begin_command();
+            app.command_buffer.push_str("export ");
+        }
+        Action::Bookmark => app.toggle_bookmark(),
+        Action::NextBookmark => app.jump_bookmark(true),
+        Action::PrevBookmark => app.jump_bookmark(false),
+        Action::Bookmarks => app.open_bookmarks(),
+        Action::Note => app.begin_note(),
+        Action::SetMark => {
+            app.mark_prompt = Some(MarkPrompt::Set);
+            app.notify("Mark: type a letter to name it");
+        }
+        Action::JumpToMark => {
+            app.mark_prompt = Some(MarkPrompt::Jump);
+            app.notify("Jump to mark: type its letter");
+        }
+        Action::JumpBack => {
+            if !app.jump_history(true) {
+                app.notify_error("No earlier jump to go back to");
+            }
+        }
+        // Falls through to the next action bound to the key (Tab switches
+        // panes) unless Ctrl+O left somewhere to come forward to.
+        Action::JumpForward => {
+            if !app.jump_history(false) {
+                return Outcome::Unhandled;
+            }
+        }
+        Action::FollowCorrelation => {
+            if let Err(err) = app.follow_correlation() {
+                app.notify_error(err);
+            }
+        }
+        Action::Clusters => app.open_clusters(),
+        Action::ClearTemplate => {
+            if !app.clear_template_filter() {
+                return Outcome::Unhandled;
+            }
+        }
+        Action::Dedupe => app.toggle_dedupe(),
+        Action::ToggleRepeats => {
+            if !app.toggle_repeats() {
+                return Outcome::Unhandled;
+            }
+        }
+        Action::TraceView => {
+            if let Err(err) = app.open_trace_view() {
+                app.notify_error(err);
+            }
+        }
+        Action::CorrelationBack => {
+            if !app.unfollow_correlation() {
+                return Outcome::Unhandled;
+            }
+        }
+        Action::Yank => app.yank(Yank::Entry),
+        Action::YankRange => app.yank(Yank::Marked),
+        Action::CommandLine => app.begin_command(),
+        Action::Palette => app.open_palette(),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn filter_input_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.cancel_filter_input(),
+        Action::Submit => app.submit_filter_input(),
+        Action::HistoryPrev => app.history_step(true),
+        Action::HistoryNext => app.history_step(false),
+        Action::HistorySearch => app.history_search_next(),
+        Action::SavedFilters => {
+            app.clear_live_filter();
+            app.open_saved_filters();
+        }
+        Action::DeleteChar => {
+            app.filter_buffer.pop();
+        }
+        Action::ClearInput => app.filter_buffer.clear(),
+        Action::ToggleSmartCase => app.toggle_filter_mode(|m| m.smart_case = !m.smart_case),
+        Action::ToggleLiteral => app.toggle_filter_mode(|m| m.literal = !m.literal),
+        Action::ToggleWholeWord => app.toggle_filter_mode(|m| m.whole_word = !m.whole_word),
+        Action::ToggleJq => app.toggle_filter_mode(|m| m.jq = !m.jq),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+/// Keys while reverse-searching the history (`Ctrl+R`): the prompt's
+/// bindings for searching again, cancelling, accepting and deleting apply,
+/// arrows take the match for editing.
+fn history_search_key(app: &mut App, key: KeyEvent, chord: KeyChord, typed: Option<char>) {
+    let actions = app.keymap.actions(KeyMode::FilterInput, chord);
+    if actions.contains(&Action::HistorySearch) {
+        app.history_search_next();
+    } else if actions.contains(&Action::Close) {
+        app.cancel_history_search();
+    } else if actions.contains(&Action::Submit) {
+        app.submit_filter_input();
+    } else if actions.contains(&Action::DeleteChar) {
+        app.history_search_edit(None);
+    } else if let Some(c) = typed {
+        app.history_search_edit(Some(c));
+    } else if matches!(
+        key.code,
+        KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+    ) {
+        app.accept_history_search();
+    }
+}
+
+/// The column prompt (`+` in the column selector) shares the filter
+/// prompt's bindings.
+fn column_input_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.cancel_column_input(),
+        Action::Submit => app.submit_column_input(),
+        Action::DeleteChar => {
+            app.column_buffer.pop();
+        }
+        Action::ClearInput => app.column_buffer.clear(),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn note_input_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.cancel_note(),
+        Action::Submit => app.submit_note(),
+        Action::DeleteChar => {
+            app.note_buffer.pop();
+        }
+        Action::ClearInput => app.note_buffer.clear(),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn command_input_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.cancel_command(),
+        Action::DeleteChar if app.command_buffer.is_empty() => app.cancel_command(),
+        Action::DeleteChar => {
+            app.command_buffer.pop();
+        }
+        Action::ClearInput => app.command_buffer.clear(),
+        Action::Complete => app.complete_command(),
+        Action::HistoryPrev => app.command_history_step(true),
+        Action::HistoryNext => app.command_history_step(false),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn column_select_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Toggle => {
+            if let Some(idx) = app.column_select_state.selected()
+                && let Some(col) = app.columns.get_mut(idx)
+            {
+                col.enabled = !col.enabled;
+            }
+        }
+        Action::AddColumn => app.begin_column_input(),
+        Action::CycleWidth => app.cycle_column_width(),
+        Action::Narrower => app.resize_column(-2),
+        Action::Wider => app.resize_column(2),
+        Action::Pin => app.toggle_column_sticky(),
+        Action::CycleSort => app.cycle_sort(),
+        Action::Remove => app.remove_computed_column(),
+        Action::MoveDown => {
+            app.move_column(1);
+        }
+        Action::MoveUp => {
+            app.move_column(-1);
+        }
+        Action::Down => {
+            let len = app.columns.len();
+            let next = app
+                .column_select_state
+                .selected()
+                .map(|i| (i + 1).min(len.saturating_sub(1)))
+                .or(Some(0));
+            app.column_select_state.select(next);
+        }
+        Action::Up => {
+            let prev = app
+                .column_select_state
+                .selected()
+                .map(|i| i.saturating_sub(1))
+                .or(Some(0));
+            app.column_select_state.select(prev);
+        }
+        Action::Top => app.column_select_state.select(Some(0)),
+        Action::Bottom if !app.columns.is_empty() => {
+            app.column_select_state
+                .select(Some(app.columns.len().saturating_sub(1)));
+        }
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn level_select_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Toggle => app.toggle_selected_level(),
+        Action::CycleMinLevel => app.cycle_min_severity(),
+        Action::ResetLevels => app.reset_level_filter(),
+        Action::Down => app.select_level_row(1),
+        Action::Up => app.select_level_row(-1),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn field_filter_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.close_field_filter(),
+        Action::Submit => app.apply_field_filter(app.field_filter_kind),
+        Action::IncludeField => app.apply_field_filter(FilterKind::Include),
+        Action::ExcludeField => app.apply_field_filter(FilterKind::Exclude),
+        Action::Down => app.select_field_filter(1),
+        Action::Up => app.select_field_filter(-1),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn saved_filters_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Submit => app.apply_saved_filter(),
+        Action::Remove => app.delete_saved_filter(),
+        Action::Down => app.select_saved_filter(1),
+        Action::Up => app.select_saved_filter(-1),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn filter_stack_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Toggle => app.toggle_filter_layer(),
+        Action::AddInclude => app.begin_filter_layer_input(FilterKind::Include),
+        Action::AddExclude => app.begin_filter_layer_input(FilterKind::Exclude),
+        Action::FlipKind => app.toggle_filter_layer_kind(),
+        Action::Remove => app.remove_filter_layer(),
+        Action::MoveDown => app.move_filter_layer(1),
+        Action::MoveUp => app.move_filter_layer(-1),
+        Action::Down => app.select_filter_layer(1),
+        Action::Up => app.select_filter_layer(-1),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn bookmarks_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Submit => app.jump_to_bookmark_row(),
+        Action::Remove => app.delete_bookmark_row(),
+        Action::Down => app.select_bookmark_row(1),
+        Action::Up => app.select_bookmark_row(-1),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn trace_view_action(app: &mut App, action: Action) -> Outcome {
+    let Some(view) = app.trace_view.as_mut() else {
+        return Outcome::Unhandled;
+    };
+    let rows = view.rows.len() as isize;
+    match action {
+        Action::Close => app.close_trace_view(),
+        Action::Submit => app.trace_view_enter(),
+        Action::Toggle => view.toggle_selected(),
+        Action::Collapse => view.set_collapsed(true),
+        Action::Expand => view.set_collapsed(false),
+        Action::Down => view.select_row(1),
+        Action::Up => view.select_row(-1),
+        Action::Top => view.select_row(-rows),
+        Action::Bottom => view.select_row(rows),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn clusters_action(app: &mut App, action: Action) -> Outcome {
+    let rows = app.cluster_rows.len() as isize;
+    match action {
+        Action::Close => app.close_clusters(),
+        Action::Submit => app.filter_by_cluster_row(),
+        Action::Down => app.select_cluster_row(1),
+        Action::Up => app.select_cluster_row(-1),
+        Action::Top => app.select_cluster_row(-rows),
+        Action::Bottom => app.select_cluster_row(rows),
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+/// Running the chosen action is left to the caller, which has the terminal
+/// an editor action needs.
+fn palette_action(app: &mut App, action: Action) -> Outcome {
+    match action {
+        Action::Close => app.input_mode = InputMode::Normal,
+        Action::Down => app.select_palette_row(1),
+        Action::Up => app.select_palette_row(-1),
+        Action::DeleteChar => app.palette_edit(None),
+        _ => return Outcome::Unhandled,
+    }
+    Outcome::Handled
+}
+
+fn field_view_action(app: &mut App, action: Action) -> Outcome {
+    let wrap = app.field_detail_wrap;
+    let filter_empty = app
+        .field_view
+        .as_ref()
+        .is_none_or(|fv| fv.filter.is_empty());
+    match action {
+        Action::Close => app.exit_field_view(),
+        Action::Filter => {
+            if let Some(selected) = app
+                .field_view
+                .as_ref()
+                .and_then(|fv| fv.selected_field())
+                .cloned()
+            {
+                move_field_value_to_filter(app, &selected);
+            }
+        }
+        // While a field filter is being typed, `=` and `!` are part of it.
+        Action::IncludeField if filter_empty => {
+            filter_by_selected_field(app, FilterKind::Include);
+        }
+        Action::ExcludeField if filter_empty => {
+            filter_by_selected_field(app, FilterKind::Exclude);
+        }
+        Action::Yank => app.yank(Yank::FieldValue),
+        Action::YankPath => app.yank(Yank::FieldPath),
+        Action::Wrap => {
+            app.field_detail_wrap = !app.field_detail_wrap;
+            app.reset_field_detail_position();
+            app.force_redraw = true;
+        }
+        // Horizontal scrolling does nothing while wrapped, but the keys are
+        // still taken rather than typed into the field filter.
+        Action::ScrollLeft => {
+            if !wrap {
+                let step = (app.last_field_detail_width / 4).max(4);
+                app.field_detail_horiz_offset = app.field_detail_horiz_offset.saturating_sub(step);
+                app.clamp_field_detail_horiz_offset();
+            }
+        }
+        Action::ScrollRight => {
+            if !wrap {
+                let step = (app.last_field_detail_width / 4).max(4);
+                app.field_detail_horiz_offset = app.field_detail_horiz_offset.saturating_add(step);
+                app.clamp_field_detail_horiz_offset();
+            }
+        }
+        Action::LineStart => {
+            if !wrap {
+                app.field_detail_horiz_offset = 0;
+            }
+        }
+        Action::LineEnd => {
+            if !wrap && app.field_detail_max_line_width > app.last_field_detail_width {
+                app.field_detail_horiz_offset = app
+                    .field_detail_max_line_width
+                    .saturating_sub(app.last_field_detail_width);
+                app.clamp_field_detail_horiz_offset();
+            }
+        }
+        Action::DeleteChar => edit_field_filter(app, |filter| {
+            filter.pop();
+        }),
+        Action::ClearInput if !filter_empty => edit_field_filter(app, String::clear),
+        Action::Down => move_field_selection(app, 1),
+        Action::Up => move_field_selection(app, -1),
+        Action::HalfPageDown => {
+            if matches!(app.field_zoom, Some(FieldZoom::Detail)) {
+                let half = (app.last_field_detail_height.max(1) / 2).max(1);
+                let max_offset = app
+                    .field_detail_total_lines
+                    .saturating_sub(app.last_field_detail_height.max(1));
+                let new = (app.field_detail_scroll as usize + half).min(max_offset);
+                app.field_detail_scroll = new as u16;
+            } else {
+                move_field_half_page(app, 1);
+            }
+        }
+        Action::HalfPageUp => {
+            if matches!(app.field_zoom, Some(FieldZoom::Detail)) {
+                let half = (app.last_field_detail_height.max(1) / 2).max(1);
+                app.field_detail_scroll = app.field_detail_scroll.saturating_sub(half as u16);
+            } else {
+                move_field_half_page(app, -1);
+            }
+        }
+        _ => return common_action(app, action),
+    }
+    Outcome::Handled
+}
+
+fn edit_field_filter(app: &mut App, edit: impl FnOnce(&mut String)) {
+    let Some(fv) = app.field_view.as_mut() else {
+        return;
+    };
+    edit(&mut fv.filter);
+    if fv.rebuild_filter() {
+        app.reset_field_detail_position();
+        app.force_redraw = true;
+    }
+}
+
 pub fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    rx: mpsc::Receiver<crate::model::LogEntry>,
+    rx: mpsc::Receiver<InputEvent>,
 ) -> Result<()> {
     loop {
-        for entry in rx.try_iter() {
-            app.ingest(entry);
+        for event in rx.try_iter() {
+            match event {
+                InputEvent::Entry(entry) => app.ingest(entry),
+                InputEvent::Profile { name, fields } => app.activate_profile(&name, &fields),
+            }
         }
+        app.tick_live_filter(Instant::now());
+        app.tick_export();
 
         if app.force_redraw {
             terminal.clear().ok();
@@ -85,446 +1040,22 @@ pub fn run_app<B: Backend>(
             .draw(|f| ui::render(f, app))
             .context("drawing frame")?;
 
-        if event::poll(Duration::from_millis(100)).context("polling for events")? {
+        let poll_timeout = if app.live_filter_pending() || app.export_job.is_some() {
+            Duration::from_millis(25)
+        } else {
+            Duration::from_millis(100)
+        };
+        if event::poll(poll_timeout).context("polling for events")? {
             match event::read().context("reading event")? {
                 Event::Key(key) if key.kind == KeyEventKind::Press => {
-                    if key.code == KeyCode::Char('q')
-                        || (key.code == KeyCode::Char('c')
-                            && key.modifiers.contains(KeyModifiers::CONTROL))
+                    app.notice = None;
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)
                     {
                         break;
                     }
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        if matches!(app.input_mode, InputMode::FieldView) {
-                            match key.code {
-                                KeyCode::Char('j') | KeyCode::Char('n') => {
-                                    move_field_selection(app, 1);
-                                    continue;
-                                }
-                                KeyCode::Char('k') | KeyCode::Char('p') => {
-                                    move_field_selection(app, -1);
-                                    continue;
-                                }
-                                _ => {}
-                            }
-                        }
-                        match key.code {
-                            KeyCode::Char('n') => {
-                                app.next();
-                                continue;
-                            }
-                            KeyCode::Char('p') => {
-                                app.previous();
-                                continue;
-                            }
-                            KeyCode::Char('t') => {
-                                app.enter_field_view();
-                                continue;
-                            }
-                            KeyCode::Char('z') => {
-                                if matches!(app.input_mode, InputMode::FieldView) {
-                                    cycle_field_zoom(app);
-                                } else {
-                                    app.zoom = match app.zoom {
-                                        Some(Focus::List) => None,
-                                        _ => Some(app.focus),
-                                    };
-                                }
-                                continue;
-                            }
-                            KeyCode::Char('e') => {
-                                if matches!(app.input_mode, InputMode::FieldView) {
-                                    if let Some(sel) =
-                                        app.field_view.as_ref().and_then(|fv| fv.selected_field())
-                                    {
-                                        open_value_in_editor(terminal, &sel.path, &sel.value)?;
-                                    }
-                                } else if let Some(entry) = app.current_entry() {
-                                    open_entry_in_editor(terminal, &entry)?;
-                                }
-                                continue;
-                            }
-                            _ => {}
-                        }
-                    }
-                    if key.code == KeyCode::Char('z')
-                        && !key.modifiers.contains(KeyModifiers::CONTROL)
-                        && !matches!(app.input_mode, InputMode::FieldView)
-                    {
-                        app.zoom = match app.zoom {
-                            Some(Focus::List) if matches!(app.focus, Focus::List) => None,
-                            Some(Focus::Detail) if matches!(app.focus, Focus::Detail) => None,
-                            _ => Some(app.focus),
-                        };
-                        continue;
-                    }
-                    if matches!(app.input_mode, InputMode::FieldView) {
-                        if key.code == KeyCode::Esc
-                            || (key.code == KeyCode::Char('t')
-                                && key.modifiers.contains(KeyModifiers::CONTROL))
-                        {
-                            app.exit_field_view();
-                            continue;
-                        }
-                        if key.code == KeyCode::Char('/')
-                            && !key.modifiers.contains(KeyModifiers::CONTROL)
-                        {
-                            if let Some(selected) = app
-                                .field_view
-                                .as_ref()
-                                .and_then(|fv| fv.selected_field())
-                                .cloned()
-                            {
-                                move_field_value_to_filter(app, &selected);
-                            }
-                            continue;
-                        }
-                        if let Some(fv) = app.field_view.as_mut() {
-                            match key.code {
-                                KeyCode::Char('w') => {
-                                    app.field_detail_wrap = !app.field_detail_wrap;
-                                    app.reset_field_detail_position();
-                                    app.force_redraw = true;
-                                }
-                                KeyCode::Char('h') => {
-                                    if !app.field_detail_wrap {
-                                        let step = (app.last_field_detail_width / 4).max(4);
-                                        app.field_detail_horiz_offset =
-                                            app.field_detail_horiz_offset.saturating_sub(step);
-                                        app.clamp_field_detail_horiz_offset();
-                                    }
-                                }
-                                KeyCode::Char('l') => {
-                                    if !app.field_detail_wrap {
-                                        let step = (app.last_field_detail_width / 4).max(4);
-                                        app.field_detail_horiz_offset =
-                                            app.field_detail_horiz_offset.saturating_add(step);
-                                        app.clamp_field_detail_horiz_offset();
-                                    }
-                                }
-                                KeyCode::Char('0') => {
-                                    if !app.field_detail_wrap {
-                                        app.field_detail_horiz_offset = 0;
-                                    }
-                                }
-                                KeyCode::Char('$') => {
-                                    if !app.field_detail_wrap
-                                        && app.field_detail_max_line_width
-                                            > app.last_field_detail_width
-                                    {
-                                        app.field_detail_horiz_offset = app
-                                            .field_detail_max_line_width
-                                            .saturating_sub(app.last_field_detail_width);
-                                        app.clamp_field_detail_horiz_offset();
-                                    }
-                                }
-                                KeyCode::Backspace => {
-                                    if !fv.filter.is_empty() {
-                                        fv.filter.pop();
-                                        let changed = fv.rebuild_filter();
-                                        if changed {
-                                            app.reset_field_detail_position();
-                                            app.force_redraw = true;
-                                        }
-                                    }
-                                }
-                                KeyCode::Char('u')
-                                    if key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    if !fv.filter.is_empty() {
-                                        fv.filter.clear();
-                                        let changed = fv.rebuild_filter();
-                                        if changed {
-                                            app.reset_field_detail_position();
-                                            app.force_redraw = true;
-                                        }
-                                    }
-                                }
-                                KeyCode::Char(c)
-                                    if !key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    fv.filter.push(c);
-                                    let changed = fv.rebuild_filter();
-                                    if changed {
-                                        app.reset_field_detail_position();
-                                        app.force_redraw = true;
-                                    }
-                                }
-                                KeyCode::Down => {
-                                    if fv.filtered_indices.is_empty() {
-                                        continue;
-                                    }
-                                    move_field_selection(app, 1);
-                                }
-                                KeyCode::Up => {
-                                    if fv.filtered_indices.is_empty() {
-                                        continue;
-                                    }
-                                    move_field_selection(app, -1);
-                                }
-                                KeyCode::Char('d')
-                                    if key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    if matches!(app.field_zoom, Some(FieldZoom::Detail)) {
-                                        let half = (app.last_field_detail_height.max(1) / 2).max(1);
-                                        let max_offset = app
-                                            .field_detail_total_lines
-                                            .saturating_sub(app.last_field_detail_height.max(1));
-                                        let new = (app.field_detail_scroll as usize + half)
-                                            .min(max_offset);
-                                        app.field_detail_scroll = new as u16;
-                                    } else {
-                                        move_field_half_page(app, 1);
-                                    }
-                                }
-                                KeyCode::Char('u')
-                                    if key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    if matches!(app.field_zoom, Some(FieldZoom::Detail)) {
-                                        let half = (app.last_field_detail_height.max(1) / 2).max(1);
-                                        app.field_detail_scroll =
-                                            app.field_detail_scroll.saturating_sub(half as u16);
-                                    } else {
-                                        move_field_half_page(app, -1);
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                        continue;
-                    }
-                    if matches!(app.input_mode, InputMode::FilterInput) {
-                        match key.code {
-                            KeyCode::Esc => {
-                                app.input_mode = InputMode::Normal;
-                                app.filter_buffer.clear();
-                                app.filter_error = None;
-                            }
-                            KeyCode::Enter => {
-                                let pattern = app.filter_buffer.clone();
-                                app.input_mode = InputMode::Normal;
-                                app.apply_filter(&pattern);
-                            }
-                            KeyCode::Backspace => {
-                                app.filter_buffer.pop();
-                            }
-                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.filter_buffer.clear();
-                            }
-                            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.filter_buffer.push(c);
-                            }
-                            _ => {}
-                        }
-                        continue;
-                    }
-                    if key.code == KeyCode::Char('s') && matches!(app.input_mode, InputMode::Normal)
-                    {
-                        app.toggle_input_pause();
-                        continue;
-                    }
-
-                    if key.code == KeyCode::Char('?') {
-                        app.show_help = !app.show_help;
-                        continue;
-                    }
-
-                    if app.show_help {
-                        match key.code {
-                            KeyCode::Esc | KeyCode::Char('?') => app.show_help = false,
-                            KeyCode::Char('q') => break,
-                            _ => {}
-                        }
-                        continue;
-                    }
-
-                    if matches!(app.input_mode, InputMode::ColumnSelect) {
-                        match key.code {
-                            KeyCode::Char('q') => break,
-                            KeyCode::Esc | KeyCode::Char('c') => {
-                                app.input_mode = InputMode::Normal;
-                            }
-                            KeyCode::Char(' ') | KeyCode::Enter => {
-                                if let Some(idx) = app.column_select_state.selected()
-                                    && let Some(col) = app.columns.get_mut(idx)
-                                {
-                                    col.enabled = !col.enabled;
-                                }
-                            }
-                            KeyCode::Char('J') => {
-                                app.move_column(1);
-                            }
-                            KeyCode::Char('K') => {
-                                app.move_column(-1);
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                let len = app.columns.len();
-                                let next = app
-                                    .column_select_state
-                                    .selected()
-                                    .map(|i| (i + 1).min(len.saturating_sub(1)))
-                                    .or(Some(0));
-                                app.column_select_state.select(next);
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                let prev = app
-                                    .column_select_state
-                                    .selected()
-                                    .map(|i| i.saturating_sub(1))
-                                    .or(Some(0));
-                                app.column_select_state.select(prev);
-                            }
-                            KeyCode::Char('g') => app.column_select_state.select(Some(0)),
-                            KeyCode::Char('G') => {
-                                if !app.columns.is_empty() {
-                                    app.column_select_state
-                                        .select(Some(app.columns.len().saturating_sub(1)));
-                                }
-                            }
-                            _ => {}
-                        }
-                        continue;
-                    }
-
-                    match app.focus {
-                        Focus::List => match key.code {
-                            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.force_redraw = true;
-                            }
-                            KeyCode::Char('j') | KeyCode::Down => app.next(),
-                            KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                            KeyCode::Char('h') => {
-                                let step = (app.last_list_width / 4).max(4);
-                                app.horiz_offset = app.horiz_offset.saturating_sub(step);
-                                app.clamp_offset();
-                            }
-                            KeyCode::Char('l') => {
-                                let step = (app.last_list_width / 4).max(4);
-                                app.horiz_offset = app.horiz_offset.saturating_add(step);
-                                app.clamp_offset();
-                            }
-                            KeyCode::Char('0') => {
-                                app.horiz_offset = 0;
-                            }
-                            KeyCode::Char('$') => {
-                                if app.max_row_width > app.last_list_width {
-                                    app.horiz_offset =
-                                        app.max_row_width.saturating_sub(app.last_list_width);
-                                } else {
-                                    app.horiz_offset = 0;
-                                }
-                                app.clamp_offset();
-                            }
-                            KeyCode::Char('c') => {
-                                app.input_mode = InputMode::ColumnSelect;
-                                if app.column_select_state.selected().is_none()
-                                    && !app.columns.is_empty()
-                                {
-                                    app.column_select_state.select(Some(0));
-                                }
-                            }
-                            KeyCode::Char('a') => {
-                                app.toggle_autoscroll();
-                            }
-                            KeyCode::Char('/') => {
-                                app.input_mode = InputMode::FilterInput;
-                                app.filter_buffer = app.filter_query.clone();
-                                app.filter_error = None;
-                            }
-                            KeyCode::Char('z') => {
-                                app.zoom = match app.zoom {
-                                    Some(Focus::List) => None,
-                                    _ => Some(Focus::List),
-                                }
-                            }
-                            KeyCode::Char('w') => {
-                                app.detail_wrap = !app.detail_wrap;
-                                app.reset_detail_position();
-                                app.force_redraw = true;
-                            }
-                            KeyCode::Char('g') => app.select_first(),
-                            KeyCode::Char('G') => app.select_last(),
-                            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.page_down()
-                            }
-                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.page_up()
-                            }
-                            KeyCode::Enter | KeyCode::Tab | KeyCode::Right => {
-                                app.focus = Focus::Detail;
-                            }
-                            _ => {}
-                        },
-                        Focus::Detail => match key.code {
-                            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.force_redraw = true;
-                            }
-                            KeyCode::Char('h') => {
-                                let step = (app.last_detail_width / 4).max(4);
-                                app.detail_horiz_offset =
-                                    app.detail_horiz_offset.saturating_sub(step);
-                                app.clamp_detail_horiz_offset();
-                            }
-                            KeyCode::Char('l') => {
-                                let step = (app.last_detail_width / 4).max(4);
-                                app.detail_horiz_offset =
-                                    app.detail_horiz_offset.saturating_add(step);
-                                app.clamp_detail_horiz_offset();
-                            }
-                            KeyCode::Char('0') => {
-                                app.detail_horiz_offset = 0;
-                            }
-                            KeyCode::Char('$') => {
-                                if app.detail_max_line_width > app.last_detail_width {
-                                    app.detail_horiz_offset = app
-                                        .detail_max_line_width
-                                        .saturating_sub(app.last_detail_width);
-                                    app.clamp_detail_horiz_offset();
-                                } else {
-                                    app.detail_horiz_offset = 0;
-                                }
-                            }
-                            KeyCode::Char('c') => {
-                                app.input_mode = InputMode::ColumnSelect;
-                                if app.column_select_state.selected().is_none()
-                                    && !app.columns.is_empty()
-                                {
-                                    app.column_select_state.select(Some(0));
-                                }
-                            }
-                            KeyCode::Char('j') | KeyCode::Down => app.detail_down(1),
-                            KeyCode::Char('k') | KeyCode::Up => app.detail_up(1),
-                            KeyCode::Char('/') => {
-                                app.input_mode = InputMode::FilterInput;
-                                app.filter_buffer = app.filter_query.clone();
-                                app.filter_error = None;
-                            }
-                            KeyCode::Char('z') => {
-                                app.zoom = match app.zoom {
-                                    Some(Focus::Detail) => None,
-                                    _ => Some(Focus::Detail),
-                                }
-                            }
-                            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                let half = (app.last_detail_height.max(1) / 2).max(1);
-                                app.detail_down(half);
-                            }
-                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                let half = (app.last_detail_height.max(1) / 2).max(1);
-                                app.detail_up(half);
-                            }
-                            KeyCode::Char('w') => {
-                                app.detail_wrap = !app.detail_wrap;
-                                app.reset_detail_position();
-                                app.force_redraw = true;
-                            }
-                            KeyCode::Char('g') => app.detail_top(),
-                            KeyCode::Char('G') => app.detail_bottom(),
-                            KeyCode::Tab | KeyCode::Esc | KeyCode::Left => {
-                                app.focus = Focus::List;
-                            }
-                            _ => {}
-                        },
+                    if handle_key(terminal, app, key)? == Outcome::Quit {
+                        break;
                     }
                 }
                 Event::Resize(_, _) => {}
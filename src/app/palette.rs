@@ -0,0 +1,50 @@
+//! The action palette: every action of the list and detail panes, narrowed
+//! by fuzzy matching on its name and description.
+
+use super::keymap::{Action, KeyMode};
+
+/// Actions matching `query`, best first. An empty query lists them all in
+/// help order.
+pub fn matches(query: &str) -> Vec<Action> {
+    let mut scored: Vec<(i64, Action)> = KeyMode::Normal
+        .actions()
+        .iter()
+        .filter(|&&action| action != Action::Palette)
+        .filter_map(|&action| {
+            let by_name = fuzzy_score(query, action.name());
+            // Description hits rank below name hits.
+            let by_description =
+                fuzzy_score(query, action.description(KeyMode::Normal)).map(|s| s - 100);
+            Some((by_name.max(by_description)?, action))
+        })
+        .collect();
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(_, action)| action).collect()
+}
+
+/// Scores `candidate` if it contains the characters of `query` in order
+/// (ignoring case). Runs of adjacent characters and matches at word starts
+/// score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut chars = candidate.char_indices().peekable();
+    let mut previous: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let (pos, _) = chars.find(|(_, c)| c.to_ascii_lowercase() == q)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == pos) {
+            score += 8;
+        }
+        let word_start = pos == 0
+            || candidate[..pos]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c == '_' || c == ' ' || c == '/');
+        if word_start {
+            score += 6;
+        }
+        previous = Some(pos);
+    }
+    Some(score)
+}
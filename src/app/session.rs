@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{input::SourceSpec, jq, model::EntryKey, timestamps::TimestampMode};
+
+use super::{
+    columns::{ColumnDef, ColumnWidth},
+    filters::{FilterKind, FilterLayer, FilterModes},
+    levels::LevelFilter,
+    marks::MarkKind,
+    sort::SortOrder,
+    state::{App, Focus},
+};
+
+/// Default file `S` writes to when logtui was started without `--session`.
+pub const DEFAULT_SESSION_FILE: &str = "logtui-session.json";
+
+/// Everything needed to reopen an investigation: where the logs came from
+/// and how they were being looked at. Entries are not stored; they are read
+/// again from the sources.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub sources: Vec<SourceSpec>,
+    pub profile: Option<String>,
+    pub filter: SavedQuery,
+    pub layers: Vec<SavedLayer>,
+    pub levels: LevelFilter,
+    pub columns: Vec<SavedColumn>,
+    pub sort: Option<SortOrder>,
+    pub timestamp_mode: TimestampMode,
+    pub selected: Option<EntryKey>,
+    pub bookmarks: Vec<EntryKey>,
+    /// Named marks (`ma`) by letter.
+    pub marks: BTreeMap<char, EntryKey>,
+    pub notes: Vec<SavedNote>,
+    pub focus: Option<Focus>,
+    pub zoom: Option<Focus>,
+    pub detail_wrap: bool,
+    pub field_wrap: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedQuery {
+    pub pattern: String,
+    pub modes: FilterModes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedLayer {
+    pub pattern: String,
+    #[serde(default)]
+    pub modes: FilterModes,
+    pub kind: FilterKind,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedNote {
+    pub entry: EntryKey,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedColumn {
+    pub name: String,
+    #[serde(default)]
+    pub path: Vec<String>,
+    /// Source of a computed column's jq expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expr: Option<String>,
+    pub enabled: bool,
+    pub width: ColumnWidth,
+    #[serde(default)]
+    pub sticky: bool,
+}
+
+impl Session {
+    pub fn capture(app: &App) -> Self {
+        Self {
+            sources: app.sources.clone(),
+            profile: app.active_profile.clone(),
+            filter: SavedQuery {
+                pattern: app.filter_query.clone(),
+                modes: app.filter_query_modes,
+            },
+            layers: app
+                .filter_stack
+                .iter()
+                .map(|layer| SavedLayer {
+                    pattern: layer.pattern.clone(),
+                    modes: layer.modes,
+                    kind: layer.kind,
+                    enabled: layer.enabled,
+                })
+                .collect(),
+            levels: app.level_filter.clone(),
+            columns: app
+                .columns
+                .iter()
+                .map(|col| SavedColumn {
+                    name: col.name.clone(),
+                    path: col.path.clone(),
+                    expr: col.expr.as_ref().map(|e| e.source().to_string()),
+                    enabled: col.enabled,
+                    width: col.width,
+                    sticky: col.sticky,
+                })
+                .collect(),
+            sort: app.sort.clone(),
+            timestamp_mode: app.timestamp_mode,
+            selected: app.current_entry().map(|e| e.key()),
+            bookmarks: app.marks.bookmark_keys(),
+            marks: app.marks.named_keys(),
+            notes: app
+                .marks
+                .note_keys()
+                .into_iter()
+                .map(|(entry, text)| SavedNote { entry, text })
+                .collect(),
+            focus: Some(app.focus),
+            zoom: app.zoom,
+            detail_wrap: app.detail_wrap,
+            field_wrap: app.field_detail_wrap,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("invalid session file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Puts the view state back. The selected entry and marks are picked up
+    /// once the reader delivers their entries. Returns the parts that could
+    /// not be restored.
+    pub fn restore(&self, app: &mut App) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.columns.is_empty() {
+            let mut columns = Vec::new();
+            for saved in &self.columns {
+                let mut col = match &saved.expr {
+                    Some(source) => match jq::Filter::parse(source) {
+                        Ok(expr) => ColumnDef::computed(saved.name.clone(), expr),
+                        Err(err) => {
+                            problems.push(format!("column {}: {err}", saved.name));
+                            continue;
+                        }
+                    },
+                    None => ColumnDef::new(saved.name.clone(), saved.path.clone()),
+                };
+                col.enabled = saved.enabled;
+                col.width = saved.width;
+                col.sticky = saved.sticky;
+                columns.push(col);
+            }
+            app.set_columns(columns);
+        }
+
+        app.filter_stack.clear();
+        for saved in &self.layers {
+            match saved.modes.compile_query(&saved.pattern) {
+                Ok(query) => {
+                    let mut layer =
+                        FilterLayer::new(saved.pattern.clone(), query, saved.modes, saved.kind);
+                    layer.enabled = saved.enabled;
+                    app.filter_stack.push(layer);
+                }
+                Err(err) => problems.push(format!("filter layer {}: {err}", saved.pattern)),
+            }
+        }
+        app.level_filter = self.levels.clone();
+        app.filter_modes = self.filter.modes;
+        if let Err(err) = app.set_default_filter(&self.filter.pattern) {
+            problems.push(format!("filter {}: {err}", self.filter.pattern));
+        }
+
+        app.set_sort(self.sort.clone());
+        app.timestamp_mode = self.timestamp_mode;
+        app.focus = self.focus.unwrap_or(Focus::List);
+        app.zoom = self.zoom;
+        app.detail_wrap = self.detail_wrap;
+        app.field_detail_wrap = self.field_wrap;
+        if let Some(key) = &self.selected {
+            app.select_by_key(key.clone());
+        }
+        let marks = self
+            .bookmarks
+            .iter()
+            .map(|key| (MarkKind::Bookmark, key.clone()))
+            .chain(
+                self.marks
+                    .iter()
+                    .map(|(&name, key)| (MarkKind::Named(name), key.clone())),
+            )
+            .chain(
+                self.notes
+                    .iter()
+                    .map(|note| (MarkKind::Note(note.text.clone()), note.entry.clone())),
+            )
+            .collect();
+        app.restore_marks(marks);
+        problems
+    }
+}
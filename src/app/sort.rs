@@ -0,0 +1,75 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::LogEntry, timestamps::TimeZoneSpec};
+
+use super::{columns::ColumnDef, levels::severity};
+
+/// The column the list is sorted by. Ties always fall back to arrival order,
+/// in both directions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    pub fn arrow(&self) -> &'static str {
+        if self.descending { "▼" } else { "▲" }
+    }
+}
+
+/// A precomputed, cheaply comparable value of the sort column. Numbers sort
+/// before text; missing values sort last whatever the direction.
+#[derive(Clone, Debug)]
+pub enum SortKey {
+    Num(f64),
+    Text(String),
+    Missing,
+}
+
+impl SortKey {
+    /// `zone` resolves timestamps written without an offset.
+    pub fn for_entry(col: &ColumnDef, entry: &LogEntry, zone: &TimeZoneSpec) -> Self {
+        if col.is_builtin("level")
+            && let Some(rank) = severity(&entry.level)
+        {
+            return SortKey::Num(f64::from(rank));
+        }
+        if col.is_builtin("timestamp")
+            && let Some(instant) = entry.time.and_then(|t| t.instant(zone))
+        {
+            return SortKey::Num(instant.timestamp_micros() as f64);
+        }
+        match col.value(entry) {
+            None => SortKey::Missing,
+            Some(text) => match text.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => SortKey::Num(n),
+                _ => SortKey::Text(text),
+            },
+        }
+    }
+
+    fn cmp_present(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Num(a), SortKey::Num(b)) => a.total_cmp(b),
+            (SortKey::Text(a), SortKey::Text(b)) => a.cmp(b),
+            (SortKey::Num(_), SortKey::Text(_)) => Ordering::Less,
+            (SortKey::Text(_), SortKey::Num(_)) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Orders two entries (by buffer index) for display.
+pub fn compare(keys: &[SortKey], descending: bool, a: usize, b: usize) -> Ordering {
+    let by_key = match (&keys[a], &keys[b]) {
+        (SortKey::Missing, SortKey::Missing) => Ordering::Equal,
+        (SortKey::Missing, _) => Ordering::Greater,
+        (_, SortKey::Missing) => Ordering::Less,
+        (ka, kb) if descending => kb.cmp_present(ka),
+        (ka, kb) => ka.cmp_present(kb),
+    };
+    by_key.then(a.cmp(&b))
+}
@@ -1,15 +1,63 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
+
+use crossterm::event::KeyEvent;
 use ratatui::widgets::ListState;
-use regex::Regex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::model::LogEntry;
+use crate::{
+    clipboard,
+    config::Profile,
+    input::{FieldMapping, SourceSpawner, SourceSpec, entry_from_value},
+    keys::KeyChord,
+    model::{EntryKey, LogEntry},
+    theme::Theme,
+    timestamps::{TimeZoneSpec, TimestampMode},
+};
 
 use super::{
-    columns::{ColumnDef, default_columns, is_reserved_column},
-    field_view::{FieldViewState, FieldZoom, collect_fields},
+    clusters::Clusters,
+    columns::{ColumnDef, default_columns, is_reserved_column, parse_computed_column},
+    commands::{self, Completion, GotoTarget, Setting},
+    correlation::{self, Breadcrumb, Correlation, SavedView},
+    dedupe::{Dedupe, DedupeBy},
+    export::{ExportFormat, ExportJob, ExportPoll, ExportSpec, TableSpec},
+    field_view::{FieldEntry, FieldViewState, FieldZoom, collect_fields},
+    filters::{EntryFilter, FilterKind, FilterLayer, FilterModes, Query, is_narrowing},
+    history::FilterHistory,
+    jumps::JumpList,
+    keymap::{Action, Keymap},
+    levels::{LevelFilter, severity},
+    live_filter::{
+        BACKGROUND_FILTER_MIN_ENTRIES, JobPoll, LIVE_FILTER_DEBOUNCE, LiveFilterJob, LivePreview,
+    },
+    marks::{MarkKind, MarkPrompt, MarkRow, Marks},
+    palette,
+    session::{DEFAULT_SESSION_FILE, Session},
+    sort::{self, SortKey, SortOrder},
+    trace::{self, TraceRow, TraceView},
 };
 
+/// What a yank action copies to the clipboard.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Yank {
+    /// The selected entry as pretty JSON.
+    Entry,
+    /// The bookmarked and named-mark entries of the list, one line each.
+    Marked,
+    FieldValue,
+    /// The selected field's jq path.
+    FieldPath,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Focus {
     List,
     Detail,
@@ -20,19 +68,86 @@ pub enum InputMode {
     Normal,
     FilterInput,
     ColumnSelect,
+    ColumnInput,
     FieldView,
+    FilterStack,
+    SavedFilters,
+    LevelSelect,
+    FieldFilter,
+    /// The `:` command line.
+    Command,
+    /// The fuzzy action palette.
+    Palette,
+    /// The list of bookmarks and named marks.
+    Bookmarks,
+    /// The note prompt for the selected entry.
+    NoteInput,
+    /// The span tree of the selected entry's trace.
+    TraceView,
+    /// The message templates mined from the entries.
+    Clusters,
 }
 
-#[derive(Clone, Copy)]
-enum SelectStrategy {
-    PreserveOrFirst,
-    Last,
+/// A one-off message for the status bar, cleared by the next key press.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notice {
+    pub text: String,
+    pub is_error: bool,
 }
 
+/// Rebuilds over at least this many candidates are spread across threads.
+const PARALLEL_FILTER_MIN: usize = 4096;
+
 pub struct App {
     pub entries: Vec<LogEntry>,
     pub filtered_indices: Vec<usize>,
+    /// Display order of `filtered_indices`; `None` keeps arrival order.
+    pub sort: Option<SortOrder>,
+    /// Sort key of every buffered entry while `sort` is set, aligned with `entries`.
+    sort_keys: Vec<SortKey>,
+    /// Folds runs of repeated entries into one row when set.
+    pub dedupe: Option<Dedupe>,
     pub columns: Vec<ColumnDef>,
+    pub timestamp_mode: TimestampMode,
+    pub time_zone: TimeZoneSpec,
+    /// Absolute sequence number (`evicted_entries` + index) of the entry
+    /// delta timestamps are measured against; `None` means the previous row.
+    pub time_reference: Option<u64>,
+    pub theme: Theme,
+    /// Key bindings, with the config's changes applied.
+    pub keymap: Keymap,
+    /// Key remaps from the config's plain `[keys]` entries.
+    pub key_remaps: HashMap<KeyChord, KeyChord>,
+    /// Profiles from the config, for when the reader detects one.
+    pub profiles: BTreeMap<String, Profile>,
+    pub active_profile: Option<String>,
+    /// Where the entries come from, as recorded in sessions.
+    pub sources: Vec<SourceSpec>,
+    /// Session file `S` saves to.
+    pub session_path: Option<PathBuf>,
+    /// Starts readers for `:source add`; `None` in tests.
+    pub spawner: Option<SourceSpawner>,
+    /// Entry to select once it arrives (restored sessions).
+    pub pending_selection: Option<EntryKey>,
+    pub marks: Marks,
+    /// Entries the selection jumped away from, for `Ctrl+O` / `Ctrl+I`.
+    pub jumps: JumpList,
+    /// Paths `*` looks for a correlation id in, in order.
+    pub correlation_fields: Vec<Vec<String>>,
+    /// Followed ids, innermost last, each with the view it was followed from.
+    pub correlation_trail: Vec<Breadcrumb>,
+    /// Message templates, mined once the template list is first opened and
+    /// kept up to date from then on.
+    pub clusters: Option<Clusters>,
+    pub cluster_rows: Vec<usize>,
+    pub cluster_state: ListState,
+    /// Only entries filed under this cluster are shown.
+    pub template_filter: Option<usize>,
+    /// Set by `m` and `'` until the mark's letter is typed.
+    pub mark_prompt: Option<MarkPrompt>,
+    pub bookmark_rows: Vec<MarkRow>,
+    pub bookmark_state: ListState,
+    pub notice: Option<Notice>,
     pub column_select_state: ListState,
     pub list_state: ListState,
     pub input_paused: bool,
@@ -40,6 +155,8 @@ pub struct App {
     pub max_entries: usize,
     pub last_list_height: usize,
     pub last_list_width: usize,
+    /// Rendered width of each column (0 when disabled), from the last frame.
+    pub last_column_widths: Vec<usize>,
     pub last_detail_height: usize,
     pub last_detail_width: usize,
     pub detail_scroll: u16,
@@ -51,14 +168,49 @@ pub struct App {
     pub zoom: Option<Focus>,
     pub autoscroll: bool,
     pub filter_query: String,
-    pub filter_regex: Option<Regex>,
+    pub filter_compiled: Option<Query>,
     pub filter_error: Option<String>,
+    pub filter_modes: FilterModes,
+    pub filter_query_modes: FilterModes,
+    pub filter_stack: Vec<FilterLayer>,
+    /// Entries each layer is the first to reject, evaluating the stack top to
+    /// bottom; aligned with `filter_stack` while its popup is open.
+    pub layer_drops: Vec<usize>,
+    pub level_filter: LevelFilter,
+    pub level_counts: BTreeMap<String, usize>,
+    pub level_select_state: ListState,
+    pub field_filter_fields: Vec<FieldEntry>,
+    pub field_filter_state: ListState,
+    pub field_filter_kind: FilterKind,
+    pub filter_stack_state: ListState,
+    pub filter_input_target: Option<FilterKind>,
+    pub filter_history: FilterHistory,
+    pub history_cursor: Option<usize>,
+    pub history_draft: (String, FilterModes),
+    pub history_search: Option<String>,
+    pub saved_filter_state: ListState,
+    pub live_preview: Option<LivePreview>,
+    pub live_filter_error: Option<String>,
+    pub live_filter_due: Option<Instant>,
+    pub live_filter_job: Option<LiveFilterJob>,
+    pub export_job: Option<ExportJob>,
+    pub evicted_entries: u64,
     pub input_mode: InputMode,
     pub filter_buffer: String,
+    pub column_buffer: String,
+    pub note_buffer: String,
+    pub command_buffer: String,
+    pub command_completion: Option<Completion>,
+    pub command_history: Vec<String>,
+    pub command_history_cursor: Option<usize>,
+    pub palette_query: String,
+    pub palette_matches: Vec<Action>,
+    pub palette_state: ListState,
     pub force_redraw: bool,
     pub max_row_width: usize,
     pub horiz_offset: usize,
     pub field_view: Option<FieldViewState>,
+    pub trace_view: Option<TraceView>,
     pub field_detail_scroll: u16,
     pub field_detail_total_lines: usize,
     pub last_field_detail_height: usize,
@@ -80,7 +232,37 @@ impl App {
         Self {
             entries: Vec::new(),
             filtered_indices: Vec::new(),
+            sort: None,
+            sort_keys: Vec::new(),
+            dedupe: None,
             columns: default_columns(),
+            timestamp_mode: TimestampMode::default(),
+            time_zone: TimeZoneSpec::default(),
+            time_reference: None,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            key_remaps: HashMap::new(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            sources: Vec::new(),
+            session_path: None,
+            spawner: None,
+            pending_selection: None,
+            marks: Marks::default(),
+            jumps: JumpList::default(),
+            correlation_fields: correlation::DEFAULT_FIELDS
+                .iter()
+                .map(|field| vec![field.to_string()])
+                .collect(),
+            correlation_trail: Vec::new(),
+            clusters: None,
+            cluster_rows: Vec::new(),
+            cluster_state: ListState::default(),
+            template_filter: None,
+            mark_prompt: None,
+            bookmark_rows: Vec::new(),
+            bookmark_state: ListState::default(),
+            notice: None,
             column_select_state,
             list_state,
             input_paused: false,
@@ -88,6 +270,7 @@ impl App {
             max_entries,
             last_list_height: 0,
             last_list_width: 0,
+            last_column_widths: Vec::new(),
             last_detail_height: 0,
             last_detail_width: 0,
             detail_scroll: 0,
@@ -99,14 +282,47 @@ impl App {
             zoom: None,
             autoscroll: true,
             filter_query: String::new(),
-            filter_regex: None,
+            filter_compiled: None,
             filter_error: None,
+            filter_modes: FilterModes::default(),
+            filter_query_modes: FilterModes::default(),
+            filter_stack: Vec::new(),
+            layer_drops: Vec::new(),
+            level_filter: LevelFilter::default(),
+            level_counts: BTreeMap::new(),
+            level_select_state: ListState::default(),
+            field_filter_fields: Vec::new(),
+            field_filter_state: ListState::default(),
+            field_filter_kind: FilterKind::Include,
+            filter_stack_state: ListState::default(),
+            filter_input_target: None,
+            filter_history: FilterHistory::default(),
+            history_cursor: None,
+            history_draft: (String::new(), FilterModes::default()),
+            history_search: None,
+            saved_filter_state: ListState::default(),
+            live_preview: None,
+            live_filter_error: None,
+            live_filter_due: None,
+            live_filter_job: None,
+            export_job: None,
+            evicted_entries: 0,
             input_mode: InputMode::Normal,
             filter_buffer: String::new(),
+            column_buffer: String::new(),
+            note_buffer: String::new(),
+            command_buffer: String::new(),
+            command_completion: None,
+            command_history: Vec::new(),
+            command_history_cursor: None,
+            palette_query: String::new(),
+            palette_matches: Vec::new(),
+            palette_state: ListState::default(),
             force_redraw: true,
             max_row_width: 0,
             horiz_offset: 0,
             field_view: None,
+            trace_view: None,
             field_detail_scroll: 0,
             field_detail_total_lines: 0,
             last_field_detail_height: 0,
@@ -120,6 +336,470 @@ impl App {
         }
     }
 
+    pub fn notify(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice {
+            text: text.into(),
+            is_error: false,
+        });
+    }
+
+    pub fn notify_error(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice {
+            text: text.into(),
+            is_error: true,
+        });
+    }
+
+    /// `S`: writes the current session to `session_path` (or
+    /// `logtui-session.json` in the working directory).
+    pub fn save_session(&mut self) {
+        let path = self
+            .session_path
+            .get_or_insert_with(|| PathBuf::from(DEFAULT_SESSION_FILE))
+            .clone();
+        match Session::capture(self).save(&path) {
+            Ok(()) => self.notify(format!("Session saved to {}", path.display())),
+            Err(err) => self.notify_error(format!("Saving session failed: {err:#}")),
+        }
+    }
+
+    /// Starts writing the filtered entries (or the rows from the first to the
+    /// last marked entry, see `marked_range`) to `spec.path` in the
+    /// background.
+    pub fn export(&mut self, spec: &ExportSpec) -> Result<(), String> {
+        if let Some(job) = &self.export_job {
+            return Err(format!(
+                "Still exporting to {}; try again when it finishes",
+                job.path.display()
+            ));
+        }
+        let mut notes = Vec::new();
+        let rows = if spec.format == ExportFormat::Timeline {
+            if spec.range {
+                return Err("The timeline covers every note; drop 'range'".into());
+            }
+            let mut noted: Vec<(usize, &str)> = self
+                .marks
+                .notes()
+                .filter_map(|(seq, text)| Some((self.index_of_seq(seq)?, text)))
+                .collect();
+            if noted.is_empty() {
+                return Err("No notes yet (n adds one to the selected entry)".into());
+            }
+            noted.sort_by_key(|&(idx, _)| {
+                let at = self.entries[idx]
+                    .time
+                    .and_then(|t| t.instant(&self.time_zone));
+                (at.is_none(), at, idx)
+            });
+            notes = noted.iter().map(|(_, text)| text.to_string()).collect();
+            noted.into_iter().map(|(idx, _)| idx).collect()
+        } else if spec.range {
+            self.marked_range()?.to_vec()
+        } else {
+            self.matched_indices().to_vec()
+        };
+        let rows: Vec<LogEntry> = rows.iter().map(|&idx| self.entries[idx].clone()).collect();
+        let table = TableSpec {
+            columns: self.columns.iter().filter(|c| c.enabled).cloned().collect(),
+            mode: self.timestamp_mode,
+            zone: self.time_zone,
+            reference: self
+                .time_reference_index()
+                .and_then(|idx| self.entries[idx].time)
+                .and_then(|t| t.instant(&self.time_zone)),
+            notes,
+        };
+        let file = std::fs::File::create(&spec.path)
+            .map_err(|err| format!("Cannot write {}: {err}", spec.path.display()))?;
+        self.notify(format!(
+            "Exporting {} entries to {}…",
+            rows.len(),
+            spec.path.display()
+        ));
+        self.export_job = Some(ExportJob::spawn(file, spec, rows, table));
+        Ok(())
+    }
+
+    /// Whether the entry at buffer index `idx` is bookmarked or carries a
+    /// named mark.
+    fn is_marked(&self, idx: usize) -> bool {
+        let seq = self.evicted_entries + idx as u64;
+        self.marks.is_bookmarked(seq) || self.marks.name_of(seq).is_some()
+    }
+
+    /// The filtered entries from the first marked one to the last, counting
+    /// both bookmarks and named marks.
+    fn marked_range(&self) -> Result<&[usize], String> {
+        let rows = self.matched_indices();
+        let first = rows
+            .iter()
+            .position(|&idx| self.is_marked(idx))
+            .ok_or("No marked entries in the list (b or m<letter> marks one)")?;
+        let last = rows
+            .iter()
+            .rposition(|&idx| self.is_marked(idx))
+            .unwrap_or(first);
+        Ok(&rows[first..=last])
+    }
+
+    /// What `target` copies, and how to describe it in the status bar.
+    pub fn yank_text(&self, target: Yank) -> Result<(String, String), String> {
+        let field = || {
+            self.field_view
+                .as_ref()
+                .and_then(|fv| fv.selected_field())
+                .ok_or("No field selected")
+        };
+        match target {
+            Yank::Entry => {
+                let entry = self.current_entry().ok_or("No entry selected")?;
+                let text = serde_json::to_string_pretty(&*entry.raw).map_err(|e| e.to_string())?;
+                Ok((text, "entry".into()))
+            }
+            Yank::Marked => {
+                let text: Vec<_> = self
+                    .matched_indices()
+                    .iter()
+                    .filter(|&&idx| self.is_marked(idx))
+                    .map(|&idx| self.entries[idx].original_line())
+                    .collect();
+                if text.is_empty() {
+                    return Err("No marked entries in the list (b or m<letter> marks one)".into());
+                }
+                Ok((text.join("\n"), format!("{} marked entries", text.len())))
+            }
+            Yank::FieldValue => {
+                let field = field()?;
+                let text = match &field.value {
+                    Value::String(s) => s.clone(),
+                    value => serde_json::to_string_pretty(value).map_err(|e| e.to_string())?,
+                };
+                Ok((text, format!("value of {}", field.path)))
+            }
+            Yank::FieldPath => {
+                let field = field()?;
+                Ok((field.jq_path.clone(), "path".into()))
+            }
+        }
+    }
+
+    /// Copies `target` to the clipboard and says so in the status bar.
+    pub fn yank(&mut self, target: Yank) {
+        let result = self.yank_text(target).and_then(|(text, what)| {
+            let used = clipboard::copy(&text).map_err(|e| format!("Copy failed: {e}"))?;
+            Ok(format!("Copied {what} ({} bytes) via {used}", text.len()))
+        });
+        match result {
+            Ok(message) => self.notify(message),
+            Err(err) => self.notify_error(err),
+        }
+    }
+
+    /// Reports progress of a running export and finishes it when done.
+    pub fn tick_export(&mut self) {
+        let Some(job) = &self.export_job else {
+            return;
+        };
+        let path = job.path.display().to_string();
+        match job.poll() {
+            ExportPoll::Pending => {}
+            ExportPoll::Progress(written) => {
+                let total = job.total;
+                self.notify(format!("Exporting to {path}: {written}/{total} entries"));
+            }
+            ExportPoll::Done => {
+                let total = job.total;
+                self.export_job = None;
+                self.notify(format!("Exported {total} entries to {path}"));
+            }
+            ExportPoll::Failed(err) => {
+                self.export_job = None;
+                self.notify_error(format!("Export to {path} failed: {err}"));
+            }
+        }
+    }
+
+    pub fn begin_command(&mut self) {
+        self.command_buffer.clear();
+        self.command_completion = None;
+        self.command_history_cursor = None;
+        self.input_mode = InputMode::Command;
+    }
+
+    pub fn cancel_command(&mut self) {
+        self.command_buffer.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Closes the command line and returns what was typed, remembering it
+    /// for Up/Down.
+    pub fn take_command(&mut self) -> String {
+        self.input_mode = InputMode::Normal;
+        let command = std::mem::take(&mut self.command_buffer);
+        let command = command.trim().to_string();
+        if !command.is_empty() && self.command_history.last() != Some(&command) {
+            self.command_history.push(command.clone());
+        }
+        command
+    }
+
+    /// Up/Down in the command line.
+    pub fn command_history_step(&mut self, older: bool) {
+        let len = self.command_history.len();
+        let cursor = match (self.command_history_cursor, older) {
+            (None, true) if len > 0 => Some(len - 1),
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < len => Some(i + 1),
+            (Some(_), false) => None,
+            (cursor, _) => cursor,
+        };
+        self.command_history_cursor = cursor;
+        self.command_buffer = cursor.map_or_else(String::new, |i| self.command_history[i].clone());
+        self.command_completion = None;
+    }
+
+    /// Tab in the command line: completes the last word, cycling through
+    /// the candidates on repeated presses.
+    pub fn complete_command(&mut self) {
+        let completion = match self.command_completion.take() {
+            Some(mut c) if c.applied == self.command_buffer && c.candidates.len() > 1 => {
+                c.index = (c.index + 1) % c.candidates.len();
+                c
+            }
+            _ => {
+                let (start, candidates) = commands::complete(self, &self.command_buffer);
+                if candidates.is_empty() {
+                    return;
+                }
+                Completion {
+                    start,
+                    candidates,
+                    index: 0,
+                    applied: String::new(),
+                }
+            }
+        };
+        let candidate = &completion.candidates[completion.index];
+        self.command_buffer.truncate(completion.start);
+        self.command_buffer.push_str(candidate);
+        // A single match is final, so the next argument can follow.
+        if completion.candidates.len() == 1 && !candidate.ends_with(['/', '=']) {
+            self.command_buffer.push(' ');
+        }
+        self.command_completion = Some(Completion {
+            applied: self.command_buffer.clone(),
+            ..completion
+        });
+    }
+
+    /// `:columns add`: shows the field column at a dotted path, or adds a
+    /// computed one for `name = jq` or a bare jq expression.
+    pub fn add_column_spec(&mut self, spec: &str) -> Result<(), String> {
+        if spec.starts_with('.') || spec.contains('=') {
+            let column = parse_computed_column(spec)?;
+            if self.columns.iter().any(|c| c.name == column.name) {
+                return Err(format!("column '{}' already exists", column.name));
+            }
+            self.columns.push(column);
+        } else {
+            let path: Vec<String> = spec.split('.').map(str::to_string).collect();
+            match self
+                .columns
+                .iter_mut()
+                .find(|c| c.name == spec || (c.expr.is_none() && c.path == path))
+            {
+                Some(col) => col.enabled = true,
+                None => {
+                    let mut col = ColumnDef::new(spec.to_string(), path);
+                    col.enabled = true;
+                    self.columns.push(col);
+                }
+            }
+        }
+        self.force_redraw = true;
+        Ok(())
+    }
+
+    /// `:columns remove`: deletes a computed column, hides a field column.
+    pub fn remove_column_named(&mut self, name: &str) -> Result<(), String> {
+        let idx = self
+            .columns
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| format!("no column '{name}'"))?;
+        if self.columns[idx].expr.is_some() {
+            self.columns.remove(idx);
+            if self.sort.as_ref().is_some_and(|o| o.column == name) {
+                self.set_sort(None);
+            }
+            let last = self.columns.len().checked_sub(1);
+            if self.column_select_state.selected() > last {
+                self.column_select_state.select(last);
+            }
+        } else {
+            self.columns[idx].enabled = false;
+        }
+        self.force_redraw = true;
+        Ok(())
+    }
+
+    /// `:goto`: selects a row of the filtered list, or the earliest visible
+    /// entry at or after a time.
+    pub fn goto(&mut self, target: &GotoTarget) -> Result<(), String> {
+        if let GotoTarget::Row(row) = *target {
+            let len = self.filtered_indices.len();
+            if row == 0 || row > len {
+                return Err(format!("row {row} out of range (1-{len})"));
+            }
+            self.remember_jump();
+            self.autoscroll = false;
+            self.list_state.select(Some(row - 1));
+            self.reset_detail_position();
+            self.force_redraw = true;
+            self.update_list_offset();
+            return Ok(());
+        }
+        let selected = self
+            .current_entry()
+            .or_else(|| {
+                let idx = *self.filtered_indices.first()?;
+                self.entries.get(idx).cloned()
+            })
+            .and_then(|e| e.time?.instant(&self.time_zone));
+        let target = commands::goto_instant(target, &self.time_zone, selected)
+            .ok_or("no timestamp to go by")?;
+        let found = self
+            .matched_indices()
+            .iter()
+            .filter_map(|&idx| {
+                let at = self.entries[idx].time?.instant(&self.time_zone)?;
+                (at >= target).then_some((at, idx))
+            })
+            .min();
+        let Some((_, idx)) = found else {
+            return Err(format!("no entry at or after {target}"));
+        };
+        self.remember_jump();
+        self.autoscroll = false;
+        self.select_entry(idx);
+        Ok(())
+    }
+
+    /// `:set`.
+    pub fn apply_setting(&mut self, setting: Setting) {
+        match setting {
+            Setting::Wrap(flag) => {
+                self.detail_wrap = flag.apply(self.detail_wrap);
+                self.reset_detail_position();
+            }
+            Setting::FieldWrap(flag) => {
+                self.field_detail_wrap = flag.apply(self.field_detail_wrap);
+                self.reset_field_detail_position();
+            }
+            Setting::Autoscroll(flag) => {
+                if flag.apply(self.autoscroll) != self.autoscroll {
+                    self.toggle_autoscroll();
+                }
+            }
+            Setting::Timestamps(mode) => self.timestamp_mode = mode,
+            Setting::TimeZone(zone) => {
+                self.time_zone = zone;
+                // Timestamp sort keys depend on the zone for naive times.
+                self.set_sort(self.sort.clone());
+            }
+        }
+        self.force_redraw = true;
+    }
+
+    /// `:source add`: reads another file into the same list.
+    pub fn add_source(&mut self, path: PathBuf) -> Result<(), String> {
+        if !path.is_file() {
+            return Err(format!("{} is not a readable file", path.display()));
+        }
+        let spec = SourceSpec::File(path.clone());
+        if self.sources.contains(&spec) {
+            return Err(format!("{} is already a source", path.display()));
+        }
+        let spawner = self.spawner.as_ref().ok_or("no reader to add to")?;
+        spawner.spawn(path);
+        self.sources.push(spec);
+        Ok(())
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette_query.clear();
+        self.refresh_palette();
+        self.input_mode = InputMode::Palette;
+    }
+
+    /// Types into (`Some`) or deletes from (`None`) the palette query.
+    pub fn palette_edit(&mut self, c: Option<char>) {
+        match c {
+            Some(c) => self.palette_query.push(c),
+            None => {
+                self.palette_query.pop();
+            }
+        }
+        self.refresh_palette();
+    }
+
+    fn refresh_palette(&mut self) {
+        self.palette_matches = palette::matches(&self.palette_query);
+        let first = (!self.palette_matches.is_empty()).then_some(0);
+        self.palette_state.select(first);
+    }
+
+    pub fn select_palette_row(&mut self, delta: isize) {
+        let len = self.palette_matches.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.palette_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        self.palette_state.select(Some(next));
+    }
+
+    /// Closes the palette and returns the chosen action.
+    pub fn take_palette_action(&mut self) -> Option<Action> {
+        self.input_mode = InputMode::Normal;
+        let idx = self.palette_state.selected()?;
+        self.palette_matches.get(idx).copied()
+    }
+
+    /// Selects the entry with `key` now if it is visible, or as soon as it
+    /// arrives. Stops following the tail so the selection stays put.
+    pub fn select_by_key(&mut self, key: EntryKey) {
+        self.autoscroll = false;
+        match self.entries.iter().position(|e| e.key() == key) {
+            Some(idx) => {
+                self.select_entry(idx);
+            }
+            None => self.pending_selection = Some(key),
+        }
+    }
+
+    /// Selects the entry at buffer index `idx` if the filters show it,
+    /// expanding the repeats it is folded into.
+    pub fn select_entry(&mut self, idx: usize) -> bool {
+        if !self.filtered_indices.contains(&idx)
+            && let Some(dedupe) = self.dedupe.as_mut()
+            && let Some(run) = dedupe.run_of(idx)
+        {
+            dedupe.set_expanded(run, true, self.evicted_entries);
+            let matched = std::mem::take(&mut dedupe.matched);
+            self.set_filtered(matched, true);
+        }
+        let Some(pos) = self.filtered_indices.iter().position(|&i| i == idx) else {
+            return false;
+        };
+        self.list_state.select(Some(pos));
+        self.reset_detail_position();
+        self.force_redraw = true;
+        self.update_list_offset();
+        true
+    }
+
     pub fn ingest(&mut self, entry: LogEntry) {
         if self.input_paused {
             return;
@@ -133,43 +813,154 @@ impl App {
     }
 
     pub fn push(&mut self, entry: LogEntry) {
+        let pending_hit = self
+            .pending_selection
+            .as_ref()
+            .is_some_and(|key| entry.timestamp == key.timestamp && entry.key() == *key);
+        let seq = self.evicted_entries + self.entries.len() as u64;
+        self.marks.resolve(&entry, seq);
+        self.push_entry(entry);
+        self.marks.evict_before(self.evicted_entries);
+        if pending_hit {
+            self.pending_selection = None;
+            self.select_entry(self.entries.len() - 1);
+        }
+    }
+
+    fn push_entry(&mut self, entry: LogEntry) {
         if self.entries.len() == self.max_entries {
-            self.entries.remove(0);
-            if let Some(sel) = self.list_state.selected() {
-                if sel > 0 {
-                    self.list_state.select(Some(sel - 1));
-                } else {
-                    self.list_state.select(Some(0));
+            let selected_entry = self.selected_entry_index();
+            let evicted = self.entries.remove(0);
+            self.evicted_entries += 1;
+            if self.sort.is_some() {
+                self.sort_keys.remove(0);
+            }
+            let dropped_cluster = self
+                .clusters
+                .as_mut()
+                .and_then(|clusters| clusters.evict_first(&evicted));
+            if let Some(id) = dropped_cluster {
+                self.cluster_rows.retain(|&row| row != id);
+                let last = self.cluster_rows.len().checked_sub(1);
+                let selected = self.cluster_state.selected();
+                self.cluster_state
+                    .select(last.map(|last| selected.unwrap_or(0).min(last)));
+            }
+            if let Some(count) = self
+                .level_counts
+                .get_mut(&evicted.level.to_ascii_uppercase())
+            {
+                *count = count.saturating_sub(1);
+            }
+            match self.dedupe.as_mut() {
+                Some(dedupe) => {
+                    dedupe.evict_first(&mut self.filtered_indices, self.evicted_entries)
+                }
+                None => {
+                    self.filtered_indices = self
+                        .filtered_indices
+                        .iter()
+                        .filter_map(|idx| idx.checked_sub(1))
+                        .collect();
                 }
             }
-            self.filtered_indices = self
-                .filtered_indices
-                .iter()
-                .filter_map(|idx| idx.checked_sub(1))
-                .collect();
+            // Keep the same entry selected; if it was the evicted one, stay
+            // at the same row.
+            if let Some(sel) = self.list_state.selected() {
+                let kept = selected_entry
+                    .and_then(|idx| idx.checked_sub(1))
+                    .and_then(|idx| self.filtered_indices.iter().position(|&i| i == idx));
+                let last = self.filtered_indices.len().saturating_sub(1);
+                self.list_state
+                    .select(Some(kept.unwrap_or(sel.saturating_sub(1)).min(last)));
+            }
             self.update_list_offset();
+            if dropped_cluster.is_some() && dropped_cluster == self.template_filter {
+                self.notify("Template filter cleared: its entries left the buffer");
+                self.clear_template_filter();
+            }
         }
         self.discover_columns(&entry.raw);
+        *self
+            .level_counts
+            .entry(entry.level.to_ascii_uppercase())
+            .or_default() += 1;
+        if let Some(order) = &self.sort
+            && let Some(col) = self.columns.iter().find(|c| c.name == order.column)
+        {
+            self.sort_keys
+                .push(SortKey::for_entry(col, &entry, &self.time_zone));
+        }
+        if let Some(dedupe) = self.dedupe.as_mut() {
+            dedupe.push(&entry);
+        }
+        if let Some(clusters) = self.clusters.as_mut()
+            && let Some(pos) = clusters.add(&entry, &self.time_zone)
+        {
+            self.cluster_rows.push(pos);
+        }
         self.entries.push(entry);
         let new_idx = self.entries.len().saturating_sub(1);
-        if !self.autoscroll {
-            if self.matches_filter(self.entries.last().expect("just pushed entry")) {
-                self.filtered_indices.push(new_idx);
-                if self.list_state.selected().is_none() {
-                    self.list_state
-                        .select(Some(self.filtered_indices.len().saturating_sub(1)));
-                    self.reset_detail_position();
-                    self.horiz_offset = 0;
-                    self.update_list_offset();
+        // Only the new entry needs evaluating and placing; rescanning,
+        // re-sorting or refolding the buffer on every line made tailing
+        // quadratic.
+        if !self.matches_filter(new_idx) {
+            return;
+        }
+        let pos = self.sorted_position(self.matched_indices(), new_idx);
+        let selected_entry = self.selected_entry_index();
+        if let Some(dedupe) = self.dedupe.as_mut() {
+            if pos == dedupe.matched.len() {
+                // Only the last run can grow, so the rest stays folded.
+                if dedupe.append(new_idx, self.evicted_entries) {
+                    self.filtered_indices.push(new_idx);
                 }
+            } else {
+                // Landing inside the sorted list can split or join runs.
+                let mut matched = std::mem::take(&mut dedupe.matched);
+                matched.insert(pos, new_idx);
+                self.filtered_indices = dedupe.fold(matched, self.evicted_entries);
             }
-        } else {
-            let strategy = SelectStrategy::Last;
-            self.rebuild_filtered(Some(strategy), false);
-            self.horiz_offset = 0;
+            match selected_entry {
+                Some(idx) if !self.autoscroll => {
+                    let row = self.row_of_entry(idx);
+                    self.list_state.select(row);
+                }
+                _ => {
+                    if let Some(row) = self.row_of_entry(new_idx) {
+                        self.select_new_row(row);
+                    }
+                }
+            }
+            return;
+        }
+        self.filtered_indices.insert(pos, new_idx);
+        match self.list_state.selected() {
+            // Follow the entry that just arrived, wherever the sort put it.
+            _ if self.autoscroll => self.select_new_row(pos),
+            Some(sel) if pos <= sel => self.list_state.select(Some(sel + 1)),
+            Some(_) => {}
+            None => self.select_new_row(pos),
         }
     }
 
+    /// Where `idx` goes among `rows`, which are in display order.
+    fn sorted_position(&self, rows: &[usize], idx: usize) -> usize {
+        match &self.sort {
+            Some(order) => rows.partition_point(|&row| {
+                sort::compare(&self.sort_keys, order.descending, row, idx).is_lt()
+            }),
+            None => rows.len(),
+        }
+    }
+
+    fn select_new_row(&mut self, row: usize) {
+        self.list_state.select(Some(row));
+        self.reset_detail_position();
+        self.horiz_offset = 0;
+        self.update_list_offset();
+    }
+
     pub fn next(&mut self) {
         if self.filtered_indices.is_empty() {
             return;
@@ -221,6 +1012,7 @@ impl App {
     }
 
     pub fn select_last(&mut self) {
+        self.remember_jump();
         if self.filtered_indices.is_empty() {
             self.list_state.select(None);
         } else {
@@ -233,6 +1025,7 @@ impl App {
     }
 
     pub fn select_first(&mut self) {
+        self.remember_jump();
         if self.filtered_indices.is_empty() {
             self.list_state.select(None);
         } else {
@@ -338,441 +1131,3766 @@ impl App {
         self.column_select_state.select(Some(new_idx));
     }
 
+    fn selected_column_mut(&mut self) -> Option<&mut ColumnDef> {
+        let idx = self.column_select_state.selected()?;
+        self.columns.get_mut(idx)
+    }
+
+    pub fn cycle_column_width(&mut self) {
+        if let Some(col) = self.selected_column_mut() {
+            col.width = col.width.cycled();
+        }
+    }
+
+    /// Makes the selected column `delta` cells wider (fixed width from now on).
+    pub fn resize_column(&mut self, delta: i32) {
+        let Some(idx) = self.column_select_state.selected() else {
+            return;
+        };
+        let current = self.last_column_widths.get(idx).copied().unwrap_or(0);
+        if let Some(col) = self.columns.get_mut(idx) {
+            let current = if current == 0 {
+                col.name.len()
+            } else {
+                current
+            };
+            col.resize(current, delta);
+        }
+    }
+
+    pub fn toggle_column_sticky(&mut self) {
+        if let Some(col) = self.selected_column_mut() {
+            col.sticky = !col.sticky;
+        }
+    }
+
+    /// Opens the prompt for a computed column (`name = <jq expression>`).
+    pub fn begin_column_input(&mut self) {
+        self.column_buffer.clear();
+        self.filter_error = None;
+        self.input_mode = InputMode::ColumnInput;
+    }
+
+    pub fn cancel_column_input(&mut self) {
+        self.column_buffer.clear();
+        self.filter_error = None;
+        self.input_mode = InputMode::ColumnSelect;
+    }
+
+    /// Adds the prompt's computed column after the selected one. On a parse
+    /// error the prompt stays open with the error in the status line.
+    pub fn submit_column_input(&mut self) {
+        match parse_computed_column(&self.column_buffer) {
+            Ok(column) => {
+                let idx = self
+                    .column_select_state
+                    .selected()
+                    .map_or(self.columns.len(), |i| (i + 1).min(self.columns.len()));
+                self.columns.insert(idx, column);
+                self.column_select_state.select(Some(idx));
+                self.column_buffer.clear();
+                self.filter_error = None;
+                self.input_mode = InputMode::ColumnSelect;
+            }
+            Err(err) => self.filter_error = Some(err),
+        }
+    }
+
+    /// Deletes the selected column if it is a computed one; field columns are
+    /// only toggled off.
+    pub fn remove_computed_column(&mut self) {
+        let Some(idx) = self.column_select_state.selected() else {
+            return;
+        };
+        if self.columns.get(idx).is_none_or(|c| c.expr.is_none()) {
+            return;
+        }
+        let removed = self.columns.remove(idx);
+        if self.sort.as_ref().is_some_and(|o| o.column == removed.name) {
+            self.set_sort(None);
+        }
+        let len = self.columns.len();
+        self.column_select_state
+            .select((len > 0).then(|| idx.min(len - 1)));
+    }
+
+    /// Compiles `pattern` with the current `filter_modes` and makes it the main filter.
     pub fn apply_filter(&mut self, pattern: &str) {
+        self.remember_jump();
+        let shown = self.filter_query.clone();
+        let shown_modes = self.filter_query_modes;
+        self.apply_filter_over(pattern, &shown, shown_modes);
+    }
+
+    /// Commits `pattern` as the main filter while the list currently shows the
+    /// results for `shown` compiled with `shown_modes`. Returns false if the
+    /// pattern did not compile.
+    fn apply_filter_over(&mut self, pattern: &str, shown: &str, shown_modes: FilterModes) -> bool {
+        let modes = self.filter_modes;
         if pattern.is_empty() {
             self.filter_query.clear();
-            self.filter_regex = None;
+            self.filter_compiled = None;
+            self.filter_query_modes = modes;
             self.filter_error = None;
-            self.rebuild_filtered(Some(SelectStrategy::PreserveOrFirst), false);
-            return;
+            self.rebuild_filtered(false);
+            return true;
         }
 
-        match Regex::new(pattern) {
-            Ok(re) => {
+        match modes.compile_query(pattern) {
+            Ok(query) => {
                 self.filter_query = pattern.to_string();
-                self.filter_regex = Some(re);
+                self.filter_compiled = Some(query);
+                self.filter_query_modes = modes;
                 self.filter_error = None;
-                self.rebuild_filtered(Some(SelectStrategy::PreserveOrFirst), false);
+                self.refilter(shown_modes == modes && is_narrowing(Some(shown), pattern, modes));
+                self.filter_history.record(pattern, modes);
+                self.persist_filter_history();
+                true
             }
             Err(err) => {
-                self.filter_error = Some(err.to_string());
+                self.filter_error = Some(err);
+                false
             }
         }
     }
 
-    pub fn enter_field_view(&mut self) {
-        let Some(entry) = self.current_entry() else {
-            return;
+    /// Sets the startup filter from the config without recording it in the
+    /// history.
+    pub fn set_default_filter(&mut self, pattern: &str) -> Result<(), String> {
+        let query = match pattern {
+            "" => None,
+            _ => Some(self.filter_modes.compile_query(pattern)?),
         };
-        let fields = collect_fields(&entry.raw);
-        if fields.is_empty() {
-            return;
-        }
-        let mut list_state = ListState::default();
-        list_state.select(Some(0));
-        let filtered_indices = (0..fields.len()).collect();
-        self.field_view = Some(FieldViewState {
-            fields,
-            filtered_indices,
-            list_state,
-            filter: String::new(),
-        });
-        self.reset_field_detail_position();
-        self.field_detail_total_lines = 0;
-        self.last_field_detail_height = 0;
-        self.last_field_detail_width = 0;
-        self.field_detail_max_line_width = 0;
-        self.field_zoom = None;
-        self.input_mode = InputMode::FieldView;
-        self.force_redraw = true;
+        self.filter_query = pattern.to_string();
+        self.filter_compiled = query;
+        self.filter_query_modes = self.filter_modes;
+        self.rebuild_filtered(false);
+        Ok(())
     }
 
-    pub fn exit_field_view(&mut self) {
-        self.field_view = None;
-        self.reset_field_detail_position();
-        self.field_detail_total_lines = 0;
-        self.last_field_detail_height = 0;
-        self.last_field_detail_width = 0;
-        self.field_detail_max_line_width = 0;
-        self.field_zoom = None;
-        self.input_mode = InputMode::Normal;
+    /// Stacks a filter layer from the config; errors are returned instead of
+    /// shown in the status bar.
+    pub fn add_default_layer(&mut self, pattern: &str, kind: FilterKind) -> Result<(), String> {
+        let query = self.filter_modes.compile_query(pattern)?;
+        self.filter_stack.push(FilterLayer::new(
+            pattern.to_string(),
+            query,
+            self.filter_modes,
+            kind,
+        ));
+        self.rebuild_filtered(false);
+        Ok(())
     }
 
-    pub fn toggle_autoscroll(&mut self) {
-        self.autoscroll = !self.autoscroll;
-        self.force_redraw = true;
-        if self.autoscroll {
-            self.select_last();
-        } else {
-            self.list_scroll_offset = self.list_state.offset();
-            *self.list_state.offset_mut() = self.list_scroll_offset;
+    /// Replaces the column set, keeping fields already discovered in the
+    /// buffer available in the selector.
+    pub fn set_columns(&mut self, columns: Vec<ColumnDef>) {
+        self.columns = columns;
+        let raws: Vec<Arc<Value>> = self.entries.iter().map(|e| e.raw.clone()).collect();
+        for raw in &raws {
+            self.discover_columns(raw);
         }
+        self.column_select_state
+            .select((!self.columns.is_empty()).then_some(0));
+        self.set_sort(self.sort.clone());
     }
 
-    fn matches_filter(&self, entry: &LogEntry) -> bool {
-        if let Some(re) = &self.filter_regex {
-            let hay = format!(
-                "{} {} {} {}",
-                entry.timestamp, entry.level, entry.message, entry.raw
-            );
-            re.is_match(&hay)
-        } else {
-            true
+    /// Switches to a profile the reader detected: entries read so far are
+    /// re-parsed with its field mapping and its view settings are applied.
+    pub fn activate_profile(&mut self, name: &str, fields: &FieldMapping) {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            return;
+        };
+        self.reparse_entries(fields);
+        if let Some(spawner) = &mut self.spawner {
+            spawner.fields = fields.clone();
+        }
+        if let Err(err) = profile.apply(self) {
+            self.filter_error = Some(format!("profile {name}: {err:#}"));
         }
+        self.active_profile = Some(name.to_string());
+        self.force_redraw = true;
     }
 
-    fn selected_entry_index(&self) -> Option<usize> {
-        let idx = self.list_state.selected()?;
-        self.filtered_indices.get(idx).copied()
+    fn reparse_entries(&mut self, fields: &FieldMapping) {
+        for entry in &mut self.entries {
+            // Text lines and reader errors have nothing to re-map.
+            if entry.raw.is_object() && entry.level != "PARSE" {
+                let line = entry.line.take();
+                *entry = entry_from_value(Value::clone(&entry.raw), fields);
+                entry.line = line;
+            }
+        }
+        self.level_counts.clear();
+        for entry in &self.entries {
+            *self
+                .level_counts
+                .entry(entry.level.to_ascii_uppercase())
+                .or_default() += 1;
+        }
+        if let Some(dedupe) = &self.dedupe {
+            self.dedupe = Some(Dedupe::new(dedupe.by.clone(), &self.entries));
+        }
+        // Templates were mined from the old messages.
+        self.clusters = None;
+        self.template_filter = None;
+        self.set_sort(self.sort.clone());
+        self.rebuild_filtered(false);
     }
 
-    fn update_list_offset(&mut self) {
-        if self.autoscroll {
-            self.list_scroll_offset = self.list_state.offset();
+    /// The key `key` stands for after applying the config's remaps.
+    pub fn remap_key(&self, key: KeyEvent) -> KeyEvent {
+        match self.key_remaps.get(&KeyChord::from_event(&key)) {
+            Some(to) => to.to_event(),
+            None => key,
+        }
+    }
+
+    pub fn begin_filter_input(&mut self, prefill: String) {
+        // The new filter may move the selection; Ctrl+O comes back here.
+        self.remember_jump();
+        self.filter_input_target = None;
+        self.filter_buffer = prefill;
+        self.filter_error = None;
+        self.history_cursor = None;
+        self.history_search = None;
+        self.filter_modes = self.filter_query_modes;
+        self.input_mode = InputMode::FilterInput;
+        if self.filter_buffer != self.filter_query {
+            self.schedule_live_filter();
+        }
+    }
+
+    /// Re-evaluates the prompt buffer once typing pauses for `LIVE_FILTER_DEBOUNCE`.
+    pub fn schedule_live_filter(&mut self) {
+        if self.filter_input_target.is_some() {
             return;
         }
-        let Some(selected) = self.list_state.selected() else {
-            self.list_scroll_offset = self.list_state.offset();
+        self.live_filter_due = Some(Instant::now() + LIVE_FILTER_DEBOUNCE);
+    }
+
+    /// Drives the live filter from the event loop: collects finished background
+    /// jobs and starts a new evaluation once the debounce has elapsed.
+    pub fn tick_live_filter(&mut self, now: Instant) {
+        if let Some(job) = &self.live_filter_job {
+            match job.poll() {
+                JobPoll::Pending => {}
+                JobPoll::Done(matches) => {
+                    let job = self.live_filter_job.take().expect("job polled above");
+                    self.finish_live_filter(job, matches);
+                }
+                JobPoll::Failed => self.live_filter_job = None,
+            }
+        }
+        if self.live_filter_due.is_some_and(|due| due <= now) {
+            self.live_filter_due = None;
+            self.run_live_filter();
+        }
+    }
+
+    pub fn live_filter_pending(&self) -> bool {
+        self.live_filter_due.is_some() || self.live_filter_job.is_some()
+    }
+
+    fn run_live_filter(&mut self) {
+        if !matches!(self.input_mode, InputMode::FilterInput) || self.filter_input_target.is_some()
+        {
+            return;
+        }
+        let pattern = self.filter_buffer.clone();
+        if pattern.starts_with(':') {
             return;
+        }
+        let query = if pattern.is_empty() {
+            None
+        } else {
+            match self.filter_modes.compile_query(&pattern) {
+                Ok(query) => Some(query),
+                Err(err) => {
+                    // Keep showing the last pattern that compiled.
+                    self.live_filter_error = Some(err);
+                    return;
+                }
+            }
         };
-        let height = self.last_list_height.max(1);
-        let offset = self.list_scroll_offset;
-        let new_offset = if selected >= offset + height {
-            selected + 1 - height
-        } else if selected < offset {
-            selected
+        self.live_filter_error = None;
+        let modes = self.filter_modes;
+        let (shown, shown_modes) = match &self.live_preview {
+            Some(preview) => (preview.pattern.as_str(), preview.modes),
+            None => (self.filter_query.as_str(), self.filter_query_modes),
+        };
+        let narrowing = self.live_filter_job.is_none()
+            && shown_modes == modes
+            && is_narrowing(Some(shown), &pattern, modes);
+        let preview = LivePreview {
+            pattern,
+            query,
+            modes,
+        };
+        let candidates = if narrowing {
+            self.matched_indices().len()
         } else {
-            offset
+            self.entries.len()
         };
-        self.list_scroll_offset = new_offset;
-        *self.list_state.offset_mut() = new_offset;
+        // Following an id or a template needs more than a snapshot holds, so
+        // those are evaluated here (in parallel) instead.
+        let detached = EntryFilter {
+            main: preview.query.as_ref(),
+            ..self.entry_filter()
+        }
+        .runs_on_snapshot();
+        if detached && candidates >= BACKGROUND_FILTER_MIN_ENTRIES {
+            let snapshot = |idx: usize| {
+                let entry = &self.entries[idx];
+                (idx, entry.search_text.clone(), entry.raw.clone())
+            };
+            let snapshots = if narrowing {
+                self.matched_indices()
+                    .iter()
+                    .map(|&idx| snapshot(idx))
+                    .collect()
+            } else {
+                (0..self.entries.len())
+                    .filter(|&idx| self.level_filter.allows(&self.entries[idx].level))
+                    .map(snapshot)
+                    .collect()
+            };
+            self.live_filter_job = Some(LiveFilterJob::spawn(
+                preview,
+                self.filter_stack.clone(),
+                snapshots,
+                self.evicted_entries,
+                self.entries.len(),
+            ));
+        } else {
+            self.live_filter_job = None;
+            self.live_preview = Some(preview);
+            self.refilter(narrowing);
+        }
     }
 
-    fn rebuild_filtered(&mut self, strategy: Option<SelectStrategy>, preserve_view: bool) {
-        let prev_selected_entry = self.selected_entry_index();
-
-        let mut filtered = Vec::with_capacity(self.entries.len());
-        for (idx, entry) in self.entries.iter().enumerate() {
-            if self.matches_filter(entry) {
+    fn finish_live_filter(&mut self, job: LiveFilterJob, matches: Vec<usize>) {
+        if !matches!(self.input_mode, InputMode::FilterInput) {
+            return;
+        }
+        // Rebase onto the current buffer: drop entries evicted since the
+        // snapshot and evaluate the ones that arrived after it here.
+        let shift = (self.evicted_entries - job.evicted) as usize;
+        let mut filtered: Vec<usize> = matches
+            .into_iter()
+            .filter_map(|idx| idx.checked_sub(shift))
+            .collect();
+        self.live_preview = Some(job.preview);
+        for idx in job.len.saturating_sub(shift)..self.entries.len() {
+            if self.matches_filter(idx) {
                 filtered.push(idx);
             }
         }
+        self.set_filtered(filtered, false);
+    }
+
+    pub fn cancel_filter_input(&mut self) {
+        self.input_mode = if self.filter_input_target.take().is_some() {
+            InputMode::FilterStack
+        } else {
+            InputMode::Normal
+        };
+        self.filter_buffer.clear();
+        self.filter_error = None;
+        self.filter_modes = self.filter_query_modes;
+        self.clear_live_filter();
+    }
 
-        self.filtered_indices = filtered;
+    /// Flips one of the prompt's filter modes and re-runs the live preview.
+    pub fn toggle_filter_mode(&mut self, toggle: impl FnOnce(&mut FilterModes)) {
+        toggle(&mut self.filter_modes);
+        self.schedule_live_filter();
+    }
 
-        if self.filtered_indices.is_empty() {
-            self.list_state.select(None);
-            self.reset_detail_position();
-            return;
+    /// Drops any live preview so the committed filter applies again.
+    pub fn clear_live_filter(&mut self) {
+        self.live_filter_due = None;
+        self.live_filter_job = None;
+        self.live_filter_error = None;
+        if self.live_preview.take().is_some() {
+            self.rebuild_filtered(false);
         }
+    }
 
-        if preserve_view
-            && let Some(prev_entry_idx) = prev_selected_entry
-            && let Some(new_pos) = self
-                .filtered_indices
-                .iter()
-                .position(|&idx| idx == prev_entry_idx)
+    /// Handles Enter in the filter prompt: `:save <name>` stores the active
+    /// filter, otherwise the buffer becomes the main filter or a new stack layer.
+    pub fn submit_filter_input(&mut self) {
+        if self.history_search.is_some() {
+            self.accept_history_search();
+        }
+        let pattern = std::mem::take(&mut self.filter_buffer);
+        self.history_cursor = None;
+        self.live_filter_due = None;
+        self.live_filter_error = None;
+        let settled = self.live_filter_job.take().is_none();
+        let preview = self.live_preview.take();
+        if let Some(preview) = &preview
+            && settled
+            && self.filter_input_target.is_none()
+            && preview.pattern == pattern
+            && preview.modes == self.filter_modes
         {
-            self.list_state.select(Some(new_pos));
+            // The preview already holds the result for this pattern; commit it
+            // without scanning the buffer again.
+            self.input_mode = InputMode::Normal;
+            self.filter_compiled = preview.query.clone();
+            self.filter_query_modes = preview.modes;
+            self.filter_query = pattern;
+            self.filter_error = None;
+            self.filter_history
+                .record(&self.filter_query.clone(), preview.modes);
+            self.persist_filter_history();
             return;
         }
-
-        match strategy.unwrap_or(SelectStrategy::PreserveOrFirst) {
-            SelectStrategy::Last => {
-                self.list_state
-                    .select(Some(self.filtered_indices.len().saturating_sub(1)));
-                self.reset_detail_position();
-                self.horiz_offset = 0;
-                self.update_list_offset();
+        // While a preview was installed the list reflects its pattern rather
+        // than the committed one.
+        let shown = preview.map(|p| (p.pattern, p.modes));
+        if let Some(kind) = self.filter_input_target.take() {
+            self.input_mode = InputMode::FilterStack;
+            self.add_filter_layer(&pattern, kind);
+            if self.filter_error.is_none() {
+                self.filter_history.record(&pattern, self.filter_modes);
+                self.persist_filter_history();
             }
-            SelectStrategy::PreserveOrFirst => {
-                if let Some(prev_entry_idx) = prev_selected_entry
-                    && let Some(new_pos) = self
-                        .filtered_indices
-                        .iter()
-                        .position(|&idx| idx == prev_entry_idx)
-                {
-                    self.list_state.select(Some(new_pos));
-                    self.reset_detail_position();
-                    self.horiz_offset = 0;
-                    self.update_list_offset();
-                    return;
-                }
-                self.list_state.select(Some(0));
-                self.reset_detail_position();
-                self.horiz_offset = 0;
-                self.update_list_offset();
+            return;
+        }
+        self.input_mode = InputMode::Normal;
+        if let Some(name) = pattern.strip_prefix(":save ") {
+            if shown.is_some() {
+                self.rebuild_filtered(false);
             }
+            self.save_current_filter(name.trim());
+            return;
+        }
+        let (base, base_modes) = shown
+            .clone()
+            .unwrap_or_else(|| (self.filter_query.clone(), self.filter_query_modes));
+        if !self.apply_filter_over(&pattern, &base, base_modes) && shown.is_some() {
+            self.rebuild_filtered(false);
         }
     }
 
-    fn discover_columns(&mut self, value: &Value) {
-        let mut to_add: Vec<ColumnDef> = Vec::new();
+    pub fn save_current_filter(&mut self, name: &str) {
+        if name.is_empty() {
+            self.filter_error = Some("usage: :save <name>".into());
+            return;
+        }
+        if self.filter_query.is_empty() {
+            self.filter_error = Some("no active filter to save".into());
+            return;
+        }
+        let pattern = self.filter_query.clone();
+        self.filter_history
+            .save_named(name, &pattern, self.filter_query_modes);
+        self.persist_filter_history();
+    }
 
-        if let Some(obj) = value.as_object() {
-            for key in obj.keys() {
-                if is_reserved_column(key) {
-                    continue;
-                }
-                let path = vec![key.clone()];
-                if !self.columns.iter().any(|c| c.path == path) {
-                    to_add.push(ColumnDef::new(key.clone(), path));
-                }
+    /// Steps back (`older == true`) or forward through filter history in the prompt.
+    pub fn history_step(&mut self, older: bool) {
+        let len = self.filter_history.entries.len();
+        if len == 0 {
+            return;
+        }
+        let next = match (self.history_cursor, older) {
+            (None, true) => {
+                self.history_draft = (self.filter_buffer.clone(), self.filter_modes);
+                Some(len - 1)
+            }
+            (None, false) => return,
+            (Some(idx), true) => Some(idx.saturating_sub(1)),
+            (Some(idx), false) if idx + 1 < len => Some(idx + 1),
+            (Some(_), false) => None,
+        };
+        self.history_cursor = next;
+        (self.filter_buffer, self.filter_modes) = match next {
+            Some(idx) => {
+                let entry = &self.filter_history.entries[idx];
+                (entry.pattern.clone(), entry.modes)
             }
+            None => std::mem::take(&mut self.history_draft),
+        };
+    }
+
+    /// Starts a reverse search, or jumps to the next older match when already searching.
+    pub fn history_search_next(&mut self) {
+        let Some(query) = self.history_search.clone() else {
+            self.history_search = Some(String::new());
+            return;
+        };
+        let before = self
+            .history_cursor
+            .unwrap_or(self.filter_history.entries.len());
+        if let Some(idx) = self.filter_history.search(&query, before) {
+            self.history_cursor = Some(idx);
         }
+    }
 
-        if let Some(data) = value.get("data").and_then(|v| v.as_object()) {
-            for key in data.keys() {
-                if is_reserved_column(key) {
-                    continue;
-                }
-                let path = vec!["data".to_string(), key.clone()];
-                if !self.columns.iter().any(|c| c.path == path) {
-                    to_add.push(ColumnDef::new(format!("data.{key}"), path));
+    pub fn history_search_edit(&mut self, push: Option<char>) {
+        let Some(query) = self.history_search.as_mut() else {
+            return;
+        };
+        match push {
+            Some(c) => query.push(c),
+            None => {
+                query.pop();
+            }
+        }
+        let query = query.clone();
+        self.history_cursor = self
+            .filter_history
+            .search(&query, self.filter_history.entries.len());
+    }
+
+    pub fn accept_history_search(&mut self) {
+        if self.history_search.take().is_none() {
+            return;
+        }
+        if let Some(entry) = self
+            .history_cursor
+            .and_then(|idx| self.filter_history.entries.get(idx))
+        {
+            self.filter_buffer = entry.pattern.clone();
+            self.filter_modes = entry.modes;
+        }
+    }
+
+    pub fn cancel_history_search(&mut self) {
+        self.history_search = None;
+        self.history_cursor = None;
+    }
+
+    pub fn history_search_hit(&self) -> Option<&str> {
+        self.history_search.as_ref()?;
+        self.history_cursor
+            .and_then(|idx| self.filter_history.entries.get(idx))
+            .map(|entry| entry.pattern.as_str())
+    }
+
+    pub fn open_saved_filters(&mut self) {
+        self.history_search = None;
+        self.filter_input_target = None;
+        self.input_mode = InputMode::SavedFilters;
+        let len = self.filter_history.saved.len();
+        match self.saved_filter_state.selected() {
+            _ if len == 0 => self.saved_filter_state.select(None),
+            Some(idx) if idx < len => {}
+            _ => self.saved_filter_state.select(Some(0)),
+        }
+    }
+
+    pub fn select_saved_filter(&mut self, delta: isize) {
+        let len = self.filter_history.saved.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.saved_filter_state.selected().unwrap_or(0);
+        let new_idx = (current as isize + delta).clamp(0, (len as isize) - 1) as usize;
+        self.saved_filter_state.select(Some(new_idx));
+    }
+
+    pub fn apply_saved_filter(&mut self) {
+        let Some(saved) = self
+            .saved_filter_state
+            .selected()
+            .and_then(|idx| self.filter_history.saved.get(idx))
+        else {
+            return;
+        };
+        let pattern = saved.pattern.clone();
+        self.filter_modes = saved.modes;
+        self.input_mode = InputMode::Normal;
+        self.apply_filter(&pattern);
+    }
+
+    pub fn delete_saved_filter(&mut self) {
+        let Some(idx) = self.saved_filter_state.selected() else {
+            return;
+        };
+        if idx >= self.filter_history.saved.len() {
+            return;
+        }
+        self.filter_history.saved.remove(idx);
+        let len = self.filter_history.saved.len();
+        self.saved_filter_state.select(if len == 0 {
+            None
+        } else {
+            Some(idx.min(len - 1))
+        });
+        self.persist_filter_history();
+    }
+
+    fn persist_filter_history(&mut self) {
+        if let Err(err) = self.filter_history.save() {
+            self.filter_error = Some(format!("saving filter history: {err:#}"));
+        }
+    }
+
+    pub fn cycle_min_severity(&mut self) {
+        let narrowing = self.level_filter.cycle_min_severity();
+        self.refilter(narrowing);
+    }
+
+    /// Levels seen so far with their current counts, most severe first;
+    /// levels without a known severity come last.
+    pub fn level_rows(&self) -> Vec<(&str, usize)> {
+        let mut rows: Vec<(&str, usize)> = self
+            .level_counts
+            .iter()
+            .map(|(level, count)| (level.as_str(), *count))
+            .collect();
+        rows.sort_by_key(|(level, _)| std::cmp::Reverse(severity(level)));
+        rows
+    }
+
+    pub fn open_level_select(&mut self) {
+        self.input_mode = InputMode::LevelSelect;
+        let len = self.level_counts.len();
+        match self.level_select_state.selected() {
+            _ if len == 0 => self.level_select_state.select(None),
+            Some(idx) if idx < len => {}
+            _ => self.level_select_state.select(Some(0)),
+        }
+    }
+
+    pub fn select_level_row(&mut self, delta: isize) {
+        let len = self.level_counts.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.level_select_state.selected().unwrap_or(0);
+        let new_idx = (current as isize + delta).clamp(0, (len as isize) - 1) as usize;
+        self.level_select_state.select(Some(new_idx));
+    }
+
+    pub fn toggle_selected_level(&mut self) {
+        let Some(level) = self
+            .level_select_state
+            .selected()
+            .and_then(|idx| self.level_rows().get(idx).map(|(l, _)| l.to_string()))
+        else {
+            return;
+        };
+        let narrowing = self.level_filter.toggle_hidden(&level);
+        self.refilter(narrowing);
+    }
+
+    pub fn reset_level_filter(&mut self) {
+        if !self.level_filter.is_active() {
+            return;
+        }
+        self.level_filter = LevelFilter::default();
+        self.rebuild_filtered(false);
+    }
+
+    pub fn open_filter_stack(&mut self) {
+        self.input_mode = InputMode::FilterStack;
+        if self.filter_stack_state.selected().is_none() && !self.filter_stack.is_empty() {
+            self.filter_stack_state.select(Some(0));
+        }
+        self.count_layer_drops();
+    }
+
+    /// Attributes every entry the stack hides to the first enabled layer
+    /// that rejects it, so moving a layer up shows what it alone removes.
+    /// Only the stack's popup shows the counts, so they are left alone while
+    /// it is closed; opening it counts afresh.
+    fn count_layer_drops(&mut self) {
+        if !matches!(self.input_mode, InputMode::FilterStack) {
+            return;
+        }
+        let filter = EntryFilter {
+            layers: &[],
+            ..self.entry_filter()
+        };
+        let layers = &self.filter_stack;
+        let zeros = || vec![0; layers.len()];
+        self.layer_drops = self
+            .entries
+            .par_iter()
+            .enumerate()
+            .fold(zeros, |mut drops, (idx, entry)| {
+                if filter.matches(idx, entry)
+                    && let Some(pos) = layers.iter().position(|layer| !layer.allows(entry))
+                {
+                    drops[pos] += 1;
                 }
+                drops
+            })
+            .reduce(zeros, |mut total, drops| {
+                total.iter_mut().zip(drops).for_each(|(t, d)| *t += d);
+                total
+            });
+    }
+
+    /// Starts filter input for a new stack layer; Enter pushes it via `add_filter_layer`.
+    pub fn begin_filter_layer_input(&mut self, kind: FilterKind) {
+        self.begin_filter_input(String::new());
+        self.filter_input_target = Some(kind);
+    }
+
+    pub fn add_filter_layer(&mut self, pattern: &str, kind: FilterKind) {
+        self.push_filter_layer(pattern, self.filter_modes, kind);
+    }
+
+    fn push_filter_layer(&mut self, pattern: &str, modes: FilterModes, kind: FilterKind) {
+        if pattern.is_empty() {
+            return;
+        }
+        match modes.compile_query(pattern) {
+            Ok(query) => {
+                self.filter_stack
+                    .push(FilterLayer::new(pattern.to_string(), query, modes, kind));
+                self.filter_stack_state
+                    .select(Some(self.filter_stack.len() - 1));
+                self.filter_error = None;
+                self.refilter(true);
+                self.count_layer_drops();
             }
+            Err(err) => {
+                self.filter_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Stacks a layer keeping (or, for `Exclude`, dropping) entries whose
+    /// `field` has exactly the field's current value. Re-adding an existing
+    /// predicate only updates its kind and re-enables it.
+    pub fn add_field_predicate(&mut self, field: &FieldEntry, kind: FilterKind) {
+        let pattern = field.equals_predicate();
+        if let Some(idx) = self.filter_stack.iter().position(|l| l.pattern == pattern) {
+            let layer = &mut self.filter_stack[idx];
+            layer.kind = kind;
+            layer.enabled = true;
+            self.filter_stack_state.select(Some(idx));
+            self.refilter(false);
+            return;
+        }
+        let modes = FilterModes {
+            jq: true,
+            ..FilterModes::default()
+        };
+        self.push_filter_layer(&pattern, modes, kind);
+    }
+
+    /// Lists the selected entry's scalar fields so one can become a
+    /// field-scoped filter layer (from the detail pane); Enter applies `kind`.
+    pub fn open_field_filter(&mut self, kind: FilterKind) {
+        let Some(entry) = self.current_entry() else {
+            return;
+        };
+        self.field_filter_fields = collect_fields(&entry.raw)
+            .into_iter()
+            .filter(FieldEntry::is_leaf)
+            .collect();
+        if self.field_filter_fields.is_empty() {
+            return;
+        }
+        self.field_filter_state.select(Some(0));
+        self.field_filter_kind = kind;
+        self.input_mode = InputMode::FieldFilter;
+    }
+
+    pub fn select_field_filter(&mut self, delta: isize) {
+        let len = self.field_filter_fields.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.field_filter_state.selected().unwrap_or(0);
+        let next = (current as isize + delta).clamp(0, len as isize - 1) as usize;
+        self.field_filter_state.select(Some(next));
+    }
+
+    /// Applies the highlighted field of the picker and closes it.
+    pub fn apply_field_filter(&mut self, kind: FilterKind) {
+        let field = self
+            .field_filter_state
+            .selected()
+            .and_then(|idx| self.field_filter_fields.get(idx))
+            .cloned();
+        self.close_field_filter();
+        if let Some(field) = field {
+            self.add_field_predicate(&field, kind);
+        }
+    }
+
+    pub fn close_field_filter(&mut self) {
+        self.field_filter_fields.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn toggle_filter_layer(&mut self) {
+        let Some(layer) = self
+            .filter_stack_state
+            .selected()
+            .and_then(|idx| self.filter_stack.get_mut(idx))
+        else {
+            return;
+        };
+        layer.enabled = !layer.enabled;
+        let narrowing = layer.enabled;
+        self.refilter(narrowing);
+        self.count_layer_drops();
+    }
+
+    pub fn toggle_filter_layer_kind(&mut self) {
+        let Some(layer) = self
+            .filter_stack_state
+            .selected()
+            .and_then(|idx| self.filter_stack.get_mut(idx))
+        else {
+            return;
+        };
+        layer.kind = layer.kind.toggled();
+        self.rebuild_filtered(false);
+        self.count_layer_drops();
+    }
+
+    pub fn remove_filter_layer(&mut self) {
+        let Some(idx) = self.filter_stack_state.selected() else {
+            return;
+        };
+        if idx >= self.filter_stack.len() {
+            return;
+        }
+        let removed = self.filter_stack.remove(idx);
+        if self.filter_stack.is_empty() {
+            self.filter_stack_state.select(None);
+        } else {
+            self.filter_stack_state
+                .select(Some(idx.min(self.filter_stack.len() - 1)));
+        }
+        if removed.enabled {
+            self.rebuild_filtered(false);
+        }
+        self.count_layer_drops();
+    }
+
+    pub fn select_filter_layer(&mut self, delta: isize) {
+        if self.filter_stack.is_empty() {
+            return;
+        }
+        let len = self.filter_stack.len();
+        let current = self.filter_stack_state.selected().unwrap_or(0);
+        let new_idx = (current as isize + delta).clamp(0, (len as isize) - 1) as usize;
+        self.filter_stack_state.select(Some(new_idx));
+    }
+
+    pub fn move_filter_layer(&mut self, delta: isize) {
+        if self.filter_stack.is_empty() {
+            return;
+        }
+        let Some(idx) = self.filter_stack_state.selected() else {
+            return;
+        };
+        let len = self.filter_stack.len();
+        let new_idx = (idx as isize + delta).clamp(0, (len as isize) - 1) as usize;
+        if new_idx == idx {
+            return;
+        }
+        self.filter_stack.swap(idx, new_idx);
+        self.filter_stack_state.select(Some(new_idx));
+        self.count_layer_drops();
+    }
+
+    pub fn active_filter_layers(&self) -> usize {
+        self.filter_stack.iter().filter(|l| l.enabled).count()
+    }
+
+    pub fn enter_field_view(&mut self) {
+        let Some(entry) = self.current_entry() else {
+            return;
+        };
+        let fields = collect_fields(&entry.raw);
+        if fields.is_empty() {
+            return;
+        }
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        let filtered_indices = (0..fields.len()).collect();
+        self.field_view = Some(FieldViewState {
+            fields,
+            filtered_indices,
+            list_state,
+            filter: String::new(),
+        });
+        self.reset_field_detail_position();
+        self.field_detail_total_lines = 0;
+        self.last_field_detail_height = 0;
+        self.last_field_detail_width = 0;
+        self.field_detail_max_line_width = 0;
+        self.field_zoom = None;
+        self.input_mode = InputMode::FieldView;
+        self.force_redraw = true;
+    }
+
+    pub fn exit_field_view(&mut self) {
+        self.field_view = None;
+        self.reset_field_detail_position();
+        self.field_detail_total_lines = 0;
+        self.last_field_detail_height = 0;
+        self.last_field_detail_width = 0;
+        self.field_detail_max_line_width = 0;
+        self.field_zoom = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// `o` in the column selector: sorts by the selected column ascending,
+    /// then descending, then back to arrival order.
+    pub fn cycle_sort(&mut self) {
+        let Some(col) = self
+            .column_select_state
+            .selected()
+            .and_then(|idx| self.columns.get(idx))
+        else {
+            return;
+        };
+        let next = match &self.sort {
+            Some(order) if order.column == col.name && !order.descending => Some(SortOrder {
+                column: col.name.clone(),
+                descending: true,
+            }),
+            Some(order) if order.column == col.name => None,
+            _ => Some(SortOrder {
+                column: col.name.clone(),
+                descending: false,
+            }),
+        };
+        self.set_sort(next);
+    }
+
+    /// Re-sorts the visible list, keeping the selected entry. Sorting turns
+    /// autoscroll off: new entries land at their sorted position instead of
+    /// the tail, so following the tail no longer means anything.
+    pub fn set_sort(&mut self, order: Option<SortOrder>) {
+        let col = order
+            .as_ref()
+            .and_then(|o| self.columns.iter().find(|c| c.name == o.column));
+        self.sort_keys = match col {
+            Some(col) => self
+                .entries
+                .iter()
+                .map(|e| SortKey::for_entry(col, e, &self.time_zone))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.sort = if col.is_some() { order } else { None };
+        if self.sort.is_some() {
+            self.autoscroll = false;
+        }
+        let mut filtered = self.matched_indices().to_vec();
+        if self.sort.is_none() {
+            filtered.sort_unstable();
+        }
+        self.set_filtered(filtered, true);
+        self.update_list_offset();
+    }
+
+    /// Folds runs of consecutive entries with the same key into one row, or
+    /// unfolds them again with `None`.
+    pub fn set_dedupe(&mut self, by: Option<DedupeBy>) {
+        let matched = self.matched_indices().to_vec();
+        self.dedupe = by.map(|by| Dedupe::new(by, &self.entries));
+        self.set_filtered(matched, true);
+        self.update_list_offset();
+        self.force_redraw = true;
+    }
+
+    /// `D`: folds repeated messages, or stops folding.
+    pub fn toggle_dedupe(&mut self) {
+        if self.dedupe.is_some() {
+            self.set_dedupe(None);
+            self.notify("Repeats unfolded");
+        } else {
+            self.set_dedupe(Some(DedupeBy::Message));
+            self.notify("Folding repeated messages (Space expands a row)");
+        }
+    }
+
+    /// Space: expands the selected row's repeats, or folds them back onto
+    /// their first entry. Returns false when the row has no repeats.
+    pub fn toggle_repeats(&mut self) -> bool {
+        let evicted = self.evicted_entries;
+        let (Some(dedupe), Some(row)) = (self.dedupe.as_mut(), self.list_state.selected()) else {
+            return false;
+        };
+        let Some(&run) = dedupe.row_runs.get(row) else {
+            return false;
+        };
+        let first = dedupe.matched[dedupe.runs[run].start];
+        let expanded = !dedupe.is_expanded(run, evicted);
+        if !dedupe.set_expanded(run, expanded, evicted) {
+            return false;
+        }
+        let matched = std::mem::take(&mut dedupe.matched);
+        self.set_filtered(matched, true);
+        self.select_entry(first);
+        true
+    }
+
+    pub fn toggle_autoscroll(&mut self) {
+        self.autoscroll = !self.autoscroll;
+        self.force_redraw = true;
+        if self.autoscroll {
+            // Following shows the newest entry, which a sort may have placed
+            // anywhere in the list.
+            let newest = self.sort.as_ref().and_then(|_| {
+                let (row, _) = self
+                    .filtered_indices
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|&(_, &idx)| idx)?;
+                Some(row)
+            });
+            match newest {
+                Some(row) => {
+                    self.remember_jump();
+                    self.select_new_row(row);
+                }
+                None => self.select_last(),
+            }
+        } else {
+            self.list_scroll_offset = self.list_state.offset();
+            *self.list_state.offset_mut() = self.list_scroll_offset;
+        }
+    }
+
+    /// `t`: cycles how the timestamp column is shown.
+    pub fn cycle_timestamp_mode(&mut self) {
+        self.timestamp_mode = self.timestamp_mode.cycled();
+        self.force_redraw = true;
+    }
+
+    /// `T`: measures delta timestamps from the selected entry, or back from
+    /// the previous row when it already is the reference. Switches to delta
+    /// mode so the effect is visible.
+    pub fn toggle_time_reference(&mut self) {
+        let Some(idx) = self.selected_entry_index() else {
+            return;
+        };
+        let seq = self.evicted_entries + idx as u64;
+        if self.time_reference == Some(seq) {
+            self.time_reference = None;
+        } else {
+            self.time_reference = Some(seq);
+            self.timestamp_mode = TimestampMode::Delta;
+        }
+        self.force_redraw = true;
+    }
+
+    /// Buffer index of the entry with absolute sequence `seq`, if it is
+    /// still buffered.
+    pub fn index_of_seq(&self, seq: u64) -> Option<usize> {
+        let idx = usize::try_from(seq.checked_sub(self.evicted_entries)?).ok()?;
+        (idx < self.entries.len()).then_some(idx)
+    }
+
+    fn selected_mark_target(&self) -> Option<(u64, EntryKey)> {
+        let idx = self.selected_entry_index()?;
+        Some((self.evicted_entries + idx as u64, self.entries[idx].key()))
+    }
+
+    /// Selects the entry with absolute sequence `seq`, leaving the tail.
+    fn jump_to_seq(&mut self, seq: u64) -> Result<(), String> {
+        let idx = self.index_of_seq(seq).ok_or("The entry was evicted")?;
+        if !self.matched_indices().contains(&idx) {
+            return Err("The entry is hidden by the filters".into());
+        }
+        self.remember_jump();
+        self.autoscroll = false;
+        self.select_entry(idx);
+        Ok(())
+    }
+
+    /// Records the selected entry in the jump list before the selection
+    /// jumps away from it.
+    fn remember_jump(&mut self) {
+        if let Some(idx) = self.selected_entry_index() {
+            self.jumps.record(self.evicted_entries + idx as u64);
+        }
+    }
+
+    /// `Ctrl+O` / `Ctrl+I`: selects the entry before (or after) the current
+    /// one in the jump list, skipping entries that were evicted or are hidden
+    /// by the filters. Returns false when there is nowhere to go.
+    pub fn jump_history(&mut self, back: bool) -> bool {
+        let Some(idx) = self.selected_entry_index() else {
+            return false;
+        };
+        let current = self.evicted_entries + idx as u64;
+        let (evicted, len) = (self.evicted_entries, self.entries.len());
+        let filtered = match &self.dedupe {
+            Some(dedupe) => &dedupe.matched,
+            None => &self.filtered_indices,
+        };
+        let visible = |seq: u64| {
+            seq.checked_sub(evicted)
+                .and_then(|idx| usize::try_from(idx).ok())
+                .is_some_and(|idx| idx < len && filtered.contains(&idx))
+        };
+        let target = if back {
+            self.jumps.back(current, visible)
+        } else {
+            self.jumps.forward(current, visible)
+        };
+        let Some(idx) = target.and_then(|seq| self.index_of_seq(seq)) else {
+            return false;
+        };
+        self.autoscroll = false;
+        self.select_entry(idx)
+    }
+
+    /// `*`: replaces the filters with the selected entry's correlation id,
+    /// showing every entry that carries it, from any source, in time order.
+    /// The previous view goes on the breadcrumb trail.
+    pub fn follow_correlation(&mut self) -> Result<(), String> {
+        let idx = self.selected_entry_index().ok_or("No entry selected")?;
+        let Some((field, id)) =
+            correlation::find_id(&self.entries[idx].raw, &self.correlation_fields)
+        else {
+            let fields: Vec<String> = self
+                .correlation_fields
+                .iter()
+                .map(|p| p.join("."))
+                .collect();
+            return Err(format!(
+                "No correlation id ({}) on this entry",
+                fields.join(", ")
+            ));
+        };
+        self.remember_jump();
+        let view = SavedView {
+            filter_query: std::mem::take(&mut self.filter_query),
+            filter_compiled: self.filter_compiled.take(),
+            filter_query_modes: self.filter_query_modes,
+            filter_stack: std::mem::take(&mut self.filter_stack),
+            level_filter: std::mem::take(&mut self.level_filter),
+            sort: self.sort.clone(),
+            selected: Some(self.evicted_entries + idx as u64),
+        };
+        self.filter_error = None;
+        self.correlation_trail.push(Breadcrumb {
+            correlation: Correlation::new(
+                field.clone(),
+                id.clone(),
+                self.correlation_fields.clone(),
+            ),
+            view,
+        });
+        let by_time = self
+            .columns
+            .iter()
+            .find(|col| col.is_builtin("timestamp"))
+            .map(|col| SortOrder {
+                column: col.name.clone(),
+                descending: false,
+            });
+        self.set_sort(by_time);
+        self.autoscroll = false;
+        self.rebuild_filtered(false);
+        self.notify(format!(
+            "Following {field}={id}: {} entries (Backspace to go back)",
+            self.matched_indices().len()
+        ));
+        Ok(())
+    }
+
+    /// Backspace: puts back the view the last id was followed from, with the
+    /// entry it was followed from selected. Returns false when not following.
+    pub fn unfollow_correlation(&mut self) -> bool {
+        let Some(Breadcrumb { view, .. }) = self.correlation_trail.pop() else {
+            return false;
+        };
+        self.filter_query = view.filter_query;
+        self.filter_compiled = view.filter_compiled;
+        self.filter_query_modes = view.filter_query_modes;
+        self.filter_stack = view.filter_stack;
+        self.level_filter = view.level_filter;
+        self.filter_error = None;
+        self.set_sort(view.sort);
+        self.rebuild_filtered(false);
+        if let Some(idx) = view.selected.and_then(|seq| self.index_of_seq(seq)) {
+            self.select_entry(idx);
+        }
+        true
+    }
+
+    /// `W`: opens the span tree of the selected entry's trace, built from
+    /// every buffered entry of that trace whatever the filters.
+    pub fn open_trace_view(&mut self) -> Result<(), String> {
+        let idx = self.selected_entry_index().ok_or("No entry selected")?;
+        let id = trace::trace_id(&self.entries[idx].raw).ok_or("No trace_id on this entry")?;
+        let seqs = self.evicted_entries..;
+        let mut view = TraceView::build(&id, seqs.zip(&self.entries), &self.time_zone);
+        let seq = self.evicted_entries + idx as u64;
+        if let Some(row) = view
+            .rows
+            .iter()
+            .position(|row| matches!(row, TraceRow::Entry { seq: s, .. } if *s == seq))
+        {
+            view.list_state.select(Some(row));
+        }
+        self.trace_view = Some(view);
+        self.input_mode = InputMode::TraceView;
+        Ok(())
+    }
+
+    pub fn close_trace_view(&mut self) {
+        self.trace_view = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Enter in the trace view: folds or unfolds a span, or closes the view
+    /// and selects the entry in the list.
+    pub fn trace_view_enter(&mut self) {
+        let Some(view) = self.trace_view.as_mut() else {
+            return;
+        };
+        match view.selected_row() {
+            Some(TraceRow::Span { .. }) => view.toggle_selected(),
+            Some(TraceRow::Entry { seq, .. }) => {
+                self.close_trace_view();
+                if let Err(err) = self.jump_to_seq(seq) {
+                    self.notify_error(err);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// `C`: lists the message templates by count, mining the buffer the
+    /// first time. Templates that appear from then on are marked new.
+    pub fn open_clusters(&mut self) {
+        let clusters = self
+            .clusters
+            .get_or_insert_with(|| Clusters::mine(self.entries.iter(), &self.time_zone));
+        self.cluster_rows = clusters.by_count();
+        let selected = self
+            .cluster_rows
+            .iter()
+            .position(|&id| Some(id) == self.template_filter);
+        self.cluster_state.select(match selected {
+            _ if self.cluster_rows.is_empty() => None,
+            Some(row) => Some(row),
+            None => Some(0),
+        });
+        self.input_mode = InputMode::Clusters;
+    }
+
+    pub fn close_clusters(&mut self) {
+        if let Some(clusters) = self.clusters.as_mut() {
+            clusters.mark_seen();
+        }
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn select_cluster_row(&mut self, delta: isize) {
+        let len = self.cluster_rows.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.cluster_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        self.cluster_state.select(Some(next));
+    }
+
+    /// Closes the template list and shows only the chosen template's entries,
+    /// on top of the other filters.
+    pub fn filter_by_cluster_row(&mut self) {
+        let Some((id, template)) = self
+            .cluster_state
+            .selected()
+            .and_then(|row| self.cluster_rows.get(row))
+            .and_then(|&id| {
+                Some((
+                    id,
+                    self.clusters
+                        .as_ref()?
+                        .clusters
+                        .get(&id)?
+                        .template
+                        .to_string(),
+                ))
+            })
+        else {
+            return;
+        };
+        self.close_clusters();
+        self.remember_jump();
+        self.notify(format!("Template: {template} (Backspace shows all)"));
+        self.template_filter = Some(id);
+        self.rebuild_filtered(false);
+    }
+
+    /// Backspace: drops the template filter. Returns false when there is none.
+    pub fn clear_template_filter(&mut self) -> bool {
+        if self.template_filter.take().is_none() {
+            return false;
+        }
+        self.rebuild_filtered(false);
+        true
+    }
+
+    /// Places session marks on buffered entries, or once they arrive.
+    pub fn restore_marks(&mut self, marks: Vec<(MarkKind, EntryKey)>) {
+        for (kind, key) in marks {
+            match self.entries.iter().position(|e| e.key() == key) {
+                Some(idx) => self
+                    .marks
+                    .place(kind, self.evicted_entries + idx as u64, key),
+                None => self.marks.restore_later(kind, key),
+            }
+        }
+    }
+
+    /// `n`: opens the note prompt for the selected entry, with its note.
+    pub fn begin_note(&mut self) {
+        let Some(idx) = self.selected_entry_index() else {
+            return;
+        };
+        let seq = self.evicted_entries + idx as u64;
+        self.note_buffer = self.marks.note(seq).unwrap_or_default().to_string();
+        self.input_mode = InputMode::NoteInput;
+    }
+
+    pub fn cancel_note(&mut self) {
+        self.note_buffer.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn submit_note(&mut self) {
+        let text = std::mem::take(&mut self.note_buffer);
+        self.input_mode = InputMode::Normal;
+        self.annotate(&text);
+    }
+
+    /// Sets the selected entry's note; blank text removes it.
+    pub fn annotate(&mut self, text: &str) {
+        let Some((seq, key)) = self.selected_mark_target() else {
+            self.notify_error("No entry selected");
+            return;
+        };
+        let had_note = self.marks.note(seq).is_some();
+        self.marks.set_note(seq, key, text);
+        match self.marks.note(seq) {
+            Some(_) => self.notify("Note saved"),
+            None if had_note => self.notify("Note removed"),
+            None => {}
+        }
+        self.force_redraw = true;
+    }
+
+    /// `b`: bookmarks or unbookmarks the selected entry.
+    pub fn toggle_bookmark(&mut self) {
+        let Some((seq, key)) = self.selected_mark_target() else {
+            return;
+        };
+        if self.marks.toggle_bookmark(seq, key) {
+            self.notify("Bookmarked");
+        } else {
+            self.notify("Bookmark removed");
+        }
+        self.force_redraw = true;
+    }
+
+    /// `]` / `[`: selects the next (or previous) bookmarked row, wrapping
+    /// around the list.
+    pub fn jump_bookmark(&mut self, forward: bool) {
+        let len = self.filtered_indices.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0);
+        let found = (1..=len)
+            .map(|step| {
+                if forward {
+                    (current + step) % len
+                } else {
+                    (current + len - step % len) % len
+                }
+            })
+            .find(|&pos| {
+                let seq = self.evicted_entries + self.filtered_indices[pos] as u64;
+                self.marks.is_bookmarked(seq)
+            });
+        match found {
+            Some(pos) => {
+                self.remember_jump();
+                self.autoscroll = false;
+                self.list_state.select(Some(pos));
+                self.reset_detail_position();
+                self.force_redraw = true;
+                self.update_list_offset();
+            }
+            None => self.notify_error("No bookmarks in the list (b adds one)"),
+        }
+    }
+
+    /// `m<letter>` / `'<letter>`: the key after `m` or `'`.
+    pub fn finish_mark_prompt(&mut self, prompt: MarkPrompt, name: char) {
+        if !name.is_ascii_alphabetic() {
+            self.notify_error(format!("Marks are named with letters, not '{name}'"));
+            return;
+        }
+        match prompt {
+            MarkPrompt::Set => {
+                if let Some((seq, key)) = self.selected_mark_target() {
+                    self.marks.set(name, seq, key);
+                    self.notify(format!("Mark '{name}' set"));
+                    self.force_redraw = true;
+                }
+            }
+            MarkPrompt::Jump => {
+                let result = match self.marks.get(name) {
+                    Some(seq) => self.jump_to_seq(seq),
+                    None => Err(format!("Mark '{name}' is not set")),
+                };
+                if let Err(err) = result {
+                    self.notify_error(err);
+                }
+            }
+        }
+    }
+
+    /// `B`: lists bookmarks and named marks.
+    pub fn open_bookmarks(&mut self) {
+        self.input_mode = InputMode::Bookmarks;
+        self.bookmark_rows = self.marks.rows();
+        let len = self.bookmark_rows.len();
+        let selected = self
+            .selected_mark_target()
+            .and_then(|(seq, _)| self.bookmark_rows.iter().position(|row| row.seq == seq));
+        self.bookmark_state.select(match selected {
+            _ if len == 0 => None,
+            Some(pos) => Some(pos),
+            None => Some(0),
+        });
+    }
+
+    pub fn select_bookmark_row(&mut self, delta: isize) {
+        let len = self.bookmark_rows.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.bookmark_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        self.bookmark_state.select(Some(next));
+    }
+
+    /// Closes the popup and selects the chosen entry.
+    pub fn jump_to_bookmark_row(&mut self) {
+        let Some(row) = self
+            .bookmark_state
+            .selected()
+            .and_then(|pos| self.bookmark_rows.get(pos))
+        else {
+            return;
+        };
+        let seq = row.seq;
+        self.input_mode = InputMode::Normal;
+        if let Err(err) = self.jump_to_seq(seq) {
+            self.notify_error(err);
+        }
+    }
+
+    /// Removes the bookmark and named marks of the chosen row.
+    pub fn delete_bookmark_row(&mut self) {
+        let Some(pos) = self.bookmark_state.selected() else {
+            return;
+        };
+        let Some(row) = self.bookmark_rows.get(pos) else {
+            return;
+        };
+        self.marks.remove(row.seq);
+        self.bookmark_rows.remove(pos);
+        let len = self.bookmark_rows.len();
+        self.bookmark_state
+            .select((len > 0).then(|| pos.min(len - 1)));
+        self.force_redraw = true;
+    }
+
+    /// Buffer index of the delta reference, if it has not been evicted.
+    pub fn time_reference_index(&self) -> Option<usize> {
+        let seq = self.time_reference?.checked_sub(self.evicted_entries)?;
+        let idx = usize::try_from(seq).ok()?;
+        (idx < self.entries.len()).then_some(idx)
+    }
+
+    fn entry_filter(&self) -> EntryFilter<'_> {
+        let main = match &self.live_preview {
+            Some(preview) => preview.query.as_ref(),
+            None => self.filter_compiled.as_ref(),
+        };
+        EntryFilter {
+            main,
+            layers: &self.filter_stack,
+            levels: &self.level_filter,
+            correlation: self.correlation_trail.last().map(|step| &step.correlation),
+            cluster: self
+                .template_filter
+                .and_then(|id| Some((self.clusters.as_ref()?, id))),
+        }
+    }
+
+    fn matches_filter(&self, idx: usize) -> bool {
+        self.entry_filter().matches(idx, &self.entries[idx])
+    }
+
+    /// Indices of `candidates` (or of the whole buffer) that pass the active
+    /// filters, evaluated in parallel for large inputs. Order is preserved.
+    fn matching_indices(&self, candidates: Option<&[usize]>) -> Vec<usize> {
+        let filter = self.entry_filter();
+        let entries = &self.entries;
+        let keep = |idx: &usize| filter.matches(*idx, &entries[*idx]);
+        match candidates {
+            Some(indices) if indices.len() >= PARALLEL_FILTER_MIN => {
+                indices.par_iter().copied().filter(keep).collect()
+            }
+            Some(indices) => indices.iter().copied().filter(keep).collect(),
+            None if entries.len() >= PARALLEL_FILTER_MIN => {
+                (0..entries.len()).into_par_iter().filter(keep).collect()
+            }
+            None => (0..entries.len()).filter(keep).collect(),
+        }
+    }
+
+    /// Re-evaluates the filters. When the change can only drop entries, the
+    /// current matches are refined instead of rescanning the buffer.
+    fn refilter(&mut self, narrowing: bool) {
+        if narrowing {
+            let filtered = self.matching_indices(Some(self.matched_indices()));
+            self.set_filtered(filtered, false);
+        } else {
+            self.rebuild_filtered(false);
+        }
+    }
+
+    /// The filtered entries in display order, before repeats are folded.
+    pub fn matched_indices(&self) -> &[usize] {
+        match &self.dedupe {
+            Some(dedupe) => &dedupe.matched,
+            None => &self.filtered_indices,
+        }
+    }
+
+    /// The row showing buffer index `idx`, or the first row of the folded
+    /// run it belongs to.
+    fn row_of_entry(&self, idx: usize) -> Option<usize> {
+        self.filtered_indices
+            .iter()
+            .position(|&i| i == idx)
+            .or_else(|| {
+                let dedupe = self.dedupe.as_ref()?;
+                dedupe.row_of_run(dedupe.run_of(idx)?)
+            })
+    }
+
+    fn selected_entry_index(&self) -> Option<usize> {
+        let idx = self.list_state.selected()?;
+        self.filtered_indices.get(idx).copied()
+    }
+
+    fn update_list_offset(&mut self) {
+        if self.autoscroll {
+            self.list_scroll_offset = self.list_state.offset();
+            return;
+        }
+        let Some(selected) = self.list_state.selected() else {
+            self.list_scroll_offset = self.list_state.offset();
+            return;
+        };
+        let height = self.last_list_height.max(1);
+        let offset = self.list_scroll_offset;
+        let new_offset = if selected >= offset + height {
+            selected + 1 - height
+        } else if selected < offset {
+            selected
+        } else {
+            offset
+        };
+        self.list_scroll_offset = new_offset;
+        *self.list_state.offset_mut() = new_offset;
+    }
+
+    fn rebuild_filtered(&mut self, preserve_view: bool) {
+        let filtered = self.matching_indices(None);
+        self.set_filtered(filtered, preserve_view);
+    }
+
+    fn set_filtered(&mut self, mut filtered: Vec<usize>, preserve_view: bool) {
+        if let Some(order) = &self.sort {
+            // Inputs are mostly in order already (arrival order, or the
+            // previous sorted result), which the stable sort handles in
+            // close to linear time.
+            filtered.sort_by(|&a, &b| sort::compare(&self.sort_keys, order.descending, a, b));
+        }
+        let prev_selected_entry = self.selected_entry_index();
+        self.filtered_indices = match self.dedupe.as_mut() {
+            Some(dedupe) => dedupe.fold(filtered, self.evicted_entries),
+            None => filtered,
+        };
+
+        if self.filtered_indices.is_empty() {
+            self.list_state.select(None);
+            self.reset_detail_position();
+            return;
+        }
+
+        if preserve_view
+            && let Some(prev_entry_idx) = prev_selected_entry
+            && let Some(new_pos) = self.row_of_entry(prev_entry_idx)
+        {
+            self.list_state.select(Some(new_pos));
+            return;
+        }
+
+        let row = prev_selected_entry
+            .and_then(|idx| self.row_of_entry(idx))
+            .unwrap_or(0);
+        self.list_state.select(Some(row));
+        self.reset_detail_position();
+        self.horiz_offset = 0;
+        self.update_list_offset();
+    }
+
+    fn discover_columns(&mut self, value: &Value) {
+        let mut to_add: Vec<ColumnDef> = Vec::new();
+
+        if let Some(obj) = value.as_object() {
+            for key in obj.keys() {
+                if is_reserved_column(key) {
+                    continue;
+                }
+                let path = vec![key.clone()];
+                if !self.columns.iter().any(|c| c.path == path) {
+                    to_add.push(ColumnDef::new(key.clone(), path));
+                }
+            }
+        }
+
+        if let Some(data) = value.get("data").and_then(|v| v.as_object()) {
+            for key in data.keys() {
+                if is_reserved_column(key) {
+                    continue;
+                }
+                let path = vec!["data".to_string(), key.clone()];
+                if !self.columns.iter().any(|c| c.path == path) {
+                    to_add.push(ColumnDef::new(format!("data.{key}"), path));
+                }
+            }
+        }
+
+        if !to_add.is_empty() {
+            self.columns.extend(to_add);
+            let len = self.columns.len();
+            if self
+                .column_select_state
+                .selected()
+                .map(|i| i >= len)
+                .unwrap_or(true)
+            {
+                self.column_select_state.select(Some(0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        columns::ColumnWidth,
+        filters::is_narrowing,
+        keymap::{Action, KeyMode},
+        sort::SortOrder,
+    };
+    use chrono::TimeDelta;
+    use serde_json::json;
+
+    fn entry_with_message(msg: &str) -> LogEntry {
+        LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            msg.to_string(),
+            json!({ "message": msg }),
+        )
+    }
+
+    #[test]
+    fn autoscroll_off_preserves_viewport_on_push() {
+        let mut app = App::new(100);
+        app.autoscroll = false;
+        app.last_list_height = 3;
+        app.entries = vec![
+            entry_with_message("one"),
+            entry_with_message("two"),
+            entry_with_message("three"),
+        ];
+        app.filtered_indices = vec![0, 1, 2];
+        app.list_state.select(Some(1));
+        app.list_scroll_offset = 1;
+        *app.list_state.offset_mut() = 1;
+
+        app.push(entry_with_message("four"));
+
+        assert_eq!(app.list_scroll_offset, 1, "offset should stay fixed");
+        assert_eq!(
+            app.list_state.offset(),
+            1,
+            "list state offset should remain"
+        );
+        assert_eq!(
+            app.list_state.selected(),
+            Some(1),
+            "selection should be unchanged"
+        );
+        assert_eq!(app.filtered_indices.len(), 4, "new entry still recorded");
+    }
+
+    #[test]
+    fn filter_preserves_selection_when_entry_still_matches() {
+        let mut app = App::new(100);
+        app.entries = vec![
+            entry_with_message("one"),
+            entry_with_message("two"),
+            entry_with_message("three"),
+        ];
+        app.filtered_indices = vec![0, 1, 2];
+        app.list_state.select(Some(1));
+
+        app.apply_filter("two|three");
+
+        let current = app.current_entry().unwrap();
+        assert_eq!(current.message, "two");
+        assert_eq!(app.filtered_indices, vec![1, 2]);
+        assert_eq!(app.list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn filter_moves_selection_when_previous_is_filtered_out() {
+        let mut app = App::new(100);
+        app.entries = vec![
+            entry_with_message("one"),
+            entry_with_message("two"),
+            entry_with_message("three"),
+        ];
+        app.filtered_indices = vec![0, 1, 2];
+        app.list_state.select(Some(0));
+
+        app.apply_filter("two");
+
+        let current = app.current_entry().unwrap();
+        assert_eq!(current.message, "two");
+        assert_eq!(app.filtered_indices, vec![1]);
+        assert_eq!(app.list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn eviction_keeps_alignment_and_selection_on_tail() {
+        let mut app = App::new(2);
+        app.push(entry_with_message("one"));
+        app.push(entry_with_message("two"));
+        app.push(entry_with_message("three")); // triggers eviction of "one"
+
+        let messages: Vec<String> = app.entries.iter().map(|e| e.message.clone()).collect();
+        assert_eq!(messages, vec!["two", "three"]);
+        assert_eq!(app.filtered_indices, vec![0, 1]);
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert_eq!(app.current_entry().unwrap().message, "three");
+    }
+
+    #[test]
+    fn toggle_autoscroll_jumps_to_latest() {
+        let mut app = App::new(10);
+        app.autoscroll = false;
+        app.entries = vec![
+            entry_with_message("one"),
+            entry_with_message("two"),
+            entry_with_message("three"),
+        ];
+        app.filtered_indices = vec![0, 1, 2];
+        app.list_state.select(Some(0));
+
+        app.toggle_autoscroll();
+
+        assert!(app.autoscroll);
+        assert_eq!(app.list_state.selected(), Some(2));
+        assert_eq!(app.current_entry().unwrap().message, "three");
+    }
+
+    #[test]
+    fn invalid_filter_does_not_change_active_regex() {
+        let mut app = App::new(10);
+        app.entries = vec![entry_with_message("one")];
+        app.filtered_indices = vec![0];
+
+        app.apply_filter("["); // invalid regex
+
+        assert!(app.filter_compiled.is_none());
+        assert!(app.filter_error.is_some());
+        assert_eq!(app.filtered_indices, vec![0]);
+    }
+
+    #[test]
+    fn column_discovery_adds_new_fields_once() {
+        let mut app = App::new(10);
+        assert_eq!(app.columns.len(), 3); // default columns
+
+        app.discover_columns(&json!({"foo": "bar", "data": { "baz": 1 }}));
+        let names: Vec<String> = app.columns.iter().map(|c| c.name.clone()).collect();
+        assert!(names.contains(&"foo".to_string()));
+        assert!(names.contains(&"data.baz".to_string()));
+
+        // Repeat discovery should not duplicate
+        app.discover_columns(&json!({"foo": "again", "data": { "baz": 2 }}));
+        let foo_count = app.columns.iter().filter(|c| c.name == "foo").count();
+        let baz_count = app.columns.iter().filter(|c| c.name == "data.baz").count();
+        assert_eq!(foo_count, 1);
+        assert_eq!(baz_count, 1);
+    }
+
+    #[test]
+    fn toggle_column_enabled_flag() {
+        let mut app = App::new(10);
+        app.column_select_state.select(Some(0));
+        let before = app.columns[0].enabled;
+        app.move_column(0); // no-op move; ensures selection exists
+        if let Some(idx) = app.column_select_state.selected() {
+            app.columns[idx].enabled = !before;
+        }
+        assert_ne!(app.columns[0].enabled, before);
+    }
+
+    #[test]
+    fn eviction_rebases_filtered_indices_with_filter_active() {
+        let mut app = App::new(2);
+        app.apply_filter("two|three"); // set filter before data arrives
+
+        app.push(entry_with_message("one")); // filtered out
+        app.push(entry_with_message("two")); // kept
+        app.push(entry_with_message("three")); // evicts "one"
+
+        let msgs: Vec<_> = app
+            .filtered_indices
+            .iter()
+            .filter_map(|&i| app.entries.get(i))
+            .map(|e| e.message.clone())
+            .collect();
+        assert_eq!(msgs, vec!["two", "three"]);
+        assert_eq!(app.entries.len(), 2);
+        assert_eq!(app.filtered_indices, vec![0, 1]);
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert_eq!(app.current_entry().unwrap().message, "three");
+    }
+
+    #[test]
+    fn filter_stack_combines_include_and_exclude_layers() {
+        let mut app = App::new(10);
+        for msg in [
+            "error: db",
+            "error: healthcheck",
+            "info: ok",
+            "error: dns flaky",
+        ] {
+            app.push(entry_with_message(msg));
+        }
+
+        app.add_filter_layer("error", FilterKind::Include);
+        app.add_filter_layer("healthcheck", FilterKind::Exclude);
+        app.add_filter_layer("dns", FilterKind::Exclude);
+        assert_eq!(app.filtered_indices, vec![0]);
+        // Drops are only counted for the open stack popup.
+        assert!(app.layer_drops.is_empty());
+
+        // Disabling the dns exclusion brings that entry back.
+        app.filter_stack_state.select(Some(2));
+        app.toggle_filter_layer();
+        assert_eq!(app.filtered_indices, vec![0, 3]);
+
+        // Flipping the healthcheck layer turns it into an include.
+        app.filter_stack_state.select(Some(1));
+        app.toggle_filter_layer_kind();
+        assert_eq!(app.filtered_indices, vec![1]);
+
+        app.remove_filter_layer();
+        assert_eq!(app.filter_stack.len(), 2);
+        assert_eq!(app.filtered_indices, vec![0, 1, 3]);
+
+        // Order decides which layer an entry is counted against.
+        app.filter_stack_state.select(Some(1));
+        app.toggle_filter_layer();
+        app.open_filter_stack();
+        assert_eq!(app.layer_drops, vec![1, 1]);
+        app.filter_stack_state.select(Some(0));
+        app.toggle_filter_layer_kind();
+        assert_eq!(app.layer_drops, vec![3, 0]);
+        app.move_filter_layer(1);
+        assert_eq!(app.filter_stack[0].pattern, "dns");
+        assert_eq!(app.layer_drops, vec![1, 2]);
+    }
+
+    #[test]
+    fn invalid_filter_layer_is_not_added() {
+        let mut app = App::new(10);
+        app.push(entry_with_message("one"));
+
+        app.add_filter_layer("(", FilterKind::Exclude);
+
+        assert!(app.filter_stack.is_empty());
+        assert!(app.filter_error.is_some());
+        assert_eq!(app.filtered_indices, vec![0]);
+    }
+
+    #[test]
+    fn filter_history_steps_and_restores_draft() {
+        let mut app = App::new(10);
+        app.apply_filter("one");
+        app.filter_modes.literal = true;
+        app.apply_filter("two");
+        app.filter_modes.literal = false;
+        app.apply_filter("one"); // duplicate moves to the end
+
+        let patterns: Vec<&str> = app
+            .filter_history
+            .entries
+            .iter()
+            .map(|e| e.pattern.as_str())
+            .collect();
+        assert_eq!(patterns, vec!["two", "one"]);
+
+        app.begin_filter_input("draft".into());
+        app.history_step(true);
+        assert_eq!(app.filter_buffer, "one");
+        app.history_step(true);
+        assert_eq!(app.filter_buffer, "two");
+        assert!(app.filter_modes.literal, "recalled with its own modes");
+        app.history_step(true);
+        assert_eq!(app.filter_buffer, "two", "stays on the oldest entry");
+        app.history_step(false);
+        assert!(!app.filter_modes.literal);
+        app.history_step(false);
+        assert_eq!(app.filter_buffer, "draft");
+    }
+
+    #[test]
+    fn corrupt_history_file_is_reported_and_left_alone() {
+        let path = std::env::temp_dir().join(format!("logtui-state-{}.json", std::process::id()));
+        std::fs::write(&path, "{\"entries\": [").unwrap();
+        let err = FilterHistory::load(path.clone()).unwrap_err();
+        assert!(format!("{err:#}").contains("parsing"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"entries\": [");
+
+        std::fs::remove_file(&path).unwrap();
+        let history = FilterHistory::load(path).unwrap();
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn reverse_search_finds_older_matches() {
+        let mut app = App::new(10);
+        for pattern in ["payments.*error", "dns", "payments timeout"] {
+            app.apply_filter(pattern);
+        }
+        app.begin_filter_input(String::new());
+
+        app.history_search_next();
+        for c in "pay".chars() {
+            app.history_search_edit(Some(c));
+        }
+        assert_eq!(app.history_search_hit(), Some("payments timeout"));
+        app.history_search_next();
+        assert_eq!(app.history_search_hit(), Some("payments.*error"));
+
+        app.submit_filter_input();
+        assert_eq!(app.filter_query, "payments.*error");
+        assert!(app.history_search.is_none());
+    }
+
+    #[test]
+    fn save_command_stores_active_filter_by_name() {
+        let mut app = App::new(10);
+        app.push(entry_with_message("payment failed"));
+        app.push(entry_with_message("ok"));
+        app.apply_filter("payment");
+
+        app.begin_filter_input(":save payments-errors".into());
+        app.submit_filter_input();
+        assert_eq!(app.filter_query, "payment", "saving keeps the filter");
+        assert_eq!(app.filter_history.saved.len(), 1);
+        assert_eq!(app.filter_history.saved[0].name, "payments-errors");
+
+        app.apply_filter("");
+        app.open_saved_filters();
+        app.apply_saved_filter();
+        assert_eq!(app.filter_query, "payment");
+        assert_eq!(app.filtered_indices, vec![0]);
+
+        app.filter_modes.literal = true;
+        app.apply_filter("a.*d");
+        app.begin_filter_input(":save literal".into());
+        app.submit_filter_input();
+        app.filter_modes.literal = false;
+        app.apply_filter("");
+        app.open_saved_filters();
+        app.saved_filter_state.select(Some(1));
+        app.apply_saved_filter();
+        assert!(app.filter_query_modes.literal, "saved modes come back");
+        assert!(app.filtered_indices.is_empty());
+    }
+
+    fn type_into_filter(app: &mut App, text: &str) {
+        app.filter_buffer = text.to_string();
+        app.schedule_live_filter();
+        app.tick_live_filter(Instant::now() + LIVE_FILTER_DEBOUNCE);
+    }
+
+    #[test]
+    fn live_filter_previews_and_keeps_last_good_pattern() {
+        let mut app = App::new(10);
+        for msg in ["alpha", "beta", "gamma"] {
+            app.push(entry_with_message(msg));
+        }
+        app.begin_filter_input(String::new());
+
+        type_into_filter(&mut app, "a");
+        assert_eq!(app.filtered_indices, vec![0, 1, 2]);
+        type_into_filter(&mut app, "bet");
+        assert_eq!(app.filtered_indices, vec![1]);
+
+        type_into_filter(&mut app, "bet(");
+        assert!(app.live_filter_error.is_some());
+        assert_eq!(app.filtered_indices, vec![1], "last good preview stays");
+        assert!(app.filter_query.is_empty(), "nothing committed yet");
+
+        app.cancel_filter_input();
+        assert!(app.live_preview.is_none());
+        assert_eq!(app.filtered_indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn live_filter_waits_for_debounce() {
+        let mut app = App::new(10);
+        app.push(entry_with_message("alpha"));
+        app.push(entry_with_message("beta"));
+        app.begin_filter_input(String::new());
+
+        app.filter_buffer = "beta".into();
+        app.schedule_live_filter();
+        app.tick_live_filter(Instant::now());
+        assert_eq!(app.filtered_indices, vec![0, 1]);
+        assert!(app.live_filter_pending());
+    }
+
+    #[test]
+    fn background_live_filter_rebases_after_eviction() {
+        let total = BACKGROUND_FILTER_MIN_ENTRIES + 10;
+        let mut app = App::new(total);
+        app.autoscroll = false;
+        for i in 0..total {
+            app.push(entry_with_message(&format!("line {i}")));
+        }
+        app.begin_filter_input(String::new());
+        type_into_filter(&mut app, r"line \w*5\b");
+        assert!(app.live_filter_job.is_some(), "large buffers use a worker");
+
+        // Two entries arrive (evicting two) while the worker runs.
+        app.push(entry_with_message("line x5"));
+        app.push(entry_with_message("line y6"));
+        while app.live_filter_job.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.tick_live_filter(Instant::now());
+        }
+
+        let messages: Vec<&str> = app
+            .filtered_indices
+            .iter()
+            .map(|&i| app.entries[i].message.as_str())
+            .collect();
+        assert_eq!(messages.first(), Some(&"line 5"));
+        assert_eq!(messages.last(), Some(&"line x5"));
+        let expected = (2..total).filter(|i| i % 10 == 5).count() + 1;
+        assert_eq!(messages.len(), expected);
+    }
+
+    #[test]
+    fn background_live_filter_evaluates_jq() {
+        let total = BACKGROUND_FILTER_MIN_ENTRIES + 10;
+        let mut app = App::new(total);
+        for i in 0..total {
+            app.push(entry_with_message(&format!("line {i}")));
+        }
+        app.begin_filter_input(String::new());
+        app.filter_modes.jq = true;
+        type_into_filter(&mut app, r#".message == "line 7""#);
+        assert!(app.live_filter_job.is_some(), "jq runs on the worker too");
+        while app.live_filter_job.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.tick_live_filter(Instant::now());
+        }
+        assert_eq!(app.filtered_indices, vec![7]);
+    }
+
+    #[test]
+    fn filter_modes_change_how_patterns_compile() {
+        let mut app = App::new(10);
+        for msg in ["ERROR boom", "error: a.b[0]", "errors everywhere", "axb[0"] {
+            app.push(entry_with_message(msg));
+        }
+
+        app.filter_modes.smart_case = true;
+        app.apply_filter("error");
+        assert_eq!(app.filtered_indices, vec![0, 1, 2]);
+        app.apply_filter("ERROR");
+        assert_eq!(
+            app.filtered_indices,
+            vec![0],
+            "uppercase stays case-sensitive"
+        );
+
+        app.filter_modes = FilterModes {
+            literal: true,
+            ..FilterModes::default()
+        };
+        app.apply_filter("a.b[0]");
+        assert_eq!(app.filtered_indices, vec![1]);
+        assert!(app.filter_error.is_none());
+
+        app.filter_modes = FilterModes {
+            smart_case: true,
+            whole_word: true,
+            ..FilterModes::default()
+        };
+        app.apply_filter("error");
+        assert_eq!(app.filtered_indices, vec![0, 1]);
+        assert_eq!(app.filter_query_modes, app.filter_modes);
+    }
+
+    #[test]
+    fn cancelling_the_prompt_restores_committed_modes() {
+        let mut app = App::new(10);
+        app.begin_filter_input(String::new());
+        app.toggle_filter_mode(|m| m.literal = true);
+        assert!(app.filter_modes.literal);
+
+        app.cancel_filter_input();
+        assert!(!app.filter_modes.literal);
+    }
+
+    fn entry_with_level(level: &str, msg: &str) -> LogEntry {
+        LogEntry::new(
+            "-".into(),
+            level.into(),
+            msg.to_string(),
+            json!({ "level": level, "message": msg }),
+        )
+    }
+
+    #[test]
+    fn min_severity_cycles_and_combines_with_text_filter() {
+        let mut app = App::new(10);
+        app.push(entry_with_level("DEBUG", "cache miss"));
+        app.push(entry_with_level("info", "request ok"));
+        app.push(entry_with_level("WARN", "slow request"));
+        app.push(entry_with_level("ERROR", "request failed"));
+        app.push(entry_with_level("TEXT", "plain line"));
+
+        app.cycle_min_severity(); // DEBUG
+        assert_eq!(app.filtered_indices, vec![0, 1, 2, 3, 4]);
+        app.cycle_min_severity(); // INFO
+        app.cycle_min_severity(); // WARN
+        assert_eq!(app.filtered_indices, vec![2, 3, 4], "TEXT has no severity");
+
+        app.apply_filter("request");
+        assert_eq!(app.filtered_indices, vec![2, 3]);
+
+        app.cycle_min_severity(); // ERROR
+        app.cycle_min_severity(); // CRITICAL
+        app.cycle_min_severity(); // off
+        assert!(app.level_filter.min_severity.is_none());
+        assert_eq!(app.filtered_indices, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn level_checklist_counts_and_hides_levels() {
+        let mut app = App::new(3);
+        app.push(entry_with_level("INFO", "a"));
+        app.push(entry_with_level("ERROR", "b"));
+        app.push(entry_with_level("INFO", "c"));
+        app.push(entry_with_level("DEBUG", "d")); // evicts the first INFO
+
+        assert_eq!(
+            app.level_rows(),
+            vec![("ERROR", 1), ("INFO", 1), ("DEBUG", 1)]
+        );
+
+        app.open_level_select();
+        app.select_level_row(1);
+        app.toggle_selected_level();
+        let messages: Vec<&str> = app
+            .filtered_indices
+            .iter()
+            .map(|&i| app.entries[i].message.as_str())
+            .collect();
+        assert_eq!(messages, vec!["b", "d"]);
+        assert_eq!(app.level_filter.describe().as_deref(), Some("-INFO"));
+
+        app.reset_level_filter();
+        assert_eq!(app.filtered_indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn narrowing_detection_only_trusts_literals() {
+        let regex = FilterModes::default();
+        assert!(is_narrowing(None, "error", regex));
+        assert!(is_narrowing(Some(""), "a.b", regex));
+        assert!(is_narrowing(Some("err"), "error", regex));
+        assert!(!is_narrowing(Some("error"), "err", regex));
+        assert!(!is_narrowing(Some("err"), "err|warn", regex));
+        assert!(!is_narrowing(Some("a.b"), "a.bc", regex));
+
+        let literal = FilterModes {
+            literal: true,
+            ..FilterModes::default()
+        };
+        assert!(is_narrowing(Some("a.b"), "a.bc", literal));
+
+        let smart = FilterModes {
+            smart_case: true,
+            ..FilterModes::default()
+        };
+        assert!(is_narrowing(Some("err"), "ERROR", smart));
+        assert!(!is_narrowing(Some("Err"), "error", smart));
+
+        let word = FilterModes {
+            whole_word: true,
+            ..FilterModes::default()
+        };
+        assert!(!is_narrowing(Some("err"), "error", word));
+    }
+
+    #[test]
+    fn large_rebuilds_match_sequential_evaluation() {
+        let total = PARALLEL_FILTER_MIN * 2;
+        let mut app = App::new(total);
+        for i in 0..total {
+            let level = if i % 7 == 0 { "error" } else { "info" };
+            app.push(entry_with_message(&format!("{level} {i}")));
+        }
+
+        app.apply_filter("err");
+        let expected: Vec<usize> = (0..total).filter(|i| i % 7 == 0).collect();
+        assert_eq!(app.filtered_indices, expected);
+
+        // Narrowing refines the previous matches.
+        app.apply_filter("error 14");
+        let expected: Vec<usize> = (0..total)
+            .filter(|i| i % 7 == 0 && format!("error {i}").contains("error 14"))
+            .collect();
+        assert_eq!(app.filtered_indices, expected);
+
+        // Widening again rescans everything.
+        app.apply_filter(r"info 1\b|error 14");
+        assert_eq!(app.filtered_indices[0], 1);
+        assert_eq!(app.filtered_indices.len(), expected.len() + 1);
+    }
+
+    #[test]
+    fn field_view_filter_rebuilds_indices_and_resets_selection() {
+        let mut app = App::new(10);
+        let entry = LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            "has data".into(),
+            json!({"a": 1, "b": 2, "nested": { "c": 3 }}),
+        );
+        app.entries.push(entry.clone());
+        app.filtered_indices = vec![0];
+        app.list_state.select(Some(0));
+
+        app.enter_field_view();
+        let fv = app.field_view.as_mut().unwrap();
+        // initial selection
+        assert_eq!(fv.list_state.selected(), Some(0));
+        fv.filter = "nested".into();
+        let _ = fv.rebuild_filter(); // may return false when selection stays valid
+        assert_eq!(fv.filtered_indices.len(), 2); // nested and nested.c
+        assert_eq!(fv.list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn paused_input_discards_new_entries() {
+        let mut app = App::new(5);
+        app.input_paused = true;
+
+        app.ingest(entry_with_message("ignored"));
+
+        assert!(app.entries.is_empty());
+        assert!(app.filtered_indices.is_empty());
+    }
+
+    #[test]
+    fn ingest_resumes_after_toggle() {
+        let mut app = App::new(5);
+        app.input_paused = true;
+
+        app.ingest(entry_with_message("ignored"));
+        app.toggle_input_pause();
+        app.ingest(entry_with_message("kept"));
+
+        assert_eq!(app.entries.len(), 1);
+        assert_eq!(app.current_entry().unwrap().message, "kept");
+    }
+
+    fn entry_with_items(count: usize) -> LogEntry {
+        let items: Vec<usize> = (0..count).collect();
+        LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            format!("{count} items"),
+            json!({ "message": format!("{count} items"), "data": { "items": items } }),
+        )
+    }
+
+    #[test]
+    fn jq_mode_filters_on_json_and_reports_parse_errors() {
+        let mut app = App::new(10);
+        for count in [1, 4, 2, 5] {
+            app.push(entry_with_items(count));
+        }
+        app.filter_modes.jq = true;
+        app.apply_filter("select(.data.items | length > 3)");
+        assert_eq!(app.filtered_indices, vec![1, 3]);
+        assert_eq!(app.filter_query_modes.labels(), vec!["jq"]);
+
+        app.apply_filter("select(.data.items | length >");
+        assert!(
+            app.filter_error
+                .as_deref()
+                .is_some_and(|e| e.starts_with("jq:"))
+        );
+        assert_eq!(
+            app.filtered_indices,
+            vec![1, 3],
+            "previous filter stays active"
+        );
+
+        app.begin_filter_input(String::new());
+        type_into_filter(
+            &mut app,
+            ".data.items[0] == 0 and (.message | test(\"^2\"))",
+        );
+        assert_eq!(app.filtered_indices, vec![2]);
+        app.cancel_filter_input();
+        assert_eq!(app.filtered_indices, vec![1, 3]);
+    }
+
+    #[test]
+    fn computed_columns_parse_names_and_expressions() {
+        let mut app = App::new(10);
+        app.input_mode = InputMode::ColumnSelect;
+        app.begin_column_input();
+        app.column_buffer = "items = .data.items | length".into();
+        app.submit_column_input();
+        assert!(matches!(app.input_mode, InputMode::ColumnSelect));
+        let col = &app.columns[1];
+        assert_eq!(col.name, "items");
+        assert!(col.enabled);
+        let raw = json!({ "data": { "items": [1, 2, 3] } });
+        assert_eq!(
+            col.expr.as_ref().and_then(|e| e.render(&raw)).as_deref(),
+            Some("3")
+        );
+
+        app.begin_column_input();
+        app.column_buffer = ".a ==".into();
+        app.submit_column_input();
+        assert!(matches!(app.input_mode, InputMode::ColumnInput));
+        assert!(app.filter_error.is_some());
+        app.cancel_column_input();
+
+        app.column_select_state.select(Some(1));
+        app.remove_computed_column();
+        assert!(app.columns.iter().all(|c| c.expr.is_none()));
+        app.column_select_state.select(Some(0));
+        app.remove_computed_column();
+        assert_eq!(app.columns.len(), 3, "field columns are never removed");
+    }
+
+    #[test]
+    fn field_predicates_stack_and_match_exact_values() {
+        let mut app = App::new(10);
+        let rows = [
+            json!({ "message": "a", "data": { "user.id": "u1", "status": 200 } }),
+            json!({ "message": "b", "data": { "user.id": "u2", "status": 200 } }),
+            json!({ "message": "c", "data": { "user.id": "u1", "status": 500 } }),
+            json!({ "message": "d", "data": { "user.id": "u1", "status": "200" } }),
+            json!({ "message": "e", "data": { "user.id": "u3", "status": 404 } }),
+        ];
+        for raw in rows {
+            let msg = raw["message"].as_str().unwrap().to_string();
+            app.push(LogEntry::new("-".into(), "INFO".into(), msg, raw));
+        }
+        let fields = collect_fields(&app.entries[0].raw);
+        let field = |path: &str| fields.iter().find(|f| f.path == path).unwrap().clone();
+
+        let user = field("data.user.id");
+        assert_eq!(user.jq_path, r#".data["user.id"]"#);
+        app.add_field_predicate(&user, FilterKind::Include);
+        assert_eq!(app.filtered_indices, vec![0, 2, 3]);
+
+        app.add_field_predicate(&field("data.status"), FilterKind::Exclude);
+        assert_eq!(
+            app.filtered_indices,
+            vec![2, 3],
+            "string \"200\" is not 200"
+        );
+        assert_eq!(app.filter_stack.len(), 2);
+
+        app.add_field_predicate(&user, FilterKind::Exclude);
+        assert_eq!(
+            app.filter_stack.len(),
+            2,
+            "same predicate is updated in place"
+        );
+        assert_eq!(app.filtered_indices, vec![4]);
+    }
+
+    #[test]
+    fn column_width_modes_resize_and_pin() {
+        let mut app = App::new(10);
+        let message = app
+            .columns
+            .iter()
+            .position(|c| c.name == "message")
+            .unwrap();
+        assert_eq!(app.columns[message].width, ColumnWidth::Flex);
+        assert!(app.columns.iter().filter(|c| c.sticky).count() == 2);
+
+        app.column_select_state.select(Some(message));
+        app.cycle_column_width();
+        assert_eq!(
+            app.columns[message].width,
+            ColumnWidth::Fixed(ColumnWidth::DEFAULT_FIXED)
+        );
+        app.cycle_column_width();
+        assert_eq!(app.columns[message].width, ColumnWidth::Auto);
+
+        app.last_column_widths = vec![20, 5, 30];
+        app.resize_column(2);
+        assert_eq!(app.columns[message].width, ColumnWidth::Fixed(32));
+        app.resize_column(-100);
+        assert_eq!(app.columns[message].width, ColumnWidth::Fixed(3));
+
+        app.toggle_column_sticky();
+        assert!(app.columns[message].sticky);
+    }
+
+    fn entry_with_duration(msg: &str, duration: Option<u64>) -> LogEntry {
+        let raw = match duration {
+            Some(ms) => json!({ "message": msg, "durationMs": ms }),
+            None => json!({ "message": msg }),
+        };
+        LogEntry::new("-".into(), "INFO".into(), msg.to_string(), raw)
+    }
+
+    fn sort_by(app: &mut App, column: &str, descending: bool) {
+        app.set_sort(Some(SortOrder {
+            column: column.to_string(),
+            descending,
+        }));
+    }
+
+    #[test]
+    fn sorting_is_stable_and_keeps_missing_values_last() {
+        let mut app = App::new(10);
+        for (msg, ms) in [
+            ("a", Some(30)),
+            ("b", None),
+            ("c", Some(5)),
+            ("d", Some(30)),
+            ("e", Some(100)),
+        ] {
+            app.push(entry_with_duration(msg, ms));
+        }
+        app.list_state.select(Some(2));
+        sort_by(&mut app, "durationMs", false);
+        assert!(!app.autoscroll, "sorting turns autoscroll off");
+        assert_eq!(app.filtered_indices, vec![2, 0, 3, 4, 1]);
+        assert_eq!(
+            app.current_entry().unwrap().message,
+            "c",
+            "selection follows the entry"
+        );
+
+        sort_by(&mut app, "durationMs", true);
+        assert_eq!(
+            app.filtered_indices,
+            vec![4, 0, 3, 2, 1],
+            "ties stay in arrival order"
+        );
+
+        app.apply_filter("[abcd]\"");
+        assert_eq!(app.filtered_indices, vec![0, 3, 2, 1]);
+
+        app.set_sort(None);
+        assert_eq!(app.filtered_indices, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn sorted_list_takes_new_entries_and_survives_eviction() {
+        let mut app = App::new(4);
+        for (msg, ms) in [("a", 20), ("b", 10), ("c", 30)] {
+            app.push(entry_with_duration(msg, Some(ms)));
+        }
+        sort_by(&mut app, "durationMs", false);
+        app.list_state.select(Some(2));
+        assert_eq!(app.current_entry().unwrap().message, "c");
+
+        app.push(entry_with_duration("d", Some(15)));
+        assert_eq!(app.filtered_indices, vec![1, 3, 0, 2]);
+        assert_eq!(app.current_entry().unwrap().message, "c");
+
+        // Evicts "a" from the middle of the sorted list.
+        app.push(entry_with_duration("e", Some(1)));
+        let order: Vec<&str> = app
+            .filtered_indices
+            .iter()
+            .map(|&i| app.entries[i].message.as_str())
+            .collect();
+        assert_eq!(order, vec!["e", "b", "d", "c"]);
+        assert_eq!(app.current_entry().unwrap().message, "c");
+
+        app.toggle_autoscroll();
+        assert_eq!(app.current_entry().unwrap().message, "e");
+        app.push(entry_with_duration("f", Some(12)));
+        assert_eq!(
+            app.current_entry().unwrap().message,
+            "f",
+            "following selects the new entry, not the last row"
+        );
+    }
+
+    #[test]
+    fn level_sort_uses_severity() {
+        let mut app = App::new(10);
+        for level in ["ERROR", "debug", "WARN", "INFO"] {
+            app.push(entry_with_level(level, level));
+        }
+        sort_by(&mut app, "level", true);
+        assert_eq!(app.filtered_indices, vec![0, 2, 3, 1]);
+    }
+
+    fn entry_at(timestamp: &str, msg: &str) -> LogEntry {
+        LogEntry::new(
+            timestamp.to_string(),
+            "INFO".into(),
+            msg.to_string(),
+            json!({ "timestamp": timestamp, "message": msg }),
+        )
+    }
+
+    #[test]
+    fn time_reference_follows_the_entry_and_timestamp_sort_uses_instants() {
+        let mut app = App::new(3);
+        app.time_zone = TimeZoneSpec::Utc;
+        app.push(entry_at("2024-03-01T12:00:02Z", "b"));
+        app.push(entry_at("2024-03-01 13:00:01+01:00", "a"));
+        app.push(entry_at("2024-03-01T12:00:03Z", "c"));
+
+        // Lexically "2024-03-01 13…" sorts last; by instant it is first.
+        sort_by(&mut app, "timestamp", false);
+        assert_eq!(app.filtered_indices, vec![1, 0, 2]);
+
+        app.list_state.select(Some(2));
+        app.toggle_time_reference();
+        assert_eq!(app.timestamp_mode, TimestampMode::Delta);
+        assert_eq!(app.time_reference_index(), Some(2));
+
+        app.push(entry_at("2024-03-01T12:00:04Z", "d"));
+        assert_eq!(app.time_reference_index(), Some(1));
+        assert_eq!(app.entries[1].message, "c");
+
+        app.list_state.select(Some(
+            app.filtered_indices.iter().position(|&i| i == 1).unwrap(),
+        ));
+        app.toggle_time_reference();
+        assert_eq!(app.time_reference, None);
+
+        // Once the marked entry is evicted, deltas fall back to the previous row.
+        app.list_state.select(Some(0));
+        app.toggle_time_reference();
+        for n in 0..3 {
+            app.push(entry_at("2024-03-01T12:00:05Z", &format!("e{n}")));
+        }
+        assert_eq!(app.time_reference_index(), None);
+    }
+
+    #[test]
+    fn config_sets_columns_wrap_colors_keys_and_filter() {
+        use crate::config::Config;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        use ratatui::style::Color;
+
+        let config = Config::parse(
+            r##"
+            max_entries = 100
+            filter = "boom"
+            wrap = false
+            timestamp_format = "utc"
+            correlation_fields = ["ctx.trace", "requestId"]
+
+            [[columns]]
+            name = "timestamp"
+
+            [[columns]]
+            name = "user"
+            path = "data.user.id"
+            width = 12
+
+            [[columns]]
+            name = "slow"
+            expr = ".durationMs > 100"
+            width = "flex"
+
+            [colors]
+            accent = "magenta"
+            levels = { warn = "#ff8800" }
+
+            [keys]
+            "ctrl+j" = "j"
+            "shift+tab" = "tab"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.max_entries, Some(100));
+        let mut app = App::new(10);
+        config.apply(&mut app).unwrap();
+
+        let names: Vec<&str> = app.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["timestamp", "user", "slow"]);
+        assert!(app.columns[0].sticky, "defaults carry over by name");
+        assert_eq!(app.columns[1].path, vec!["data", "user", "id"]);
+        assert_eq!(app.columns[1].width, ColumnWidth::Fixed(12));
+        assert!(app.columns[2].expr.is_some());
+        assert!(!app.detail_wrap);
+        assert_eq!(app.timestamp_mode, TimestampMode::IsoUtc);
+        assert_eq!(
+            app.correlation_fields,
+            vec![vec!["ctx", "trace"], vec!["requestId"]]
+        );
+        assert_eq!(app.theme.accent, Color::Magenta);
+        assert_eq!(
+            app.theme.level_style("Warn").fg,
+            Some(Color::Rgb(0xff, 0x88, 0x00))
+        );
+
+        let remapped = app.remap_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL));
+        assert_eq!(
+            remapped,
+            KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE)
+        );
+        let back_tab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(app.remap_key(back_tab).code, KeyCode::Tab);
+
+        // The startup filter is active but not recorded as history.
+        app.push(entry_with_message("boom"));
+        app.push(entry_with_message("fine"));
+        assert_eq!(app.filtered_indices, vec![0]);
+        assert!(app.filter_history.entries.is_empty());
+    }
+
+    #[test]
+    fn config_binds_keys_per_mode() {
+        use crate::{config::Config, keys::KeyChord};
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let config = Config::parse(
+            r##"
+            [keys]
+            "ctrl+n" = "j"
+
+            [keys.normal]
+            "ctrl+j" = "down"
+            "shift+tab" = "toggle_focus"
+            "s" = "none"
+
+            [keys.field_view]
+            "ctrl+u" = ["half_page_up"]
+
+            [keys.filter_stack]
+            "-" = "remove"
+            "##,
+        )
+        .unwrap();
+        let mut app = App::new(10);
+        config.apply(&mut app).unwrap();
+
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+        assert_eq!(
+            app.keymap.actions(KeyMode::Normal, chord("ctrl+j")),
+            vec![Action::Down]
+        );
+        assert_eq!(
+            app.keymap.actions(KeyMode::Normal, chord("j")),
+            vec![Action::Down],
+            "defaults stay unless rebound"
+        );
+        let back_tab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(
+            app.keymap
+                .actions(KeyMode::Normal, KeyChord::from_event(&back_tab)),
+            vec![Action::ToggleFocus]
+        );
+        assert!(app.keymap.actions(KeyMode::Normal, chord("s")).is_empty());
+        assert_eq!(
+            app.keymap.actions(KeyMode::FieldView, chord("ctrl+u")),
+            vec![Action::HalfPageUp]
+        );
+        assert_eq!(
+            app.keymap.actions(KeyMode::FilterStack, chord("-")),
+            vec![Action::Remove]
+        );
+        assert_eq!(
+            app.remap_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+                .code,
+            KeyCode::Char('j'),
+            "remaps still work next to the mode tables"
+        );
+        assert!(Config::parse("[keys]\nnormals = \"j\"").is_err());
+    }
+
+    #[test]
+    fn commands_parse_into_actions_and_arguments() {
+        use crate::app::commands::{Command, Flag, GotoTarget, Setting, parse};
+
+        assert_eq!(parse(":zoom"), Ok(Command::Action(Action::Zoom)));
+        assert_eq!(parse("mark"), Ok(Command::Action(Action::Bookmark)));
+        assert_eq!(parse("mark a"), Ok(Command::Mark('a')));
+        assert!(parse("mark 1").is_err());
+        assert_eq!(
+            parse("reference"),
+            Ok(Command::Action(Action::TimeReference))
+        );
+        assert_eq!(parse("q"), Ok(Command::Action(Action::Quit)));
+        assert_eq!(parse("filter  a b"), Ok(Command::Filter("a b".into())));
+        assert_eq!(
+            parse("columns add data.userId"),
+            Ok(Command::AddColumn("data.userId".into()))
+        );
+        assert_eq!(
+            parse("set nowrap"),
+            Ok(Command::Set(Setting::Wrap(Flag::Off)))
+        );
+        assert_eq!(
+            parse("set timestamps=utc"),
+            Ok(Command::Set(Setting::Timestamps(TimestampMode::IsoUtc)))
+        );
+        assert!(matches!(
+            parse("goto 14:32"),
+            Ok(Command::Goto(GotoTarget::TimeOfDay(_)))
+        ));
+        assert_eq!(parse("goto 12"), Ok(Command::Goto(GotoTarget::Row(12))));
+        assert!(
+            parse("columns frobnicate")
+                .unwrap_err()
+                .starts_with("usage:")
+        );
+        assert!(parse("zoom now").unwrap_err().contains("no arguments"));
+        assert!(parse("toggle_jq").unwrap_err().contains("unknown command"));
+        assert!(parse("set tz=Mars/Base").is_err());
+    }
+
+    #[test]
+    fn command_line_tab_completes_and_cycles() {
+        let mut app = App::new(10);
+        app.push(LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            "hi".into(),
+            json!({ "message": "hi", "data": { "userId": 7, "user": "x" } }),
+        ));
+        app.begin_command();
+        app.command_buffer = "col".into();
+        app.complete_command();
+        assert_eq!(app.command_buffer, "columns ");
+        app.command_buffer.push_str("add data.us");
+        app.complete_command();
+        assert_eq!(app.command_buffer, "columns add data.user");
+        app.complete_command();
+        assert_eq!(app.command_buffer, "columns add data.userId");
+        app.complete_command();
+        assert_eq!(app.command_buffer, "columns add data.user", "wraps around");
+
+        let command = app.take_command();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        app.begin_command();
+        app.command_history_step(true);
+        assert_eq!(app.command_buffer, command);
+    }
+
+    #[test]
+    fn column_commands_show_add_and_remove_columns() {
+        let mut app = App::new(10);
+        app.push(LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            "hi".into(),
+            json!({ "message": "hi", "data": { "userId": 7 } }),
+        ));
+        app.add_column_spec("data.userId").unwrap();
+        let col = app
+            .columns
+            .iter()
+            .find(|c| c.name == "data.userId")
+            .unwrap();
+        assert!(col.enabled, "discovered column is shown");
+        app.add_column_spec("req.route").unwrap();
+        assert_eq!(app.columns.last().unwrap().path, vec!["req", "route"]);
+        app.add_column_spec("slow = .durationMs > 100").unwrap();
+        assert!(app.add_column_spec("slow = .x").is_err());
+
+        app.remove_column_named("slow").unwrap();
+        assert!(app.columns.iter().all(|c| c.name != "slow"));
+        app.remove_column_named("req.route").unwrap();
+        assert!(
+            !app.columns
+                .iter()
+                .any(|c| c.name == "req.route" && c.enabled)
+        );
+        assert!(app.remove_column_named("nope").is_err());
+    }
+
+    #[test]
+    fn goto_selects_rows_and_the_first_entry_at_a_time() {
+        use crate::app::commands::{GotoTarget, parse};
+
+        let mut app = App::new(10);
+        app.time_zone = TimeZoneSpec::Utc;
+        for (ts, msg) in [
+            ("2024-05-01T14:30:00Z", "a"),
+            ("2024-05-01T14:31:30Z", "b"),
+            ("2024-05-01T14:33:00Z", "c"),
+        ] {
+            app.push(entry_at(ts, msg));
+        }
+        app.goto(&GotoTarget::Row(2)).unwrap();
+        assert_eq!(app.current_entry().unwrap().message, "b");
+        assert!(!app.autoscroll);
+        assert!(app.goto(&GotoTarget::Row(4)).is_err());
+
+        let target = |s: &str| match parse(&format!("goto {s}")).unwrap() {
+            crate::app::commands::Command::Goto(target) => target,
+            other => panic!("{other:?}"),
+        };
+        app.goto(&target("14:32")).unwrap();
+        assert_eq!(app.current_entry().unwrap().message, "c");
+        app.goto(&target("2024-05-01T14:31:00Z")).unwrap();
+        assert_eq!(app.current_entry().unwrap().message, "b");
+        assert!(app.goto(&target("15:00")).is_err());
+    }
+
+    #[test]
+    fn palette_ranks_fuzzy_matches_on_action_names_first() {
+        use crate::app::palette::{fuzzy_score, matches};
+
+        assert!(fuzzy_score("hpd", "half_page_down").is_some());
+        assert!(fuzzy_score("xyz", "half_page_down").is_none());
+        assert!(fuzzy_score("zoom", "zoom") > fuzzy_score("zoom", "z_o_o_m"));
+        assert_eq!(matches("session").first(), Some(&Action::SaveSession));
+        assert_eq!(matches("wrap").first(), Some(&Action::Wrap));
+        assert!(!matches("").contains(&Action::Palette));
+
+        let mut app = App::new(10);
+        app.open_palette();
+        "tsmode".chars().for_each(|c| app.palette_edit(Some(c)));
+        assert_eq!(app.take_palette_action(), Some(Action::TimestampMode));
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn export_formats_write_raw_json_csv_and_aligned_text() {
+        use crate::app::export::{ExportFormat, TableSpec, write_rows};
+
+        let mut rows = vec![
+            entry_at("2024-05-01T14:30:00Z", "plain"),
+            entry_at("2024-05-01T14:30:01Z", "with, \"quotes\""),
+            LogEntry::new(
+                "-".into(),
+                "TEXT".into(),
+                "not json".into(),
+                json!("not json"),
+            ),
+        ];
+        let line = r#"{"message": "unsorted", "at": 1.50, "level": "info"}"#;
+        rows.push(
+            crate::input::entry_from_value(
+                serde_json::from_str(line).unwrap(),
+                &Default::default(),
+            )
+            .with_line(line.to_string()),
+        );
+        let mut columns = default_columns();
+        columns.retain(|c| c.is_builtin("timestamp") || c.is_builtin("message"));
+        columns.iter_mut().for_each(|c| c.enabled = true);
+        let table = TableSpec {
+            columns,
+            mode: TimestampMode::Original,
+            zone: TimeZoneSpec::Utc,
+            reference: None,
+            notes: Vec::new(),
+        };
+        let write = |format: ExportFormat| {
+            let mut out = Vec::new();
+            write_rows(&mut out, format, &rows, &table, |_| {}).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let raw = write(ExportFormat::Raw);
+        assert_eq!(raw.lines().nth(2), Some("not json"));
+        assert_eq!(
+            raw.lines().nth(3),
+            Some(line),
+            "JSON lines are written as read"
+        );
+        let jsonl = write(ExportFormat::Jsonl);
+        assert_eq!(jsonl.lines().nth(2), Some("\"not json\""));
+        assert_eq!(jsonl.lines().nth(3), Some(line));
+        let json: Value = serde_json::from_str(&write(ExportFormat::Json)).unwrap();
+        assert_eq!(json[1]["message"], "with, \"quotes\"");
+        assert_eq!(
+            write(ExportFormat::Csv),
+            "timestamp,message\r\n\
+             2024-05-01T14:30:00Z,plain\r\n\
+             2024-05-01T14:30:01Z,\"with, \"\"quotes\"\"\"\r\n\
+             -,not json\r\n\
+             -,unsorted\r\n"
+        );
+        let text = write(ExportFormat::Text);
+        assert_eq!(text.lines().next(), Some("timestamp             message"));
+        assert_eq!(text.lines().nth(3), Some("-                     not json"));
+    }
+
+    #[test]
+    fn export_writes_the_marked_range_in_the_background() {
+        use crate::app::commands::{Command, parse};
+
+        let mut app = App::new(10);
+        for msg in ["a", "b", "c", "d"] {
+            app.push(entry_with_message(msg));
+        }
+        let path = std::env::temp_dir().join(format!("logtui-export-{}.jsonl", std::process::id()));
+        let Command::Export(spec) =
+            parse(&format!("export range jsonl {}", path.display())).unwrap()
+        else {
+            panic!("not an export");
+        };
+        app.select_entry(1);
+        app.toggle_time_reference();
+        assert!(app.export(&spec).is_err(), "the delta reference is no mark");
+
+        app.toggle_bookmark();
+        app.select_entry(2);
+        app.finish_mark_prompt(MarkPrompt::Set, 'a');
+        app.select_entry(3);
+        app.export(&spec).unwrap();
+        while app.export_job.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.tick_export();
+        }
+        let notice = app.notice.clone().unwrap();
+        assert!(!notice.is_error, "{}", notice.text);
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let messages: Vec<Value> = written
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["message"].clone())
+            .collect();
+        assert_eq!(messages, vec![json!("b"), json!("c")]);
+
+        assert!(parse("export yaml out.yaml").is_err());
+        let missing = std::env::temp_dir().join("logtui-no-such-dir/out.csv");
+        assert!(
+            app.export(&ExportSpec {
+                range: false,
+                path: missing,
+                ..spec
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn yank_copies_entries_marked_entries_and_field_values_and_paths() {
+        use crate::clipboard::osc52;
+
+        assert_eq!(osc52("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52("hi!", true), "\x1bPtmux;\x1b\x1b]52;c;aGkh\x07\x1b\\");
+
+        let mut app = App::new(10);
+        for msg in ["a", "b", "c"] {
+            app.push(entry_with_message(msg));
+        }
+        app.push(LogEntry::new(
+            "-".into(),
+            "INFO".into(),
+            "d".into(),
+            json!({ "message": "d", "http": { "user-agent": "curl" } }),
+        ));
+        app.select_entry(0);
+        let (text, _) = app.yank_text(Yank::Entry).unwrap();
+        assert_eq!(text, "{\n  \"message\": \"a\"\n}");
+        assert!(app.yank_text(Yank::Marked).is_err(), "no mark set");
+
+        app.toggle_bookmark();
+        app.select_entry(2);
+        app.finish_mark_prompt(MarkPrompt::Set, 'a');
+        let (text, what) = app.yank_text(Yank::Marked).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![r#"{"message":"a"}"#, r#"{"message":"c"}"#]
+        );
+        assert_eq!(what, "2 marked entries");
+
+        app.select_entry(3);
+        app.enter_field_view();
+        let fv = app.field_view.as_mut().unwrap();
+        fv.filter = "agent".into();
+        fv.rebuild_filter();
+        assert_eq!(app.yank_text(Yank::FieldValue).unwrap().0, "curl");
+        assert_eq!(
+            app.yank_text(Yank::FieldPath).unwrap().0,
+            r#".http["user-agent"]"#
+        );
+    }
+
+    #[test]
+    fn bookmarks_and_named_marks_follow_entries_through_filters_and_eviction() {
+        use crate::app::marks::MarkPrompt;
+
+        let mut app = App::new(4);
+        for (ts, msg) in [
+            ("2024-05-01T10:00:00Z", "req start"),
+            ("2024-05-01T10:00:01Z", "retry"),
+            ("2024-05-01T10:00:02Z", "req error"),
+            ("2024-05-01T10:00:03Z", "noise"),
+        ] {
+            app.push(entry_at(ts, msg));
+        }
+        app.select_entry(0);
+        app.toggle_bookmark();
+        app.select_entry(2);
+        app.toggle_bookmark();
+        app.finish_mark_prompt(MarkPrompt::Set, 'a');
+        app.select_entry(3);
+
+        app.jump_bookmark(true);
+        assert_eq!(app.current_entry().unwrap().message, "req start");
+        app.jump_bookmark(true);
+        assert_eq!(app.current_entry().unwrap().message, "req error");
+        app.jump_bookmark(false);
+        assert_eq!(app.current_entry().unwrap().message, "req start");
+
+        app.apply_filter("req");
+        app.finish_mark_prompt(MarkPrompt::Jump, 'a');
+        assert_eq!(app.current_entry().unwrap().message, "req error");
+
+        // Evicting "req start" drops its bookmark; the others move with
+        // their entries.
+        app.push(entry_at("2024-05-01T10:00:04Z", "req done"));
+        app.apply_filter("");
+        let rows = app.marks.rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].names, vec!['a']);
+        assert!(rows[0].bookmarked);
+        let idx = app.index_of_seq(rows[0].seq).unwrap();
+        assert_eq!(app.entries[idx].message, "req error");
+
+        app.finish_mark_prompt(MarkPrompt::Jump, 'z');
+        assert!(app.notice.as_ref().unwrap().is_error);
+
+        let session = Session::capture(&app);
+        let json = serde_json::to_string(&session).unwrap();
+        let session: Session = serde_json::from_str(&json).unwrap();
+        let mut restored = App::new(10);
+        assert!(session.restore(&mut restored).is_empty());
+        for entry in app.entries.clone() {
+            restored.push(entry);
+        }
+        restored.finish_mark_prompt(MarkPrompt::Jump, 'a');
+        assert_eq!(restored.current_entry().unwrap().message, "req error");
+        restored.open_bookmarks();
+        assert_eq!(restored.bookmark_rows.len(), 1);
+        restored.delete_bookmark_row();
+        assert!(restored.marks.is_empty());
+    }
+
+    #[test]
+    fn notes_annotate_entries_and_export_a_markdown_timeline() {
+        use crate::app::commands::{Command, parse};
+
+        let mut app = App::new(10);
+        app.time_zone = TimeZoneSpec::Utc;
+        for (ts, msg) in [
+            ("2024-05-01T10:00:05Z", "deploy finished"),
+            ("2024-05-01T10:00:01Z", "upstream 502"),
+            ("2024-05-01T10:00:03Z", "noise"),
+        ] {
+            app.push(entry_at(ts, msg));
         }
+        app.select_entry(0);
+        app.annotate("deploy | done");
+        app.select_entry(1);
+        app.begin_note();
+        "first 502".chars().for_each(|c| app.note_buffer.push(c));
+        app.submit_note();
+        app.begin_note();
+        assert_eq!(app.note_buffer, "first 502", "the prompt edits the note");
+        app.cancel_note();
 
-        if !to_add.is_empty() {
-            self.columns.extend(to_add);
-            let len = self.columns.len();
-            if self
-                .column_select_state
-                .selected()
-                .map(|i| i >= len)
-                .unwrap_or(true)
-            {
-                self.column_select_state.select(Some(0));
-            }
+        let path = std::env::temp_dir().join(format!("logtui-timeline-{}.md", std::process::id()));
+        let Command::Export(spec) = parse(&format!("export timeline {}", path.display())).unwrap()
+        else {
+            panic!("not an export");
+        };
+        app.apply_filter("noise");
+        app.export(&spec).unwrap();
+        while app.export_job.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.tick_export();
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let rows: Vec<&str> = written
+            .lines()
+            .filter(|l| l.starts_with("| 2024"))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "| 2024-05-01T10:00:01Z | INFO | upstream 502 | first 502 |",
+                "| 2024-05-01T10:00:05Z | INFO | deploy finished | deploy \\| done |",
+            ]
+        );
+
+        let json = serde_json::to_string(&Session::capture(&app)).unwrap();
+        let session: Session = serde_json::from_str(&json).unwrap();
+        let mut restored = App::new(10);
+        assert!(session.restore(&mut restored).is_empty());
+        for entry in app.entries.clone() {
+            restored.push(entry);
         }
+        let notes: Vec<&str> = restored.marks.notes().map(|(_, text)| text).collect();
+        assert_eq!(notes, vec!["deploy | done", "first 502"]);
+
+        restored.apply_filter("");
+        restored.select_entry(0);
+        restored.annotate("  ");
+        assert_eq!(restored.marks.notes().count(), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+    #[test]
+    fn jump_list_goes_back_and_forth_by_entry_across_filters() {
+        let mut app = App::new(6);
+        for msg in ["keep 0", "drop 1", "keep 2", "keep 3", "drop 4", "keep 5"] {
+            app.push(entry_with_message(msg));
+        }
+        let selected = |app: &App| app.current_entry().unwrap().message;
+        app.select_entry(1);
+        app.select_last();
+        app.goto(&GotoTarget::Row(3)).unwrap();
+        app.apply_filter("keep");
+        assert_eq!(selected(&app), "keep 2");
 
-    fn entry_with_message(msg: &str) -> LogEntry {
-        LogEntry {
-            timestamp: "-".into(),
-            level: "INFO".into(),
-            message: msg.to_string(),
-            raw: json!({ "message": msg }),
+        // "drop 1" is hidden by the filter, so the list stops at "keep 5".
+        assert!(app.jump_history(true));
+        assert_eq!(selected(&app), "keep 5");
+        assert!(!app.jump_history(true));
+        assert!(app.jump_history(false));
+        assert_eq!(selected(&app), "keep 2");
+        assert!(!app.jump_history(false));
+
+        app.apply_filter("");
+        assert!(app.jump_history(true));
+        assert!(app.jump_history(true));
+        assert_eq!(selected(&app), "drop 1");
+
+        // A new jump drops the forward history.
+        app.select_first();
+        assert!(!app.jump_history(false));
+        assert!(app.jump_history(true));
+        assert_eq!(selected(&app), "drop 1");
+
+        // Evicted entries are skipped.
+        app.select_entry(3);
+        app.goto(&GotoTarget::Row(1)).unwrap();
+        app.goto(&GotoTarget::Row(2)).unwrap();
+        for msg in ["keep 6", "keep 7"] {
+            app.push(entry_with_message(msg));
         }
+        app.select_entry(0);
+        assert_eq!(selected(&app), "keep 2");
+        assert!(app.jump_history(true));
+        assert_eq!(selected(&app), "keep 3");
+        assert!(!app.jump_history(true));
     }
 
     #[test]
-    fn autoscroll_off_preserves_viewport_on_push() {
-        let mut app = App::new(100);
-        app.autoscroll = false;
-        app.last_list_height = 3;
-        app.entries = vec![
-            entry_with_message("one"),
-            entry_with_message("two"),
-            entry_with_message("three"),
+    fn trace_view_nests_spans_and_jumps_to_entries() {
+        let mut app = App::new(10);
+        let lines = [
+            (
+                "2024-05-01T10:00:00Z",
+                "GET /orders",
+                json!({ "span_id": "a" }),
+            ),
+            (
+                "2024-05-01T10:00:01Z",
+                "query db",
+                json!({ "span_id": "b", "parent_span_id": "a" }),
+            ),
+            (
+                "2024-05-01T10:00:03Z",
+                "db done",
+                json!({ "span_id": "b", "parent_span_id": "a" }),
+            ),
+            (
+                "2024-05-01T10:00:04Z",
+                "GET done",
+                json!({ "span_id": "a" }),
+            ),
         ];
-        app.filtered_indices = vec![0, 1, 2];
-        app.list_state.select(Some(1));
-        app.list_scroll_offset = 1;
-        *app.list_state.offset_mut() = 1;
-
-        app.push(entry_with_message("four"));
+        for (ts, msg, extra) in lines {
+            let mut raw = json!({ "timestamp": ts, "message": msg, "otel": { "trace_id": "t1" } });
+            raw.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            app.push(LogEntry::new(ts.into(), "INFO".into(), msg.into(), raw));
+        }
+        let other = json!({ "timestamp": "2024-05-01T10:00:02Z", "trace_id": "t2" });
+        app.push(LogEntry::new(
+            "2024-05-01T10:00:02Z".into(),
+            "INFO".into(),
+            "other".into(),
+            other,
+        ));
+        app.select_entry(2);
 
-        assert_eq!(app.list_scroll_offset, 1, "offset should stay fixed");
+        app.open_trace_view().unwrap();
+        assert!(matches!(app.input_mode, InputMode::TraceView));
+        let view = app.trace_view.as_mut().unwrap();
         assert_eq!(
-            app.list_state.offset(),
-            1,
-            "list state offset should remain"
+            view.rows,
+            vec![
+                TraceRow::Span { span: 0, depth: 0 },
+                TraceRow::Entry { seq: 0, depth: 1 },
+                TraceRow::Span { span: 1, depth: 1 },
+                TraceRow::Entry { seq: 1, depth: 2 },
+                TraceRow::Entry { seq: 2, depth: 2 },
+                TraceRow::Entry { seq: 3, depth: 1 },
+            ]
         );
         assert_eq!(
-            app.list_state.selected(),
-            Some(1),
-            "selection should be unchanged"
+            view.selected_row(),
+            Some(TraceRow::Entry { seq: 2, depth: 2 })
         );
-        assert_eq!(app.filtered_indices.len(), 4, "new entry still recorded");
+        assert_eq!(view.spans[1].duration(), Some(TimeDelta::seconds(2)));
+        assert_eq!(view.duration(), Some(TimeDelta::seconds(4)));
+
+        // Collapsing from an entry folds its span and selects it.
+        view.set_collapsed(true);
+        assert_eq!(view.rows.len(), 4);
+        assert_eq!(
+            view.selected_row(),
+            Some(TraceRow::Span { span: 1, depth: 1 })
+        );
+
+        view.select_row(1);
+        app.trace_view_enter();
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.current_entry().unwrap().message, "GET done");
     }
 
     #[test]
-    fn filter_preserves_selection_when_entry_still_matches() {
-        let mut app = App::new(100);
-        app.entries = vec![
-            entry_with_message("one"),
-            entry_with_message("two"),
-            entry_with_message("three"),
-        ];
-        app.filtered_indices = vec![0, 1, 2];
-        app.list_state.select(Some(1));
+    fn dedupe_folds_consecutive_repeats_over_the_filtered_list() {
+        let mut app = App::new(20);
+        for msg in ["retry", "retry", "retry", "ok", "retry", "retry"] {
+            app.push(entry_with_message(msg));
+        }
+        app.toggle_dedupe();
+        assert_eq!(app.filtered_indices, vec![0, 3, 4]);
+        assert_eq!(app.dedupe.as_ref().unwrap().members(0), &[0, 1, 2]);
 
-        app.apply_filter("two|three");
+        // Folding follows the filters: without "ok" all retries are one run.
+        app.apply_filter("retry");
+        assert_eq!(app.filtered_indices, vec![0]);
+        assert_eq!(app.matched_indices(), &[0, 1, 2, 4, 5]);
+        app.apply_filter("");
 
-        let current = app.current_entry().unwrap();
-        assert_eq!(current.message, "two");
-        assert_eq!(app.filtered_indices, vec![1, 2]);
+        // Selecting a folded entry expands its run; Space folds it again.
+        assert!(app.select_entry(1));
+        assert_eq!(app.filtered_indices, vec![0, 1, 2, 3, 4]);
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert!(app.toggle_repeats());
+        assert_eq!(app.filtered_indices, vec![0, 3, 4]);
+        assert_eq!(app.list_state.selected(), Some(0));
+
+        // A new repeat while tailing joins the last run.
+        assert!(app.autoscroll);
+        app.push(entry_with_message("retry"));
+        assert_eq!(app.filtered_indices, vec![0, 3, 4]);
+        assert_eq!(app.list_state.selected(), Some(2));
+        assert_eq!(app.dedupe.as_ref().unwrap().members(2), &[4, 5, 6]);
+
+        // A different line starts a run; an expanded run shows its new
+        // member, and the selection stays put when not following.
+        app.autoscroll = false;
+        assert!(app.select_entry(0));
+        app.push(entry_with_message("ok"));
+        assert_eq!(app.filtered_indices, vec![0, 3, 4, 7]);
+        app.push(entry_with_message("ok"));
+        assert_eq!(app.filtered_indices, vec![0, 3, 4, 7]);
         assert_eq!(app.list_state.selected(), Some(0));
+        assert!(app.select_entry(8));
+        app.push(entry_with_message("ok"));
+        assert_eq!(app.filtered_indices, vec![0, 3, 4, 7, 8, 9]);
+        assert_eq!(app.list_state.selected(), Some(4));
+
+        // Entries without the chosen fields never fold.
+        app.set_dedupe(Some(DedupeBy::Fields(vec![vec!["code".into()]])));
+        assert_eq!(app.filtered_indices.len(), 10);
+        app.set_dedupe(None);
+        assert_eq!(app.filtered_indices, (0..10).collect::<Vec<_>>());
     }
 
     #[test]
-    fn filter_moves_selection_when_previous_is_filtered_out() {
-        let mut app = App::new(100);
-        app.entries = vec![
-            entry_with_message("one"),
-            entry_with_message("two"),
-            entry_with_message("three"),
-        ];
-        app.filtered_indices = vec![0, 1, 2];
-        app.list_state.select(Some(0));
+    fn dedupe_runs_follow_the_buffer_as_it_wraps() {
+        let mut app = App::new(4);
+        for msg in ["a", "a", "b", "a"] {
+            app.push(entry_with_message(msg));
+        }
+        app.toggle_dedupe();
+        assert!(app.select_entry(1));
+        assert_eq!(app.filtered_indices, vec![0, 1, 2, 3]);
 
-        app.apply_filter("two");
+        // Evicting the head of an expanded run keeps the rest expanded.
+        app.push(entry_with_message("a"));
+        assert_eq!(app.filtered_indices, vec![0, 1, 2]);
+        let dedupe = app.dedupe.as_ref().unwrap();
+        assert!(dedupe.is_expanded(0, app.evicted_entries));
+        assert_eq!(dedupe.members(2), &[2, 3]);
 
-        let current = app.current_entry().unwrap();
-        assert_eq!(current.message, "two");
-        assert_eq!(app.filtered_indices, vec![1]);
-        assert_eq!(app.list_state.selected(), Some(0));
+        // Evicting a run of one drops its row.
+        app.push(entry_with_message("c"));
+        assert_eq!(app.filtered_indices, vec![0, 1, 3]);
+        assert_eq!(app.dedupe.as_ref().unwrap().members(1), &[1, 2]);
+        let rows = app.filtered_indices.clone();
+        app.rebuild_filtered(true);
+        assert_eq!(app.filtered_indices, rows);
     }
 
     #[test]
-    fn eviction_keeps_alignment_and_selection_on_tail() {
-        let mut app = App::new(2);
-        app.push(entry_with_message("one"));
-        app.push(entry_with_message("two"));
-        app.push(entry_with_message("three")); // triggers eviction of "one"
+    fn clusters_mine_templates_and_filter_by_one() {
+        let mut app = App::new(20);
+        app.push(entry_with_level("INFO", "User 42 logged in from 10.0.0.1"));
+        app.push(entry_with_level("INFO", "Disk full on /dev/sda1"));
+        app.push(entry_with_level(
+            "WARN",
+            "User 7 logged in from 192.168.1.9:443",
+        ));
+        app.push(entry_with_level("INFO", "User bob logged in from 10.0.0.2"));
 
-        let messages: Vec<String> = app.entries.iter().map(|e| e.message.clone()).collect();
-        assert_eq!(messages, vec!["two", "three"]);
-        assert_eq!(app.filtered_indices, vec![0, 1]);
-        assert_eq!(app.list_state.selected(), Some(1));
-        assert_eq!(app.current_entry().unwrap().message, "three");
+        app.open_clusters();
+        assert!(matches!(app.input_mode, InputMode::Clusters));
+        let clusters = app.clusters.as_ref().unwrap();
+        let top = &clusters.clusters[&app.cluster_rows[0]];
+        assert_eq!(top.template.to_string(), "User <*> logged in from <IP>");
+        assert_eq!(top.count, 3);
+        assert_eq!(top.levels.get("WARN"), Some(&1));
+        assert_eq!(app.cluster_rows.len(), 2);
+        assert!(clusters.clusters.values().all(|c| !c.new));
+
+        // Templates first seen while tailing are new until the list closes.
+        app.push(entry_with_level("ERROR", "Connection reset by peer"));
+        app.push(entry_with_level("INFO", "User 9 logged in from 10.1.1.1"));
+        assert_eq!(app.cluster_rows.len(), 3);
+        let clusters = app.clusters.as_ref().unwrap();
+        assert!(clusters.clusters[&app.cluster_rows[2]].new);
+        assert_eq!(clusters.clusters[&app.cluster_rows[0]].count, 4);
+
+        app.filter_by_cluster_row();
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.filtered_indices, vec![0, 2, 3, 5]);
+        assert!(
+            app.clusters
+                .as_ref()
+                .unwrap()
+                .clusters
+                .values()
+                .all(|c| !c.new)
+        );
+
+        assert!(app.clear_template_filter());
+        assert_eq!(app.filtered_indices.len(), 6);
+        assert!(!app.clear_template_filter());
     }
 
     #[test]
-    fn toggle_autoscroll_jumps_to_latest() {
-        let mut app = App::new(10);
-        app.autoscroll = false;
-        app.entries = vec![
-            entry_with_message("one"),
-            entry_with_message("two"),
-            entry_with_message("three"),
-        ];
-        app.filtered_indices = vec![0, 1, 2];
-        app.list_state.select(Some(0));
+    fn clusters_follow_the_buffer_as_it_wraps() {
+        let mut app = App::new(4);
+        app.push(LogEntry::new(
+            "2024-05-01T10:00:01Z".into(),
+            "WARN".into(),
+            "Disk full on sda".into(),
+            json!({ "level": "WARN", "message": "Disk full on sda" }),
+        ));
+        app.push(entry_with_level("INFO", "Connection reset by peer"));
+        app.open_clusters();
+        app.filter_by_cluster_row();
+        assert_eq!(app.filtered_indices, vec![0]);
 
-        app.toggle_autoscroll();
+        // The filter takes in the entries filed under its template.
+        app.push(entry_at("2024-05-01T10:00:02Z", "Disk full on sdb"));
+        app.push(entry_with_level("INFO", "Connection reset by peer"));
+        assert_eq!(app.filtered_indices, vec![0, 2]);
+        let id = app.template_filter.unwrap();
+        let cluster = &app.clusters.as_ref().unwrap().clusters[&id];
+        assert_eq!(cluster.template.to_string(), "Disk full on <*>");
+        assert_eq!(
+            cluster.first.unwrap().to_rfc3339(),
+            "2024-05-01T10:00:01+00:00"
+        );
 
-        assert!(app.autoscroll);
-        assert_eq!(app.list_state.selected(), Some(2));
-        assert_eq!(app.current_entry().unwrap().message, "three");
+        // Evicted entries leave their cluster's counts and time span.
+        app.push(entry_with_level("INFO", "Connection reset by peer"));
+        assert_eq!(app.filtered_indices, vec![1]);
+        let cluster = &app.clusters.as_ref().unwrap().clusters[&id];
+        assert_eq!(cluster.count, 1);
+        assert_eq!(cluster.levels.get("WARN"), None);
+        assert_eq!(cluster.first, cluster.last);
+        assert_eq!(
+            cluster.last.unwrap().to_rfc3339(),
+            "2024-05-01T10:00:02+00:00"
+        );
+
+        // An emptied cluster is dropped, and so is a filter on it.
+        for _ in 0..2 {
+            app.push(entry_with_level("INFO", "Connection reset by peer"));
+        }
+        let clusters = app.clusters.as_ref().unwrap();
+        assert_eq!(clusters.clusters.len(), 1);
+        assert_eq!(clusters.by_count().len(), 1);
+        assert_eq!(app.template_filter, None);
+        assert_eq!(app.filtered_indices.len(), 4);
     }
 
     #[test]
-    fn invalid_filter_does_not_change_active_regex() {
+    fn following_a_correlation_id_filters_by_time_and_goes_back() {
         let mut app = App::new(10);
-        app.entries = vec![entry_with_message("one")];
-        app.filtered_indices = vec![0];
+        let lines = [
+            (
+                "2024-05-01T10:00:03Z",
+                "checkout done",
+                json!({ "traceId": "abc" }),
+            ),
+            (
+                "2024-05-01T10:00:01Z",
+                "checkout start",
+                json!({ "traceId": "abc" }),
+            ),
+            (
+                "2024-05-01T10:00:02Z",
+                "charge card",
+                json!({ "ctx": { "trace_id": "abc" }, "requestId": "r1" }),
+            ),
+            (
+                "2024-05-01T10:00:00Z",
+                "other trace",
+                json!({ "traceId": "xyz", "requestId": "r1" }),
+            ),
+            ("2024-05-01T10:00:04Z", "no id", json!({})),
+        ];
+        for (ts, msg, extra) in lines {
+            let mut raw = json!({ "timestamp": ts, "message": msg });
+            raw.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            app.push(LogEntry::new(ts.into(), "INFO".into(), msg.into(), raw));
+        }
+        app.apply_filter("checkout");
+        app.select_entry(0);
 
-        app.apply_filter("["); // invalid regex
+        app.follow_correlation().unwrap();
+        let shown: Vec<&str> = app
+            .filtered_indices
+            .iter()
+            .map(|&idx| app.entries[idx].message.as_str())
+            .collect();
+        assert_eq!(
+            shown,
+            vec!["checkout start", "charge card", "checkout done"]
+        );
+        assert!(app.filter_query.is_empty());
+        assert_eq!(app.current_entry().unwrap().message, "checkout done");
 
-        assert!(app.filter_regex.is_none());
-        assert!(app.filter_error.is_some());
-        assert_eq!(app.filtered_indices, vec![0]);
+        // Following another id from inside the trail stacks another step.
+        app.correlation_fields = vec![vec!["requestId".into()]];
+        app.select_entry(2);
+        app.follow_correlation().unwrap();
+        assert_eq!(app.filtered_indices, vec![3, 2]);
+        assert_eq!(app.correlation_trail.len(), 2);
+
+        assert!(app.unfollow_correlation());
+        assert_eq!(app.filtered_indices.len(), 3);
+        assert!(app.unfollow_correlation());
+        assert_eq!(app.filter_query, "checkout");
+        assert_eq!(app.sort, None);
+        assert_eq!(app.filtered_indices, vec![0, 1]);
+        assert_eq!(app.current_entry().unwrap().message, "checkout done");
+        assert!(!app.unfollow_correlation());
+
+        app.apply_filter("");
+        app.select_entry(4);
+        assert!(app.follow_correlation().is_err());
+        app.correlation_fields = vec![vec!["message".into()]];
+        app.follow_correlation().unwrap();
+        assert_eq!(app.filtered_indices, vec![4]);
     }
 
     #[test]
-    fn column_discovery_adds_new_fields_once() {
-        let mut app = App::new(10);
-        assert_eq!(app.columns.len(), 3); // default columns
+    fn keymap_defaults_cover_every_action_and_rebinding_updates_help() {
+        use crate::keys::KeyChord;
 
-        app.discover_columns(&json!({"foo": "bar", "data": { "baz": 1 }}));
-        let names: Vec<String> = app.columns.iter().map(|c| c.name.clone()).collect();
-        assert!(names.contains(&"foo".to_string()));
-        assert!(names.contains(&"data.baz".to_string()));
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+        let mut keymap = Keymap::default();
+        for mode in KeyMode::ALL {
+            assert_eq!(
+                keymap.help(mode).len(),
+                mode.actions().len(),
+                "every {mode:?} action has a default key"
+            );
+        }
+        assert_eq!(
+            keymap.actions(KeyMode::FieldView, chord("ctrl+u")),
+            vec![Action::ClearInput, Action::HalfPageUp]
+        );
 
-        // Repeat discovery should not duplicate
-        app.discover_columns(&json!({"foo": "again", "data": { "baz": 2 }}));
-        let foo_count = app.columns.iter().filter(|c| c.name == "foo").count();
-        let baz_count = app.columns.iter().filter(|c| c.name == "data.baz").count();
-        assert_eq!(foo_count, 1);
-        assert_eq!(baz_count, 1);
+        keymap
+            .bind(KeyMode::Normal, chord("ctrl+f"), &[Action::HalfPageDown])
+            .unwrap();
+        keymap.bind(KeyMode::Normal, chord("ctrl+d"), &[]).unwrap();
+        let keys = |keymap: &Keymap, action| {
+            keymap
+                .help(KeyMode::Normal)
+                .into_iter()
+                .find(|(a, _)| *a == action)
+                .map(|(_, keys)| keys.iter().map(ToString::to_string).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            keys(&keymap, Action::HalfPageDown),
+            Some(vec!["Ctrl+f".into()])
+        );
+        keymap.bind(KeyMode::Normal, chord("ctrl+f"), &[]).unwrap();
+        assert_eq!(keys(&keymap, Action::HalfPageDown), None);
+        assert!(
+            keymap
+                .bind(KeyMode::ColumnSelect, chord("x"), &[Action::ToggleJq])
+                .is_err()
+        );
     }
 
     #[test]
-    fn toggle_column_enabled_flag() {
+    fn config_errors_name_the_problem() {
+        use crate::config::Config;
+
+        let err = |toml: &str| format!("{:#}", Config::parse(toml).unwrap_err());
+        assert!(err("colour = true").contains("unknown field `colour`"));
+        assert!(err("[[columns]]\nname = \"a\"\nwdth = 3").contains("unknown field `wdth`"));
+        assert!(err("timezone = \"Mars/Base\"").contains("unknown time zone"));
+        assert!(err("[keys.normal]\n\"hyper+x\" = \"down\"").contains("unknown modifier"));
+        assert!(err("[keys.normal]\nx = \"jump\"").contains("unknown action 'jump'"));
+        assert!(err("[keys.insert]\nx = \"down\"").contains("unknown field `insert`"));
+        assert!(err("[[columns]]\nname = \"a\"\nexpr = \".a |\"").contains("jq"));
+
         let mut app = App::new(10);
-        app.column_select_state.select(Some(0));
-        let before = app.columns[0].enabled;
-        app.move_column(0); // no-op move; ensures selection exists
-        if let Some(idx) = app.column_select_state.selected() {
-            app.columns[idx].enabled = !before;
-        }
-        assert_ne!(app.columns[0].enabled, before);
+        let dup = Config::parse("[[columns]]\nname = \"a\"\n[[columns]]\nname = \"a\"").unwrap();
+        assert!(dup.apply(&mut app).is_err());
+        let bad_filter = Config::parse("filter = \"(\"").unwrap();
+        assert!(bad_filter.apply(&mut app).is_err());
+        let wrong_mode = Config::parse("[keys.filter_input]\nx = \"quit\"").unwrap();
+        let err = format!("{:#}", wrong_mode.apply(&mut app).unwrap_err());
+        assert!(err.contains("not available in keys.filter_input"), "{err}");
     }
 
     #[test]
-    fn eviction_rebases_filtered_indices_with_filter_active() {
-        let mut app = App::new(2);
-        app.apply_filter("two|three"); // set filter before data arrives
-
-        app.push(entry_with_message("one")); // filtered out
-        app.push(entry_with_message("two")); // kept
-        app.push(entry_with_message("three")); // evicts "one"
+    fn project_config_overrides_user_config() {
+        use crate::config::Config;
 
-        let msgs: Vec<_> = app
-            .filtered_indices
-            .iter()
-            .filter_map(|&i| app.entries.get(i))
-            .map(|e| e.message.clone())
-            .collect();
-        assert_eq!(msgs, vec!["two", "three"]);
-        assert_eq!(app.entries.len(), 2);
-        assert_eq!(app.filtered_indices, vec![0, 1]);
-        assert_eq!(app.list_state.selected(), Some(1));
-        assert_eq!(app.current_entry().unwrap().message, "three");
+        let mut config = Config::parse(
+            "max_entries = 100\nwrap = false\n[colors.levels]\ninfo = \"green\"\nerror = \"red\"",
+        )
+        .unwrap();
+        config.merge(Config::parse("max_entries = 50\n[colors.levels]\ninfo = \"blue\"").unwrap());
+        assert_eq!(config.max_entries, Some(50));
+        assert_eq!(config.wrap, Some(false));
+        assert_eq!(config.colors.levels.len(), 2);
+        assert_eq!(config.colors.levels["info"].0, ratatui::style::Color::Blue);
     }
 
     #[test]
-    fn field_view_filter_rebuilds_indices_and_resets_selection() {
-        let mut app = App::new(10);
-        let entry = LogEntry {
-            timestamp: "-".into(),
-            level: "INFO".into(),
-            message: "has data".into(),
-            raw: json!({"a": 1, "b": 2, "nested": { "c": 3 }}),
+    fn detected_profile_remaps_fields_and_applies_its_view() {
+        use crate::{
+            config::Config,
+            input::{InputEvent, InputSource, spawn_reader},
         };
-        app.entries.push(entry.clone());
-        app.filtered_indices = vec![0];
-        app.list_state.select(Some(0));
 
-        app.enter_field_view();
-        let fv = app.field_view.as_mut().unwrap();
-        // initial selection
-        assert_eq!(fv.list_state.selected(), Some(0));
-        fv.filter = "nested".into();
-        let _ = fv.rebuild_filter(); // may return false when selection stays valid
-        assert_eq!(fv.filtered_indices.len(), 2); // nested and nested.c
-        assert_eq!(fv.list_state.selected(), Some(0));
+        let config = Config::parse(
+            r#"
+            [fields]
+            levels = { "50" = "ERROR" }
+
+            [profile.article-api]
+            match = '.service == "article-api"'
+            exclude = ["healthcheck"]
+            fields = { message = "msg", level = "lvl", levels = { "30" = "INFO" } }
+            columns = [{ name = "level" }, { name = "route", path = "req.route" }, { name = "message" }]
+
+            [profile.other]
+            match = '.service == "other"'
+            "#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("logtui-profile-{}.log", std::process::id()));
+        std::fs::write(
+            &path,
+            [
+                r#"{"msg":"booting","lvl":30}"#,
+                r#"{"service":"article-api","msg":"GET /a","lvl":30,"req":{"route":"/a"}}"#,
+                r#"{"service":"article-api","msg":"healthcheck","lvl":30}"#,
+                r#"{"service":"article-api","msg":"boom","lvl":50}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let mut app = App::new(10);
+        config.apply(&mut app).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn_reader(
+            InputSource::File(path.clone()),
+            tx,
+            config.fields.to_mapping(),
+            config.detectors(),
+        );
+        for event in rx {
+            match event {
+                InputEvent::Entry(entry) => app.push(entry),
+                InputEvent::Profile { name, fields } => app.activate_profile(&name, &fields),
+            }
+        }
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(app.active_profile.as_deref(), Some("article-api"));
+        // The line read before detection is re-parsed with the profile's fields.
+        let shown: Vec<(&str, &str)> = app
+            .filtered_indices
+            .iter()
+            .map(|&i| {
+                (
+                    app.entries[i].level.as_str(),
+                    app.entries[i].message.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            shown,
+            vec![("INFO", "booting"), ("INFO", "GET /a"), ("ERROR", "boom")]
+        );
+        assert_eq!(app.level_counts.get("INFO"), Some(&3));
+        let names: Vec<&str> = app.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(&names[..3], ["level", "route", "message"]);
+        assert!(
+            names.contains(&"service"),
+            "discovered fields stay available"
+        );
+        assert_eq!(app.columns[1].value(&app.entries[1]).as_deref(), Some("/a"));
     }
 
     #[test]
-    fn paused_input_discards_new_entries() {
-        let mut app = App::new(5);
-        app.input_paused = true;
-
-        app.ingest(entry_with_message("ignored"));
+    fn unknown_profile_lists_configured_ones() {
+        use crate::config::Config;
 
-        assert!(app.entries.is_empty());
-        assert!(app.filtered_indices.is_empty());
+        let config = Config::parse("[profile.a]\n[profile.b]\nfields.level = \"sev\"").unwrap();
+        let err = config.find_profile("c").unwrap_err().to_string();
+        assert!(err.contains("configured: a, b"), "{err}");
+        assert!(
+            config.detectors().is_empty(),
+            "profiles without match are never detected"
+        );
+        assert!(Config::parse("[profile.a]\ncolumn = []").is_err());
     }
 
     #[test]
-    fn ingest_resumes_after_toggle() {
-        let mut app = App::new(5);
-        app.input_paused = true;
+    fn session_round_trip_restores_view_and_selection() {
+        use crate::app::session::Session;
 
-        app.ingest(entry_with_message("ignored"));
-        app.toggle_input_pause();
-        app.ingest(entry_with_message("kept"));
+        let lines = [
+            entry_at("2024-03-01T12:00:01Z", "alpha"),
+            entry_at("2024-03-01T12:00:02Z", "beta"),
+            entry_at("2024-03-01T12:00:03Z", "gamma"),
+            entry_at("2024-03-01T12:00:04Z", "beta"),
+        ];
+        let mut app = App::new(10);
+        for entry in lines.clone() {
+            app.push(entry);
+        }
+        app.sources = vec![SourceSpec::File("svc.log".into())];
+        app.begin_column_input();
+        app.column_buffer = "len = .message | length".into();
+        app.submit_column_input();
+        app.add_filter_layer("gamma", FilterKind::Exclude);
+        app.filter_modes.literal = true;
+        app.apply_filter("a");
+        sort_by(&mut app, "timestamp", true);
+        app.zoom = Some(Focus::Detail);
+        app.detail_wrap = false;
+        // The second "beta": same message, different timestamp.
+        app.list_state.select(Some(0));
+        assert_eq!(
+            app.current_entry().unwrap().timestamp,
+            "2024-03-01T12:00:04Z"
+        );
 
-        assert_eq!(app.entries.len(), 1);
-        assert_eq!(app.current_entry().unwrap().message, "kept");
+        let json = serde_json::to_string(&Session::capture(&app)).unwrap();
+        let session: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(session.sources, app.sources);
+
+        let mut restored = App::new(10);
+        assert!(session.restore(&mut restored).is_empty());
+        for entry in lines {
+            restored.push(entry);
+        }
+        assert_eq!(restored.filter_query, "a");
+        assert!(restored.filter_query_modes.literal);
+        assert_eq!(restored.filter_stack.len(), 1);
+        assert_eq!(restored.filter_stack[0].kind, FilterKind::Exclude);
+        assert_eq!(restored.sort, app.sort);
+        assert!(
+            restored
+                .columns
+                .iter()
+                .any(|c| c.name == "len" && c.expr.is_some())
+        );
+        assert_eq!(restored.zoom, Some(Focus::Detail));
+        assert!(!restored.detail_wrap);
+        assert_eq!(restored.filtered_indices, app.filtered_indices);
+        assert_eq!(
+            restored.current_entry().map(|e| e.timestamp),
+            Some("2024-03-01T12:00:04Z".to_string())
+        );
+        assert!(restored.pending_selection.is_none());
     }
 }
@@ -0,0 +1,315 @@
+//! The span tree of one trace, built from entries carrying OpenTelemetry-style
+//! `trace_id`, `span_id` and `parent_span_id` fields.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use ratatui::widgets::ListState;
+use serde_json::Value;
+
+use crate::{model::LogEntry, timestamps::TimeZoneSpec};
+
+use super::correlation::{id_text, lookup};
+
+/// Names the ids go by, found at any depth of the JSON.
+const TRACE_FIELDS: [&str; 2] = ["trace_id", "traceId"];
+const SPAN_FIELDS: [&str; 2] = ["span_id", "spanId"];
+const PARENT_FIELDS: [&str; 2] = ["parent_span_id", "parentSpanId"];
+
+fn first_id(value: &Value, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|&name| lookup(value, &[name.to_string()]).and_then(id_text))
+}
+
+/// The trace id of an entry, if it has one.
+pub fn trace_id(value: &Value) -> Option<String> {
+    first_id(value, &TRACE_FIELDS)
+}
+
+/// One span: its own entries, and the time they and its child spans cover.
+#[derive(Clone, Debug)]
+pub struct Span {
+    /// Empty for the entries of the trace that carry no span id.
+    pub id: String,
+    pub parent: Option<String>,
+    /// The message of the span's first entry.
+    pub name: String,
+    /// Absolute sequence numbers and times of the span's entries, in time
+    /// order.
+    pub entries: Vec<(u64, Option<DateTime<Utc>>)>,
+    pub children: Vec<usize>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Span {
+    pub fn duration(&self) -> Option<TimeDelta> {
+        Some(self.end? - self.start?)
+    }
+}
+
+/// A row of the tree: a span, or one of its entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceRow {
+    Span { span: usize, depth: usize },
+    Entry { seq: u64, depth: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceView {
+    pub trace_id: String,
+    pub spans: Vec<Span>,
+    roots: Vec<usize>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    collapsed: BTreeSet<usize>,
+    /// The visible rows, rebuilt when a span is collapsed or expanded.
+    pub rows: Vec<TraceRow>,
+    pub list_state: ListState,
+}
+
+impl TraceView {
+    /// Groups the entries of `trace_id` (given with their sequence numbers)
+    /// into spans. Spans whose parent is not among them become roots.
+    pub fn build<'a>(
+        trace_id: &str,
+        entries: impl Iterator<Item = (u64, &'a LogEntry)>,
+        zone: &TimeZoneSpec,
+    ) -> Self {
+        let mut spans: Vec<Span> = Vec::new();
+        let mut by_id: HashMap<String, usize> = HashMap::new();
+        for (seq, entry) in entries {
+            if !entry.search_text.contains(trace_id)
+                || self::trace_id(&entry.raw).as_deref() != Some(trace_id)
+            {
+                continue;
+            }
+            let id = first_id(&entry.raw, &SPAN_FIELDS).unwrap_or_default();
+            let pos = *by_id.entry(id.clone()).or_insert_with(|| {
+                spans.push(Span {
+                    name: if id.is_empty() {
+                        "(no span)".to_string()
+                    } else {
+                        entry.message.clone()
+                    },
+                    id,
+                    parent: None,
+                    entries: Vec::new(),
+                    children: Vec::new(),
+                    start: None,
+                    end: None,
+                });
+                spans.len() - 1
+            });
+            let span = &mut spans[pos];
+            if span.parent.is_none() {
+                span.parent = first_id(&entry.raw, &PARENT_FIELDS);
+            }
+            span.entries
+                .push((seq, entry.time.and_then(|t| t.instant(zone))));
+        }
+
+        let mut roots = Vec::new();
+        for pos in 0..spans.len() {
+            spans[pos]
+                .entries
+                .sort_by_key(|&(seq, at)| (at.is_none(), at, seq));
+            match spans[pos].parent.as_ref().and_then(|p| by_id.get(p)) {
+                Some(&parent) if parent != pos => spans[parent].children.push(pos),
+                _ => roots.push(pos),
+            }
+        }
+        // Spans in a parent cycle are unreachable from the roots; list them
+        // as roots of their own.
+        let mut seen = vec![false; spans.len()];
+        let mut stack = roots.clone();
+        while let Some(pos) = stack.pop() {
+            if !std::mem::replace(&mut seen[pos], true) {
+                stack.extend(&spans[pos].children);
+            }
+        }
+        for pos in (0..spans.len()).filter(|&pos| !seen[pos]) {
+            spans
+                .iter_mut()
+                .for_each(|s| s.children.retain(|&c| c != pos));
+            roots.push(pos);
+        }
+
+        for &root in &roots {
+            cover(&mut spans, root);
+        }
+        let by_start = |spans: &[Span], a: &usize, b: &usize| {
+            let key = |s: &Span| (s.start.is_none(), s.start);
+            key(&spans[*a]).cmp(&key(&spans[*b])).then(a.cmp(b))
+        };
+        for pos in 0..spans.len() {
+            let mut children = std::mem::take(&mut spans[pos].children);
+            children.sort_by(|a, b| by_start(&spans, a, b));
+            spans[pos].children = children;
+        }
+        roots.sort_by(|a, b| by_start(&spans, a, b));
+
+        let start = roots.iter().filter_map(|&r| spans[r].start).min();
+        let end = roots.iter().filter_map(|&r| spans[r].end).max();
+        let mut view = Self {
+            trace_id: trace_id.to_string(),
+            spans,
+            roots,
+            start,
+            end,
+            collapsed: BTreeSet::new(),
+            rows: Vec::new(),
+            list_state: ListState::default(),
+        };
+        view.rebuild_rows();
+        view.list_state.select((!view.rows.is_empty()).then_some(0));
+        view
+    }
+
+    pub fn duration(&self) -> Option<TimeDelta> {
+        Some(self.end? - self.start?)
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.spans.iter().map(|s| s.entries.len()).sum()
+    }
+
+    pub fn is_collapsed(&self, span: usize) -> bool {
+        self.collapsed.contains(&span)
+    }
+
+    pub fn selected_row(&self) -> Option<TraceRow> {
+        self.rows.get(self.list_state.selected()?).copied()
+    }
+
+    pub fn select_row(&mut self, delta: isize) {
+        let len = self.rows.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        self.list_state.select(Some(next));
+    }
+
+    /// Collapses (or expands) the selected span. On an entry row, collapsing
+    /// folds the span it belongs to and selects it.
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        let Some(pos) = self.list_state.selected() else {
+            return;
+        };
+        let span = match self.rows[pos] {
+            TraceRow::Span { span, .. } => span,
+            TraceRow::Entry { .. } if !collapsed => return,
+            TraceRow::Entry { depth, .. } => {
+                let Some((row, span)) =
+                    self.rows[..pos]
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .find_map(|(row, r)| match *r {
+                            TraceRow::Span { span, depth: d } if d + 1 == depth => {
+                                Some((row, span))
+                            }
+                            _ => None,
+                        })
+                else {
+                    return;
+                };
+                self.list_state.select(Some(row));
+                span
+            }
+        };
+        if collapsed {
+            self.collapsed.insert(span);
+        } else {
+            self.collapsed.remove(&span);
+        }
+        self.rebuild_rows();
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(TraceRow::Span { span, .. }) = self.selected_row() {
+            let collapsed = !self.is_collapsed(span);
+            self.set_collapsed(collapsed);
+        }
+    }
+
+    /// Spans with their entries and child spans interleaved in time order.
+    fn rebuild_rows(&mut self) {
+        let mut rows = Vec::new();
+        for &root in &self.roots {
+            self.push_rows(root, 0, &mut rows);
+        }
+        self.rows = rows;
+        let len = self.rows.len();
+        if let Some(selected) = self.list_state.selected() {
+            self.list_state
+                .select((len > 0).then(|| selected.min(len - 1)));
+        }
+    }
+
+    fn push_rows(&self, span: usize, depth: usize, rows: &mut Vec<TraceRow>) {
+        rows.push(TraceRow::Span { span, depth });
+        if self.collapsed.contains(&span) {
+            return;
+        }
+        let this = &self.spans[span];
+        let mut entries = this.entries.iter().peekable();
+        let mut children = this.children.iter().peekable();
+        loop {
+            let take_entry = match (entries.peek(), children.peek()) {
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some((_, at)), Some(&&child)) => match (at, self.spans[child].start) {
+                    (Some(at), Some(start)) => *at <= start,
+                    (None, _) => false,
+                    (_, None) => true,
+                },
+                (None, None) => break,
+            };
+            if take_entry {
+                let &(seq, _) = entries.next().unwrap();
+                rows.push(TraceRow::Entry {
+                    seq,
+                    depth: depth + 1,
+                });
+            } else {
+                let &child = children.next().unwrap();
+                self.push_rows(child, depth + 1, rows);
+            }
+        }
+    }
+}
+
+/// Widens each span's start and end to cover its entries and children.
+fn cover(spans: &mut [Span], pos: usize) {
+    let children = spans[pos].children.clone();
+    for &child in &children {
+        cover(spans, child);
+    }
+    let times: Vec<DateTime<Utc>> = spans[pos]
+        .entries
+        .iter()
+        .filter_map(|&(_, at)| at)
+        .chain(
+            children
+                .iter()
+                .flat_map(|&c| [spans[c].start, spans[c].end])
+                .flatten(),
+        )
+        .collect();
+    spans[pos].start = times.iter().min().copied();
+    spans[pos].end = times.iter().max().copied();
+}
+
+/// `850ms`, `1.25s`, `3m04s`.
+pub fn format_duration(d: TimeDelta) -> String {
+    let millis = d.num_milliseconds().max(0);
+    match millis {
+        0..1000 => format!("{millis}ms"),
+        1000..60_000 => format!("{:.2}s", millis as f64 / 1000.0),
+        _ => format!("{}m{:02}s", millis / 60_000, millis % 60_000 / 1000),
+    }
+}
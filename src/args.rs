@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::timestamps::TimeZoneSpec;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Interactive TUI log viewer")]
 pub struct Args {
@@ -9,7 +11,24 @@ pub struct Args {
     #[arg(short, long)]
     pub file: Option<PathBuf>,
 
-    /// Maximum number of log entries to keep in memory
-    #[arg(long, default_value_t = 5000)]
-    pub max_entries: usize,
+    /// Maximum number of log entries to keep in memory [default: 5000]
+    #[arg(long)]
+    pub max_entries: Option<usize>,
+
+    /// Config file to use instead of $XDG_CONFIG_HOME/logtui/config.toml
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Session file: restored on startup if it exists, written by `S`
+    #[arg(long)]
+    pub session: Option<PathBuf>,
+
+    /// Config profile to use instead of detecting one from the first lines
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Time zone for converted timestamps: local, UTC, an IANA name
+    /// (Europe/Berlin) or an offset (+05:30) [default: local]
+    #[arg(long)]
+    pub tz: Option<TimeZoneSpec>,
 }
@@ -0,0 +1,99 @@
+//! Copying text to the system clipboard. The OSC 52 escape asks the terminal
+//! to do it, which also works over SSH; `wl-copy` or `xclip` take over when
+//! the terminal is known to ignore the escape.
+
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Escapes longer than this are dropped by many terminals.
+const OSC52_MAX_LEN: usize = 100_000;
+
+/// Copies `text` and returns how it was sent: `"OSC 52"`, or the external
+/// tool used instead.
+pub fn copy(text: &str) -> io::Result<&'static str> {
+    let escape = osc52(text, env::var_os("TMUX").is_some());
+    if !osc52_usable(escape.len())
+        && let Some((program, args)) = fallback_tool()
+        && pipe_to(program, args, text).is_ok()
+    {
+        return Ok(program);
+    }
+    // Without a tool the escape is still worth a try.
+    let mut stdout = io::stdout();
+    stdout.write_all(escape.as_bytes())?;
+    stdout.flush()?;
+    Ok("OSC 52")
+}
+
+/// Whether the terminal is likely to apply an escape of `len` bytes. VTE
+/// terminals (GNOME Terminal, Tilix), Apple's Terminal and the Linux console
+/// ignore OSC 52.
+fn osc52_usable(len: usize) -> bool {
+    let vte = env::var_os("VTE_VERSION").is_some();
+    let apple = env::var("TERM_PROGRAM").is_ok_and(|t| t == "Apple_Terminal");
+    let console = env::var("TERM").is_ok_and(|t| t == "linux");
+    len <= OSC52_MAX_LEN && !vte && !apple && !console
+}
+
+fn fallback_tool() -> Option<(&'static str, &'static [&'static str])> {
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        Some(("wl-copy", &[]))
+    } else if env::var_os("DISPLAY").is_some() {
+        Some(("xclip", &["-selection", "clipboard"]))
+    } else {
+        None
+    }
+}
+
+/// The escape that sets the clipboard to `text`, wrapped for tmux to pass it
+/// through to the outer terminal.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// Starts `program` and feeds it `text` from a thread, since `xclip` keeps
+/// running until something else takes the clipboard.
+fn pipe_to(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let text = text.to_string();
+    thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
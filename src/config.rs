@@ -0,0 +1,531 @@
+//! User configuration: `$XDG_CONFIG_HOME/logtui/config.toml` (or `--config`),
+//! overridden per project by `./.logtui.toml`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{
+    app::{Action, App, ColumnDef, ColumnWidth, FilterKind, KeyMode, default_column},
+    input::{Detector, FieldMapping},
+    jq,
+    keys::KeyChord,
+    timestamps::{TimeZoneSpec, TimestampMode},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+const PROJECT_FILE: &str = ".logtui.toml";
+
+/// Every setting is optional; a later file only overrides what it sets.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub max_entries: Option<usize>,
+    /// Regex applied as the `/` filter on startup.
+    pub filter: Option<String>,
+    /// Wrap long lines in the detail pane.
+    pub wrap: Option<bool>,
+    /// Wrap long lines in the field viewer's value pane.
+    pub field_wrap: Option<bool>,
+    pub timezone: Option<Parsed<TimeZoneSpec>>,
+    pub timestamp_format: Option<Parsed<TimestampMode>>,
+    /// Columns in display order; replaces the default set.
+    pub columns: Option<Vec<ColumnConfig>>,
+    pub colors: ColorConfig,
+    /// Key bindings per mode.
+    pub keys: KeysConfig,
+    /// Where timestamp, level and message are read from.
+    pub fields: FieldsConfig,
+    /// Filter-stack layers added on startup.
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    /// Fields `*` takes a correlation id from, e.g. `traceId` (any depth) or
+    /// `context.requestId`.
+    pub correlation_fields: Option<Vec<String>>,
+    /// Settings for one kind of log, applied over the ones above.
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// A named set of settings for one log shape (`[profile.<name>]`), chosen
+/// with `--profile` or by its `match` expression.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// jq expression; the profile is picked when one of the first lines
+    /// matches it.
+    #[serde(rename = "match")]
+    pub signature: Option<Parsed<jq::Filter>>,
+    pub fields: FieldsConfig,
+    pub columns: Option<Vec<ColumnConfig>>,
+    pub filter: Option<String>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub timestamp_format: Option<Parsed<TimestampMode>>,
+    pub colors: ColorConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldsConfig {
+    /// Dotted paths, e.g. `ts` or `data.severity`.
+    pub timestamp: Option<String>,
+    pub level: Option<String>,
+    pub message: Option<String>,
+    /// Raw level values to level names, e.g. `30 = "INFO"`.
+    pub levels: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnConfig {
+    pub name: String,
+    /// Dotted field path; defaults to the name.
+    pub path: Option<String>,
+    /// jq expression for a computed column.
+    pub expr: Option<Parsed<jq::Filter>>,
+    pub enabled: Option<bool>,
+    #[serde(default, deserialize_with = "width")]
+    pub width: Option<ColumnWidth>,
+    pub sticky: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    /// Border of the focused pane.
+    pub accent: Option<Parsed<Color>>,
+    /// Level name (any case) to color.
+    pub levels: BTreeMap<String, Parsed<Color>>,
+}
+
+/// A config value read from a string with `FromStr`, so mistakes are reported
+/// with the file position like any other type error.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Parsed<T>(pub T);
+
+impl<'de, T> Deserialize<'de> for Parsed<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map(Parsed).map_err(de::Error::custom)
+    }
+}
+
+/// `[keys.<mode>]` tables: a key and the action it runs, or a list of
+/// actions tried in order until one applies. Plain `"ctrl+j" = "j"` entries
+/// directly under `[keys]` are remaps: outside the prompts and the field
+/// viewer, the left key acts as the right one.
+#[derive(Debug, Default)]
+pub struct KeysConfig {
+    pub modes: HashMap<KeyMode, HashMap<Parsed<KeyChord>, Bindings>>,
+    pub remaps: HashMap<Parsed<KeyChord>, Parsed<KeyChord>>,
+}
+
+impl KeysConfig {
+    fn merge(&mut self, other: KeysConfig) {
+        for (mode, table) in other.modes {
+            self.modes.entry(mode).or_default().extend(table);
+        }
+        self.remaps.extend(other.remaps);
+    }
+}
+
+const MODE_NAMES: [&str; 12] = [
+    "normal",
+    "filter_input",
+    "column_select",
+    "field_view",
+    "level_select",
+    "field_filter",
+    "saved_filters",
+    "filter_stack",
+    "palette",
+    "bookmarks",
+    "trace_view",
+    "clusters",
+];
+
+/// A plain `[keys]` entry: a remap target, or a table under a name that is
+/// not a mode.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RemapValue {
+    Key(String),
+    Table(de::IgnoredAny),
+}
+
+impl<'de> Deserialize<'de> for KeysConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> de::Visitor<'de> for KeysVisitor {
+            type Value = KeysConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("tables of key bindings per mode, or key remaps")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<KeysConfig, A::Error> {
+                let mut keys = KeysConfig::default();
+                while let Some(name) = map.next_key::<String>()? {
+                    match KeyMode::ALL.into_iter().find(|mode| mode.name() == name) {
+                        Some(mode) => {
+                            keys.modes.insert(mode, map.next_value()?);
+                        }
+                        None => match map.next_value()? {
+                            RemapValue::Key(to) => {
+                                let from = name.parse().map_err(de::Error::custom)?;
+                                let to = to.parse().map_err(de::Error::custom)?;
+                                keys.remaps.insert(Parsed(from), Parsed(to));
+                            }
+                            RemapValue::Table(_) => {
+                                return Err(de::Error::unknown_field(&name, &MODE_NAMES));
+                            }
+                        },
+                    }
+                }
+                Ok(keys)
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+/// The actions of one key binding; `"none"` or `[]` unbinds the key.
+#[derive(Clone, Debug)]
+pub struct Bindings(pub Vec<Action>);
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BindingsVisitor;
+
+        impl<'de> de::Visitor<'de> for BindingsVisitor {
+            type Value = Bindings;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an action name or a list of them")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Bindings, E> {
+                if s == "none" {
+                    return Ok(Bindings(Vec::new()));
+                }
+                s.parse()
+                    .map(|action| Bindings(vec![action]))
+                    .map_err(E::custom)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Bindings, A::Error> {
+                let mut actions = Vec::new();
+                while let Some(Parsed(action)) = seq.next_element::<Parsed<Action>>()? {
+                    actions.push(action);
+                }
+                Ok(Bindings(actions))
+            }
+        }
+
+        deserializer.deserialize_any(BindingsVisitor)
+    }
+}
+
+/// Widths are `"auto"`, `"flex"` or a number of cells.
+fn width<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ColumnWidth>, D::Error> {
+    struct WidthVisitor;
+
+    impl de::Visitor<'_> for WidthVisitor {
+        type Value = ColumnWidth;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("\"auto\", \"flex\" or a number of cells")
+        }
+
+        fn visit_i64<E: de::Error>(self, n: i64) -> Result<ColumnWidth, E> {
+            n.to_string().parse().map_err(E::custom)
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<ColumnWidth, E> {
+            s.parse().map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(WidthVisitor).map(Some)
+}
+
+impl Config {
+    /// Reads the user config (`explicit` replaces the default location, and
+    /// must exist), then layers `./.logtui.toml` on top. Missing default files
+    /// are fine; malformed ones and unknown keys are errors.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let mut config = match explicit {
+            Some(path) => {
+                if !path.exists() {
+                    bail!("config file {} does not exist", path.display());
+                }
+                Self::read(path)?
+            }
+            None => match config_dir() {
+                Some(dir) if dir.join(CONFIG_FILE).exists() => Self::read(&dir.join(CONFIG_FILE))?,
+                _ => Self::default(),
+            },
+        };
+        let project = Path::new(PROJECT_FILE);
+        if project.exists() {
+            config.merge(Self::read(project)?);
+        }
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Overrides the settings `other` sets; colors and keys merge per entry.
+    pub fn merge(&mut self, other: Config) {
+        macro_rules! take {
+            ($($field:ident),*) => {$(
+                if other.$field.is_some() {
+                    self.$field = other.$field;
+                }
+            )*};
+        }
+        take!(
+            max_entries,
+            filter,
+            wrap,
+            field_wrap,
+            timezone,
+            timestamp_format,
+            columns,
+            include,
+            exclude,
+            correlation_fields
+        );
+        self.colors.merge(other.colors);
+        self.keys.merge(other.keys);
+        self.fields.merge(other.fields);
+        self.profile.extend(other.profile);
+    }
+
+    /// Applies everything but `max_entries`, `timezone` and `fields`, which
+    /// the caller resolves against the command line and the input reader.
+    pub fn apply(&self, app: &mut App) -> Result<()> {
+        if let Some(columns) = &self.columns {
+            apply_columns(app, columns)?;
+        }
+        if let Some(wrap) = self.wrap {
+            app.detail_wrap = wrap;
+        }
+        if let Some(wrap) = self.field_wrap {
+            app.field_detail_wrap = wrap;
+        }
+        if let Some(Parsed(mode)) = self.timestamp_format {
+            app.timestamp_mode = mode;
+        }
+        if let Some(fields) = &self.correlation_fields {
+            app.correlation_fields = fields
+                .iter()
+                .map(|field| field.split('.').map(str::to_string).collect())
+                .collect();
+        }
+        apply_colors(app, &self.colors);
+        apply_keys(app, &self.keys)?;
+        apply_filters(app, &self.filter, &self.include, &self.exclude)?;
+        app.profiles = self.profile.clone();
+        Ok(())
+    }
+
+    /// The profile called `name`, or an error listing the known ones.
+    pub fn find_profile(&self, name: &str) -> Result<&Profile> {
+        match self.profile.get(name) {
+            Some(profile) => Ok(profile),
+            None if self.profile.is_empty() => bail!("unknown profile '{name}': none configured"),
+            None => bail!(
+                "unknown profile '{name}' (configured: {})",
+                self.profile.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    /// Profiles with a `match` expression, for the reader to try.
+    pub fn detectors(&self) -> Vec<Detector> {
+        self.profile
+            .iter()
+            .filter_map(|(name, profile)| {
+                let Parsed(signature) = profile.signature.clone()?;
+                Some(Detector {
+                    name: name.clone(),
+                    signature,
+                    fields: self.fields.overlaid(&profile.fields).to_mapping(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Profile {
+    /// Applies the profile's view settings over the current ones. Field
+    /// mappings are handled by the reader (and `App::activate_profile`).
+    pub fn apply(&self, app: &mut App) -> Result<()> {
+        if let Some(columns) = &self.columns {
+            apply_columns(app, columns)?;
+        }
+        if let Some(Parsed(mode)) = self.timestamp_format {
+            app.timestamp_mode = mode;
+        }
+        apply_colors(app, &self.colors);
+        apply_filters(app, &self.filter, &self.include, &self.exclude)
+    }
+}
+
+impl FieldsConfig {
+    fn merge(&mut self, other: FieldsConfig) {
+        if other.timestamp.is_some() {
+            self.timestamp = other.timestamp;
+        }
+        if other.level.is_some() {
+            self.level = other.level;
+        }
+        if other.message.is_some() {
+            self.message = other.message;
+        }
+        self.levels.extend(other.levels);
+    }
+
+    /// These settings with `other`'s on top.
+    pub fn overlaid(&self, other: &FieldsConfig) -> FieldsConfig {
+        let mut merged = self.clone();
+        merged.merge(other.clone());
+        merged
+    }
+
+    pub fn to_mapping(&self) -> FieldMapping {
+        let path = |p: &Option<String>| {
+            p.as_ref()
+                .map(|p| p.split('.').map(str::to_string).collect())
+        };
+        FieldMapping {
+            timestamp: path(&self.timestamp),
+            level: path(&self.level),
+            message: path(&self.message),
+            level_names: self.levels.clone(),
+        }
+    }
+}
+
+impl ColorConfig {
+    fn merge(&mut self, other: ColorConfig) {
+        if other.accent.is_some() {
+            self.accent = other.accent;
+        }
+        self.levels.extend(other.levels);
+    }
+}
+
+fn apply_columns(app: &mut App, columns: &[ColumnConfig]) -> Result<()> {
+    for (pos, col) in columns.iter().enumerate() {
+        if columns[..pos].iter().any(|c| c.name == col.name) {
+            bail!("column '{}' is listed twice", col.name);
+        }
+    }
+    app.set_columns(columns.iter().map(ColumnConfig::to_column).collect());
+    Ok(())
+}
+
+fn apply_colors(app: &mut App, colors: &ColorConfig) {
+    if let Some(Parsed(accent)) = colors.accent {
+        app.theme.accent = accent;
+    }
+    for (level, Parsed(color)) in &colors.levels {
+        app.theme.levels.insert(level.to_ascii_uppercase(), *color);
+    }
+}
+
+fn apply_keys(app: &mut App, keys: &KeysConfig) -> Result<()> {
+    app.key_remaps = keys
+        .remaps
+        .iter()
+        .map(|(Parsed(from), Parsed(to))| (*from, *to))
+        .collect();
+    for (&mode, table) in &keys.modes {
+        for (Parsed(chord), Bindings(actions)) in table {
+            app.keymap
+                .bind(mode, *chord, actions)
+                .map_err(|err| anyhow!("key '{chord}': {err}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_filters(
+    app: &mut App,
+    filter: &Option<String>,
+    include: &Option<Vec<String>>,
+    exclude: &Option<Vec<String>>,
+) -> Result<()> {
+    if let Some(filter) = filter {
+        app.set_default_filter(filter)
+            .map_err(|err| anyhow!("filter '{filter}': {err}"))?;
+    }
+    let layers = include
+        .iter()
+        .flatten()
+        .map(|p| (p, FilterKind::Include))
+        .chain(exclude.iter().flatten().map(|p| (p, FilterKind::Exclude)));
+    for (pattern, kind) in layers {
+        app.add_default_layer(pattern, kind)
+            .map_err(|err| anyhow!("filter layer '{pattern}': {err}"))?;
+    }
+    Ok(())
+}
+
+impl ColumnConfig {
+    /// Columns named like a default one start from its settings, so listing
+    /// `timestamp` keeps it pinned.
+    fn to_column(&self) -> ColumnDef {
+        let mut col = match &self.expr {
+            Some(Parsed(expr)) => ColumnDef::computed(self.name.clone(), expr.clone()),
+            None => {
+                let path: Vec<String> = match &self.path {
+                    Some(path) => path.split('.').map(str::to_string).collect(),
+                    None => vec![self.name.clone()],
+                };
+                default_column(&self.name)
+                    .filter(|col| col.path == path)
+                    .unwrap_or_else(|| ColumnDef::new(self.name.clone(), path))
+            }
+        };
+        col.enabled = self.enabled.unwrap_or(true);
+        if let Some(width) = self.width {
+            col.width = width;
+        }
+        if let Some(sticky) = self.sticky {
+            col.sticky = sticky;
+        }
+        col
+    }
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("logtui"))
+}
@@ -20,7 +20,7 @@ pub fn open_entry_in_editor<B: Backend>(
         .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
         .collect();
     let filename = format!("logtui-{}.json", sanitized_ts);
-    let contents = serde_json::to_string_pretty(&entry.raw)?;
+    let contents = serde_json::to_string_pretty(&*entry.raw)?;
     open_text_in_editor(terminal, &filename, &contents)
 }
 
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{self, BufRead, BufReader, IsTerminal},
     path::PathBuf,
@@ -8,9 +9,10 @@ use std::{
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-use crate::{args::Args, model::LogEntry};
+use crate::{args::Args, jq, model::LogEntry};
 
 pub enum InputSource {
     Stdin,
@@ -18,6 +20,23 @@ pub enum InputSource {
     StdinPipe(File),
 }
 
+/// An input as recorded in a session: a file, or whatever was on stdin.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceSpec {
+    Stdin,
+    File(PathBuf),
+}
+
+impl InputSource {
+    pub fn spec(&self) -> SourceSpec {
+        match self {
+            InputSource::File(path) => SourceSpec::File(path.clone()),
+            InputSource::Stdin | InputSource::StdinPipe(_) => SourceSpec::Stdin,
+        }
+    }
+}
+
 pub fn resolve_input_source(args: &Args) -> Result<InputSource> {
     if let Some(path) = args.file.clone() {
         Ok(InputSource::File(path))
@@ -29,94 +48,177 @@ pub fn resolve_input_source(args: &Args) -> Result<InputSource> {
     }
 }
 
-pub fn spawn_reader(input: InputSource, tx: mpsc::Sender<LogEntry>) {
+/// What the reader thread sends to the UI.
+pub enum InputEvent {
+    Entry(LogEntry),
+    /// A profile's signature matched; entries sent before this were parsed
+    /// without its field mapping.
+    Profile {
+        name: String,
+        fields: FieldMapping,
+    },
+}
+
+/// Where `timestamp`, `level` and `message` are read from. Unset fields use
+/// the built-in lookup (top level, then `data.*`).
+#[derive(Clone, Debug, Default)]
+pub struct FieldMapping {
+    pub timestamp: Option<Vec<String>>,
+    pub level: Option<Vec<String>>,
+    pub message: Option<Vec<String>>,
+    /// Raw level values (e.g. pino's `30`) to level names.
+    pub level_names: BTreeMap<String, String>,
+}
+
+/// A profile the reader tries on the first lines of input.
+pub struct Detector {
+    pub name: String,
+    pub signature: jq::Filter,
+    pub fields: FieldMapping,
+}
+
+/// Profiles are only detected among this many leading JSON lines.
+const DETECT_LINES: usize = 20;
+
+/// Starts readers for files added while running (`:source add`), feeding
+/// the same channel as the first one.
+#[derive(Clone)]
+pub struct SourceSpawner {
+    tx: mpsc::Sender<InputEvent>,
+    /// Field mapping for new readers; follows the active profile.
+    pub fields: FieldMapping,
+}
+
+impl SourceSpawner {
+    pub fn new(tx: mpsc::Sender<InputEvent>, fields: FieldMapping) -> Self {
+        Self { tx, fields }
+    }
+
+    pub fn spawn(&self, path: PathBuf) {
+        spawn_reader(
+            InputSource::File(path),
+            self.tx.clone(),
+            self.fields.clone(),
+            Vec::new(),
+        );
+    }
+}
+
+pub fn spawn_reader(
+    input: InputSource,
+    tx: mpsc::Sender<InputEvent>,
+    mut fields: FieldMapping,
+    mut detectors: Vec<Detector>,
+) {
     thread::spawn(move || {
         let reader: Box<dyn BufRead + Send> = match input {
             InputSource::Stdin => Box::new(BufReader::new(io::stdin())),
             InputSource::File(path) => match File::open(&path) {
                 Ok(file) => Box::new(BufReader::new(file)),
                 Err(err) => {
-                    let _ = tx.send(LogEntry {
-                        timestamp: "-".into(),
-                        level: "PARSE".into(),
-                        message: format!("Failed to open file {path:?}: {err}"),
-                        raw: json!({"error": err.to_string(), "path": path}),
-                    });
+                    let _ = tx.send(InputEvent::Entry(LogEntry::new(
+                        "-".into(),
+                        "PARSE".into(),
+                        format!("Failed to open file {path:?}: {err}"),
+                        json!({"error": err.to_string(), "path": path}),
+                    )));
                     return;
                 }
             },
             InputSource::StdinPipe(file) => Box::new(BufReader::new(file)),
         };
 
+        let mut json_lines = 0;
         for line in reader.lines() {
-            match line {
-                Ok(line) => match parse_log_line(&line) {
-                    Ok(entry) => {
-                        if tx.send(entry).is_err() {
-                            break;
+            let entry = match line {
+                Ok(line) => match serde_json::from_str::<Value>(&line) {
+                    Ok(value) => {
+                        json_lines += 1;
+                        if json_lines > DETECT_LINES {
+                            detectors.clear();
                         }
+                        if let Some(pos) =
+                            detectors.iter().position(|d| d.signature.matches(&value))
+                        {
+                            let detector = detectors.swap_remove(pos);
+                            detectors.clear();
+                            fields = detector.fields.clone();
+                            let event = InputEvent::Profile {
+                                name: detector.name,
+                                fields: detector.fields,
+                            };
+                            if tx.send(event).is_err() {
+                                break;
+                            }
+                        }
+                        entry_from_value(value, &fields).with_line(line)
                     }
-                    Err(err) => {
-                        let _ = tx.send(LogEntry {
-                            timestamp: "-".into(),
-                            level: "PARSE".into(),
-                            message: format!("Failed to parse line: {err}"),
-                            raw: json!({ "error": err.to_string(), "line": line }),
-                        });
-                    }
+                    Err(_) => LogEntry::new(
+                        "-".into(),
+                        "TEXT".into(),
+                        line.to_string(),
+                        Value::String(line),
+                    ),
                 },
-                Err(err) => {
-                    let _ = tx.send(LogEntry {
-                        timestamp: "-".into(),
-                        level: "PARSE".into(),
-                        message: format!("Failed to read line: {err}"),
-                        raw: json!({ "error": err.to_string() }),
-                    });
-                }
+                Err(err) => LogEntry::new(
+                    "-".into(),
+                    "PARSE".into(),
+                    format!("Failed to read line: {err}"),
+                    json!({ "error": err.to_string() }),
+                ),
+            };
+            if tx.send(InputEvent::Entry(entry)).is_err() {
+                break;
             }
         }
     });
 }
 
-fn parse_log_line(line: &str) -> Result<LogEntry> {
-    match serde_json::from_str::<Value>(line) {
-        Ok(value) => {
-            let timestamp = {
-                let ts = extract_timestamp(&value);
-                if ts == "-" {
-                    if let Some(data) = value.get("data") {
-                        extract_timestamp(data)
-                    } else {
-                        ts
-                    }
+/// Builds an entry from a parsed JSON line.
+pub fn entry_from_value(value: Value, fields: &FieldMapping) -> LogEntry {
+    let timestamp = match &fields.timestamp {
+        Some(path) => lookup_text(&value, path).unwrap_or_else(|| "-".into()),
+        None => {
+            let ts = extract_timestamp(&value);
+            if ts == "-" {
+                if let Some(data) = value.get("data") {
+                    extract_timestamp(data)
                 } else {
                     ts
                 }
-            };
-
-            let level = find_str(&value, "level")
-                .or_else(|| value.get("data").and_then(|d| find_str(d, "level")))
-                .unwrap_or("UNKNOWN")
-                .to_string();
-
-            let message = find_str(&value, "message")
-                .or_else(|| value.get("data").and_then(|d| find_str(d, "message")))
-                .unwrap_or("")
-                .to_string();
-
-            Ok(LogEntry {
-                timestamp,
-                level,
-                message,
-                raw: value,
-            })
+            } else {
+                ts
+            }
         }
-        Err(_) => Ok(LogEntry {
-            timestamp: "-".into(),
-            level: "TEXT".into(),
-            message: line.to_string(),
-            raw: Value::String(line.to_string()),
-        }),
+    };
+
+    let level = match &fields.level {
+        Some(path) => lookup_text(&value, path),
+        None => find_str(&value, "level")
+            .or_else(|| value.get("data").and_then(|d| find_str(d, "level")))
+            .map(str::to_string),
+    }
+    .unwrap_or_else(|| "UNKNOWN".into());
+    let level = fields.level_names.get(&level).cloned().unwrap_or(level);
+
+    let message = match &fields.message {
+        Some(path) => lookup_text(&value, path),
+        None => find_str(&value, "message")
+            .or_else(|| value.get("data").and_then(|d| find_str(d, "message")))
+            .map(str::to_string),
+    }
+    .unwrap_or_default();
+
+    LogEntry::new(timestamp, level, message, value)
+}
+
+/// The value at `path` as text; strings unquoted, other scalars as JSON.
+fn lookup_text(value: &Value, path: &[String]) -> Option<String> {
+    let found = path.iter().try_fold(value, |v, key| v.get(key))?;
+    match found {
+        Value::String(s) => Some(s.clone()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+        other => Some(other.to_string()),
     }
 }
 
@@ -0,0 +1,1136 @@
+//! A small jq-compatible expression language used for JSON filters and
+//! computed columns. It covers paths (`.a.b[0]`, `.[]`, `..`), pipes, commas,
+//! comparisons, arithmetic, `and`/`or`/`//`, `if … then … else … end`, array
+//! construction and the common builtins (`select`, `length`, `map`, `test`, …).
+//! Variables, object construction, assignment and user-defined functions are
+//! not supported.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use regex::Regex;
+use serde_json::{Number, Value};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JqError(String);
+
+impl fmt::Display for JqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for JqError {}
+
+fn err<T>(msg: impl Into<String>) -> Result<T, JqError> {
+    Err(JqError(msg.into()))
+}
+
+/// A parsed expression, ready to run against many values.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl FromStr for Filter {
+    type Err = JqError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, JqError> {
+        let tokens = lex(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.pipe()?;
+        if let Some(tok) = parser.peek() {
+            return err(format!("jq: unexpected {tok} at end of expression"));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>, JqError> {
+        eval(&self.expr, input)
+    }
+
+    /// Filter semantics: true if any output is truthy. Runtime errors count as
+    /// no match, like `select(…)?`.
+    pub fn matches(&self, input: &Value) -> bool {
+        self.run(input)
+            .map(|out| out.iter().any(truthy))
+            .unwrap_or(false)
+    }
+
+    /// Column semantics: outputs joined with `, `, strings unquoted.
+    pub fn render(&self, input: &Value) -> Option<String> {
+        let out = self.run(input).ok()?;
+        if out.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = out
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+        Some(parts.join(", "))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Lexer
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Pipe,
+    Comma,
+    Semicolon,
+    Question,
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Dot => f.write_str("'.'"),
+            Token::DotDot => f.write_str("'..'"),
+            Token::Field(name) => write!(f, "'.{name}'"),
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Str(s) => write!(f, "{s:?}"),
+            Token::Num(n) => write!(f, "{n}"),
+            Token::LBracket => f.write_str("'['"),
+            Token::RBracket => f.write_str("']'"),
+            Token::LParen => f.write_str("'('"),
+            Token::RParen => f.write_str("')'"),
+            Token::Pipe => f.write_str("'|'"),
+            Token::Comma => f.write_str("','"),
+            Token::Semicolon => f.write_str("';'"),
+            Token::Question => f.write_str("'?'"),
+            Token::Op(op) => write!(f, "'{op}'"),
+        }
+    }
+}
+
+fn lex(src: &str) -> Result<Vec<Token>, JqError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let ident_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '.' => {
+                if chars.get(i + 1) == Some(&'.') {
+                    tokens.push(Token::DotDot);
+                    i += 2;
+                } else if chars.get(i + 1).is_some_and(|&n| ident_start(n)) {
+                    let start = i + 1;
+                    i += 1;
+                    while i < chars.len() && ident_char(chars[i]) {
+                        i += 1;
+                    }
+                    tokens.push(Token::Field(chars[start..i].iter().collect()));
+                } else {
+                    tokens.push(Token::Dot);
+                    i += 1;
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return err("jq: unterminated string"),
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if chars.get(i + 1) == Some(&'u') => {
+                            let hex: String = chars.iter().skip(i + 2).take(4).collect();
+                            let ch = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| JqError(format!("jq: invalid escape \\u{hex}")))?;
+                            s.push(ch);
+                            i += 6;
+                        }
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('r') => '\r',
+                                Some(&other) => other,
+                                None => return err("jq: unterminated string"),
+                            };
+                            s.push(escaped);
+                            i += 2;
+                        }
+                        Some(&ch) => {
+                            s.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                if matches!(chars.get(i), Some('e' | 'E')) {
+                    i += 1;
+                    if matches!(chars.get(i), Some('+' | '-')) {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                let n = text
+                    .parse::<f64>()
+                    .map_err(|_| JqError(format!("jq: invalid number {text}")))?;
+                tokens.push(Token::Num(n));
+            }
+            c if ident_start(c) => {
+                let start = i;
+                while i < chars.len() && ident_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            ';' => {
+                tokens.push(Token::Semicolon);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::Question);
+                i += 1;
+            }
+            _ => {
+                let next = chars.get(i + 1).copied();
+                let (op, len) = match (c, next) {
+                    ('=', Some('=')) => ("==", 2),
+                    ('!', Some('=')) => ("!=", 2),
+                    ('<', Some('=')) => ("<=", 2),
+                    ('>', Some('=')) => (">=", 2),
+                    ('/', Some('/')) => ("//", 2),
+                    ('<', _) => ("<", 1),
+                    ('>', _) => (">", 1),
+                    ('+', _) => ("+", 1),
+                    ('-', _) => ("-", 1),
+                    ('*', _) => ("*", 1),
+                    ('/', _) => ("/", 1),
+                    ('%', _) => ("%", 1),
+                    ('|', _) => {
+                        tokens.push(Token::Pipe);
+                        i += 1;
+                        continue;
+                    }
+                    _ => return err(format!("jq: unexpected character '{c}'")),
+                };
+                tokens.push(Token::Op(op));
+                i += len;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Identity,
+    Recurse,
+    Literal(Value),
+    Index(Box<Expr>, Box<Expr>),
+    Iterate(Box<Expr>),
+    Try(Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Alt(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    Test(Box<Regex>),
+    Call(&'static str, Vec<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+/// Builtins and their arities; `test` is handled separately so its pattern is
+/// compiled once at parse time.
+const BUILTINS: &[(&str, usize)] = &[
+    ("select", 1),
+    ("length", 0),
+    ("keys", 0),
+    ("has", 1),
+    ("contains", 1),
+    ("startswith", 1),
+    ("endswith", 1),
+    ("ltrimstr", 1),
+    ("rtrimstr", 1),
+    ("ascii_downcase", 0),
+    ("ascii_upcase", 0),
+    ("tostring", 0),
+    ("tonumber", 0),
+    ("tojson", 0),
+    ("type", 0),
+    ("map", 1),
+    ("any", 0),
+    ("all", 0),
+    ("first", 0),
+    ("last", 0),
+    ("not", 0),
+    ("empty", 0),
+    ("split", 1),
+    ("join", 1),
+    ("add", 0),
+    ("min", 0),
+    ("max", 0),
+    ("floor", 0),
+    ("values", 0),
+];
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eat(&mut self, tok: &Token) -> bool {
+        if self.peek() == Some(tok) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn eat_keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, tok: Token) -> Result<(), JqError> {
+        match self.next() {
+            Some(t) if t == tok => Ok(()),
+            Some(t) => err(format!("jq: expected {tok}, found {t}")),
+            None => err(format!("jq: expected {tok}, found end of expression")),
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, JqError> {
+        let lhs = self.comma()?;
+        if self.eat(&Token::Pipe) {
+            let rhs = self.pipe()?;
+            return Ok(Expr::Pipe(Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn comma(&mut self) -> Result<Expr, JqError> {
+        let mut lhs = self.alternative()?;
+        while self.eat(&Token::Comma) {
+            let rhs = self.alternative()?;
+            lhs = Expr::Comma(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn alternative(&mut self) -> Result<Expr, JqError> {
+        let lhs = self.or()?;
+        if self.eat_op(&["//"]).is_some() {
+            let rhs = self.alternative()?;
+            return Ok(Expr::Alt(Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, JqError> {
+        let mut lhs = self.and()?;
+        while self.eat_keyword("or") {
+            let rhs = self.and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, JqError> {
+        let mut lhs = self.comparison()?;
+        while self.eat_keyword("and") {
+            let rhs = self.comparison()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, JqError> {
+        let lhs = self.additive()?;
+        if let Some(op) = self.eat_op(&["==", "!=", "<", "<=", ">", ">="]) {
+            let rhs = self.additive()?;
+            return Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn additive(&mut self) -> Result<Expr, JqError> {
+        let mut lhs = self.multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let rhs = self.multiplicative()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, JqError> {
+        let mut lhs = self.postfix()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            let rhs = self.postfix()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Field(name)) => {
+                    let key = Expr::Literal(Value::String(name.clone()));
+                    self.pos += 1;
+                    expr = Expr::Index(Box::new(expr), Box::new(key));
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) =>
+                {
+                    self.pos += 1;
+                    let Some(Token::Str(key)) = self.next() else {
+                        unreachable!("checked above");
+                    };
+                    expr = Expr::Index(Box::new(expr), Box::new(Expr::Literal(Value::String(key))));
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    if self.eat(&Token::RBracket) {
+                        expr = Expr::Iterate(Box::new(expr));
+                    } else {
+                        let key = self.pipe()?;
+                        self.expect(Token::RBracket)?;
+                        expr = Expr::Index(Box::new(expr), Box::new(key));
+                    }
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::LBracket)) =>
+                {
+                    // `.a.[0]` is accepted by jq 1.7 as well as `.a[0]`.
+                    self.pos += 1;
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    expr = Expr::Try(Box::new(expr));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, JqError> {
+        let Some(tok) = self.next() else {
+            return err("jq: unexpected end of expression");
+        };
+        match tok {
+            Token::Dot => match self.peek() {
+                Some(Token::Str(_)) => {
+                    let Some(Token::Str(key)) = self.next() else {
+                        unreachable!("checked above");
+                    };
+                    Ok(Expr::Index(
+                        Box::new(Expr::Identity),
+                        Box::new(Expr::Literal(Value::String(key))),
+                    ))
+                }
+                _ => Ok(Expr::Identity),
+            },
+            Token::DotDot => Ok(Expr::Recurse),
+            Token::Field(name) => Ok(Expr::Index(
+                Box::new(Expr::Identity),
+                Box::new(Expr::Literal(Value::String(name))),
+            )),
+            Token::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Token::Num(n) => Ok(Expr::Literal(number(n))),
+            Token::Op("-") => Ok(Expr::Neg(Box::new(self.postfix()?))),
+            Token::LParen => {
+                let inner = self.pipe()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Token::LBracket => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+                let inner = self.pipe()?;
+                self.expect(Token::RBracket)?;
+                Ok(Expr::Array(Some(Box::new(inner))))
+            }
+            Token::Ident(word) => self.ident(word),
+            other => err(format!("jq: unexpected {other}")),
+        }
+    }
+
+    fn ident(&mut self, word: String) -> Result<Expr, JqError> {
+        match word.as_str() {
+            "true" => return Ok(Expr::Literal(Value::Bool(true))),
+            "false" => return Ok(Expr::Literal(Value::Bool(false))),
+            "null" => return Ok(Expr::Literal(Value::Null)),
+            "if" => return self.if_expr(),
+            _ => {}
+        }
+        let mut args = Vec::new();
+        if self.eat(&Token::LParen) {
+            loop {
+                args.push(self.pipe()?);
+                if self.eat(&Token::Semicolon) {
+                    continue;
+                }
+                self.expect(Token::RParen)?;
+                break;
+            }
+        }
+        if word == "test" {
+            return match args.as_slice() {
+                [Expr::Literal(Value::String(pattern))] => Regex::new(pattern)
+                    .map(|re| Expr::Test(Box::new(re)))
+                    .map_err(|e| JqError(format!("jq: invalid regex in test(): {e}"))),
+                [_] => err("jq: test() needs a literal string pattern"),
+                _ => err(format!("jq: test/{} is not defined", args.len())),
+            };
+        }
+        match BUILTINS.iter().find(|(name, _)| *name == word) {
+            Some((name, arity)) if *arity == args.len() => Ok(Expr::Call(name, args)),
+            _ => err(format!("jq: {word}/{} is not defined", args.len())),
+        }
+    }
+
+    fn if_expr(&mut self) -> Result<Expr, JqError> {
+        let cond = self.pipe()?;
+        if !self.eat_keyword("then") {
+            return err("jq: expected 'then'");
+        }
+        let then = self.pipe()?;
+        let otherwise = if self.eat_keyword("elif") {
+            Some(Box::new(self.if_expr()?))
+        } else if self.eat_keyword("else") {
+            let e = self.pipe()?;
+            if !self.eat_keyword("end") {
+                return err("jq: expected 'end'");
+            }
+            Some(Box::new(e))
+        } else if self.eat_keyword("end") {
+            None
+        } else {
+            return err("jq: expected 'else', 'elif' or 'end'");
+        };
+        Ok(Expr::If(Box::new(cond), Box::new(then), otherwise))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9e15 {
+        Value::Number(Number::from(n as i64))
+    } else {
+        Number::from_f64(n)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+fn truthy(v: &Value) -> bool {
+    !matches!(v, Value::Null | Value::Bool(false))
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// jq's total order: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => {
+            for (l, r) in x.iter().zip(y) {
+                let ord = compare(l, r);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            x.len().cmp(&y.len())
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            let mut xk: Vec<&String> = x.keys().collect();
+            let mut yk: Vec<&String> = y.keys().collect();
+            xk.sort();
+            yk.sort();
+            xk.cmp(&yk).then_with(|| {
+                xk.iter()
+                    .map(|k| compare(&x[*k], &y[*k]))
+                    .find(|o| *o != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn as_f64(v: &Value, op: &str) -> Result<f64, JqError> {
+    v.as_f64()
+        .ok_or_else(|| JqError(format!("jq: {} cannot be used with '{op}'", type_name(v))))
+}
+
+fn binary(op: &str, l: &Value, r: &Value) -> Result<Value, JqError> {
+    let out = match op {
+        "==" => Value::Bool(compare(l, r) == Ordering::Equal),
+        "!=" => Value::Bool(compare(l, r) != Ordering::Equal),
+        "<" => Value::Bool(compare(l, r) == Ordering::Less),
+        "<=" => Value::Bool(compare(l, r) != Ordering::Greater),
+        ">" => Value::Bool(compare(l, r) == Ordering::Greater),
+        ">=" => Value::Bool(compare(l, r) != Ordering::Less),
+        "+" => match (l, r) {
+            (Value::Null, other) | (other, Value::Null) => other.clone(),
+            (Value::String(a), Value::String(b)) => Value::String(format!("{a}{b}")),
+            (Value::Array(a), Value::Array(b)) => {
+                Value::Array(a.iter().chain(b).cloned().collect())
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let mut merged = a.clone();
+                merged.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+                Value::Object(merged)
+            }
+            _ => number(as_f64(l, op)? + as_f64(r, op)?),
+        },
+        "-" => match (l, r) {
+            (Value::Array(a), Value::Array(b)) => {
+                Value::Array(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+            }
+            _ => number(as_f64(l, op)? - as_f64(r, op)?),
+        },
+        "*" => number(as_f64(l, op)? * as_f64(r, op)?),
+        "/" => {
+            let d = as_f64(r, op)?;
+            if d == 0.0 {
+                return err("jq: division by zero");
+            }
+            number(as_f64(l, op)? / d)
+        }
+        "%" => {
+            let d = as_f64(r, op)? as i64;
+            if d == 0 {
+                return err("jq: modulo by zero");
+            }
+            match (as_f64(l, op)? as i64).checked_rem(d) {
+                Some(rem) => number(rem as f64),
+                None => return err("jq: modulo overflow"),
+            }
+        }
+        _ => return err(format!("jq: unknown operator {op}")),
+    };
+    Ok(out)
+}
+
+fn index(target: &Value, key: &Value) -> Result<Value, JqError> {
+    match (target, key) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(map), Value::String(k)) => Ok(map.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(arr), Value::Number(n)) => {
+            let i = n.as_f64().unwrap_or(0.0) as i64;
+            let i = if i < 0 { arr.len() as i64 + i } else { i };
+            Ok(usize::try_from(i)
+                .ok()
+                .and_then(|i| arr.get(i))
+                .cloned()
+                .unwrap_or(Value::Null))
+        }
+        _ => err(format!(
+            "jq: cannot index {} with {}",
+            type_name(target),
+            type_name(key)
+        )),
+    }
+}
+
+fn iterate(v: &Value) -> Result<Vec<Value>, JqError> {
+    match v {
+        Value::Array(arr) => Ok(arr.clone()),
+        Value::Object(map) => Ok(map.values().cloned().collect()),
+        other => err(format!("jq: cannot iterate over {}", type_name(other))),
+    }
+}
+
+fn recurse(v: &Value, out: &mut Vec<Value>) {
+    out.push(v.clone());
+    match v {
+        Value::Array(arr) => arr.iter().for_each(|c| recurse(c, out)),
+        Value::Object(map) => map.values().for_each(|c| recurse(c, out)),
+        _ => {}
+    }
+}
+
+fn contains(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => x.contains(y.as_str()),
+        (Value::Array(x), Value::Array(y)) => {
+            y.iter().all(|yv| x.iter().any(|xv| contains(xv, yv)))
+        }
+        (Value::Object(x), Value::Object(y)) => y
+            .iter()
+            .all(|(k, yv)| x.get(k).is_some_and(|xv| contains(xv, yv))),
+        _ => a == b,
+    }
+}
+
+fn eval(expr: &Expr, input: &Value) -> Result<Vec<Value>, JqError> {
+    match expr {
+        Expr::Identity => Ok(vec![input.clone()]),
+        Expr::Recurse => {
+            let mut out = Vec::new();
+            recurse(input, &mut out);
+            Ok(out)
+        }
+        Expr::Literal(v) => Ok(vec![v.clone()]),
+        Expr::Index(target, key) => {
+            let keys = eval(key, input)?;
+            let mut out = Vec::new();
+            for t in eval(target, input)? {
+                for k in &keys {
+                    out.push(index(&t, k)?);
+                }
+            }
+            Ok(out)
+        }
+        Expr::Iterate(target) => {
+            let mut out = Vec::new();
+            for t in eval(target, input)? {
+                out.extend(iterate(&t)?);
+            }
+            Ok(out)
+        }
+        Expr::Try(inner) => Ok(eval(inner, input).unwrap_or_default()),
+        Expr::Array(inner) => {
+            let items = match inner {
+                Some(inner) => eval(inner, input)?,
+                None => Vec::new(),
+            };
+            Ok(vec![Value::Array(items)])
+        }
+        Expr::Pipe(lhs, rhs) => {
+            let mut out = Vec::new();
+            for v in eval(lhs, input)? {
+                out.extend(eval(rhs, &v)?);
+            }
+            Ok(out)
+        }
+        Expr::Comma(lhs, rhs) => {
+            let mut out = eval(lhs, input)?;
+            out.extend(eval(rhs, input)?);
+            Ok(out)
+        }
+        Expr::Alt(lhs, rhs) => {
+            let kept: Vec<Value> = eval(lhs, input)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(truthy)
+                .collect();
+            if kept.is_empty() {
+                eval(rhs, input)
+            } else {
+                Ok(kept)
+            }
+        }
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            let is_and = matches!(expr, Expr::And(..));
+            let mut out = Vec::new();
+            for l in eval(lhs, input)? {
+                if truthy(&l) != is_and {
+                    out.push(Value::Bool(!is_and));
+                    continue;
+                }
+                for r in eval(rhs, input)? {
+                    out.push(Value::Bool(truthy(&r)));
+                }
+            }
+            Ok(out)
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let rights = eval(rhs, input)?;
+            let mut out = Vec::new();
+            for r in &rights {
+                for l in eval(lhs, input)? {
+                    out.push(binary(op, &l, r)?);
+                }
+            }
+            Ok(out)
+        }
+        Expr::Neg(inner) => eval(inner, input)?
+            .iter()
+            .map(|v| Ok(number(-as_f64(v, "-")?)))
+            .collect(),
+        Expr::If(cond, then, otherwise) => {
+            let mut out = Vec::new();
+            for c in eval(cond, input)? {
+                if truthy(&c) {
+                    out.extend(eval(then, input)?);
+                } else if let Some(otherwise) = otherwise {
+                    out.extend(eval(otherwise, input)?);
+                } else {
+                    out.push(input.clone());
+                }
+            }
+            Ok(out)
+        }
+        Expr::Test(re) => match input {
+            Value::String(s) => Ok(vec![Value::Bool(re.is_match(s))]),
+            other => err(format!("jq: {} cannot be matched", type_name(other))),
+        },
+        Expr::Call(name, args) => call(name, args, input),
+    }
+}
+
+fn string_arg(args: &[Expr], input: &Value, name: &str) -> Result<Vec<String>, JqError> {
+    eval(&args[0], input)?
+        .into_iter()
+        .map(|v| match v {
+            Value::String(s) => Ok(s),
+            other => err(format!(
+                "jq: {name}() needs a string argument, got {}",
+                type_name(&other)
+            )),
+        })
+        .collect()
+}
+
+fn call(name: &str, args: &[Expr], input: &Value) -> Result<Vec<Value>, JqError> {
+    let one = |v: Value| Ok(vec![v]);
+    match name {
+        "select" => {
+            let keep = eval(&args[0], input)?.iter().any(truthy);
+            Ok(if keep {
+                vec![input.clone()]
+            } else {
+                Vec::new()
+            })
+        }
+        "empty" => Ok(Vec::new()),
+        "not" => one(Value::Bool(!truthy(input))),
+        "length" => one(match input {
+            Value::Null => number(0.0),
+            Value::Bool(_) => return err("jq: boolean has no length"),
+            Value::Number(n) => number(n.as_f64().unwrap_or(0.0).abs()),
+            Value::String(s) => number(s.chars().count() as f64),
+            Value::Array(a) => number(a.len() as f64),
+            Value::Object(m) => number(m.len() as f64),
+        }),
+        "keys" => one(match input {
+            Value::Object(m) => {
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort();
+                Value::Array(keys.into_iter().map(|k| Value::String(k.clone())).collect())
+            }
+            Value::Array(a) => Value::Array((0..a.len()).map(|i| number(i as f64)).collect()),
+            other => return err(format!("jq: {} has no keys", type_name(other))),
+        }),
+        "has" => eval(&args[0], input)?
+            .iter()
+            .map(|k| {
+                Ok(Value::Bool(match (input, k) {
+                    (Value::Object(m), Value::String(k)) => m.contains_key(k),
+                    (Value::Array(a), Value::Number(n)) => n
+                        .as_f64()
+                        .is_some_and(|i| i >= 0.0 && (i as usize) < a.len()),
+                    _ => {
+                        return err(format!(
+                            "jq: cannot check whether {} has a {} key",
+                            type_name(input),
+                            type_name(k)
+                        ));
+                    }
+                }))
+            })
+            .collect(),
+        "contains" => eval(&args[0], input)?
+            .iter()
+            .map(|b| Ok(Value::Bool(contains(input, b))))
+            .collect(),
+        "startswith" | "endswith" | "ltrimstr" | "rtrimstr" | "split" => {
+            let Value::String(s) = input else {
+                return match name {
+                    "ltrimstr" | "rtrimstr" => one(input.clone()),
+                    _ => err(format!("jq: {name}() requires string input")),
+                };
+            };
+            string_arg(args, input, name)?
+                .into_iter()
+                .map(|arg| {
+                    Ok(match name {
+                        "startswith" => Value::Bool(s.starts_with(&arg)),
+                        "endswith" => Value::Bool(s.ends_with(&arg)),
+                        "ltrimstr" => Value::String(s.strip_prefix(&arg).unwrap_or(s).to_string()),
+                        "rtrimstr" => Value::String(s.strip_suffix(&arg).unwrap_or(s).to_string()),
+                        _ => Value::Array(
+                            s.split(arg.as_str())
+                                .map(|p| Value::String(p.to_string()))
+                                .collect(),
+                        ),
+                    })
+                })
+                .collect()
+        }
+        "join" => {
+            let items = iterate(input)?;
+            string_arg(args, input, name)?
+                .into_iter()
+                .map(|sep| {
+                    let parts: Vec<String> = items
+                        .iter()
+                        .map(|v| match v {
+                            Value::Null => String::new(),
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        })
+                        .collect();
+                    Ok(Value::String(parts.join(&sep)))
+                })
+                .collect()
+        }
+        "ascii_downcase" | "ascii_upcase" => match input {
+            Value::String(s) => one(Value::String(if name == "ascii_downcase" {
+                s.to_ascii_lowercase()
+            } else {
+                s.to_ascii_uppercase()
+            })),
+            other => err(format!(
+                "jq: {name}() requires string input, got {}",
+                type_name(other)
+            )),
+        },
+        "tostring" => one(match input {
+            Value::String(_) => input.clone(),
+            other => Value::String(other.to_string()),
+        }),
+        "tojson" => one(Value::String(input.to_string())),
+        "tonumber" => match input {
+            Value::Number(_) => one(input.clone()),
+            Value::String(s) => s
+                .trim()
+                .parse::<f64>()
+                .map(|n| vec![number(n)])
+                .map_err(|_| JqError(format!("jq: cannot parse {s:?} as a number"))),
+            other => err(format!(
+                "jq: {} cannot be parsed as a number",
+                type_name(other)
+            )),
+        },
+        "type" => one(Value::String(type_name(input).to_string())),
+        "map" => {
+            let mut out = Vec::new();
+            for v in iterate(input)? {
+                out.extend(eval(&args[0], &v)?);
+            }
+            one(Value::Array(out))
+        }
+        "values" => Ok(if input.is_null() {
+            Vec::new()
+        } else {
+            vec![input.clone()]
+        }),
+        "any" => one(Value::Bool(iterate(input)?.iter().any(truthy))),
+        "all" => one(Value::Bool(iterate(input)?.iter().all(truthy))),
+        "first" => one(iterate(input)?.into_iter().next().unwrap_or(Value::Null)),
+        "last" => one(iterate(input)?.into_iter().last().unwrap_or(Value::Null)),
+        "add" => {
+            let mut acc = Value::Null;
+            for v in iterate(input)? {
+                acc = binary("+", &acc, &v)?;
+            }
+            one(acc)
+        }
+        "min" | "max" => {
+            let items = iterate(input)?;
+            let pick = items.into_iter().reduce(|a, b| {
+                let ord = compare(&a, &b);
+                match (name, ord) {
+                    ("min", Ordering::Greater) | ("max", Ordering::Less) => b,
+                    _ => a,
+                }
+            });
+            one(pick.unwrap_or(Value::Null))
+        }
+        "floor" => one(number(as_f64(input, "floor")?.floor())),
+        _ => err(format!("jq: {name} is not defined")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(src: &str, input: Value) -> Vec<Value> {
+        Filter::parse(src).unwrap().run(&input).unwrap()
+    }
+
+    #[test]
+    fn paths_and_iteration() {
+        let input = json!({"data": {"items": [1, 2, 3], "user": {"id": "u1"}}});
+        assert_eq!(run(".data.items[1]", input.clone()), vec![json!(2)]);
+        assert_eq!(run(".data.items[-1]", input.clone()), vec![json!(3)]);
+        assert_eq!(run(".data.items[]", input.clone()).len(), 3);
+        assert_eq!(run(".data[\"user\"].id", input.clone()), vec![json!("u1")]);
+        assert_eq!(run(".missing.deeper", input), vec![Value::Null]);
+    }
+
+    #[test]
+    fn select_with_comparisons_and_builtins() {
+        let f = Filter::parse("select(.data.items | length > 3)").unwrap();
+        assert!(f.matches(&json!({"data": {"items": [1, 2, 3, 4]}})));
+        assert!(!f.matches(&json!({"data": {"items": [1]}})));
+        assert!(
+            !f.matches(&json!({"data": 5})),
+            "runtime errors are no match"
+        );
+
+        let f = Filter::parse(r#".durationMs >= 250 and (.path | test("^/api"))"#).unwrap();
+        assert!(f.matches(&json!({"durationMs": 300, "path": "/api/x"})));
+        assert!(!f.matches(&json!({"durationMs": 300, "path": "/health"})));
+
+        let f = Filter::parse(r#".tags | any(.[]; . == "x")"#);
+        assert!(f.is_err(), "unsupported arities are parse errors");
+        let f = Filter::parse(r#"[.tags[] | . == "x"] | any"#).unwrap();
+        assert!(f.matches(&json!({"tags": ["a", "x"]})));
+    }
+
+    #[test]
+    fn render_joins_outputs_for_columns() {
+        let f = Filter::parse(".items | map(.price) | add").unwrap();
+        let input = json!({"items": [{"price": 2}, {"price": 3.5}]});
+        assert_eq!(f.render(&input).as_deref(), Some("5.5"));
+
+        let f = Filter::parse(r#".user // "anon", (.tags | join("+"))"#).unwrap();
+        let input = json!({"tags": ["a", "b"]});
+        assert_eq!(f.render(&input).as_deref(), Some("anon, a+b"));
+
+        let f = Filter::parse("if .status >= 500 then \"5xx\" else \"ok\" end").unwrap();
+        assert_eq!(f.render(&json!({"status": 502})).as_deref(), Some("5xx"));
+    }
+
+    #[test]
+    fn modulo_overflow_is_an_error() {
+        assert_eq!(run(".n % 3", json!({"n": -7})), vec![json!(-1)]);
+        let f = Filter::parse(".n % -1").unwrap();
+        assert!(f.run(&json!({"n": -1e19})).is_err());
+        assert!(f.run(&json!({"n": 5})).is_ok());
+    }
+
+    #[test]
+    fn parse_errors_are_reported() {
+        assert!(Filter::parse(".a |").is_err());
+        assert!(Filter::parse("select(.a").is_err());
+        assert!(Filter::parse("nosuchfn").is_err());
+        assert!(Filter::parse(r#"test("(")"#).is_err());
+    }
+}
@@ -1,8 +1,14 @@
 mod app;
 mod args;
+mod clipboard;
+mod config;
 mod editor;
 mod input;
+mod jq;
+mod keys;
 mod model;
+mod theme;
+mod timestamps;
 mod ui;
 
 use std::io;
@@ -16,17 +22,96 @@ use crossterm::{
 use ratatui::{Terminal, backend::CrosstermBackend};
 
 use crate::{
-    app::App,
+    app::{App, FilterHistory, Session},
     args::Args,
-    input::{resolve_input_source, spawn_reader},
+    config::{Config, Parsed},
+    input::{InputSource, SourceSpawner, SourceSpec, resolve_input_source, spawn_reader},
 };
 
+const DEFAULT_MAX_ENTRIES: usize = 5000;
+
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // Config and session errors are reported before the terminal is taken over.
+    let config = Config::load(args.config.as_deref())?;
+    let session = match &args.session {
+        Some(path) if path.exists() => Some(Session::load(path)?),
+        _ => None,
+    };
+    // A restored session re-reads its source and profile unless the command
+    // line names others.
+    if let Some(session) = &session {
+        if args.file.is_none()
+            && let Some(SourceSpec::File(path)) = session.sources.first()
+        {
+            args.file = Some(path.clone());
+        }
+        if args.profile.is_none() {
+            args.profile = session
+                .profile
+                .clone()
+                .filter(|name| config.profile.contains_key(name));
+        }
+    }
+    let max_entries = args
+        .max_entries
+        .or(config.max_entries)
+        .unwrap_or(DEFAULT_MAX_ENTRIES);
+    let mut app = App::new(max_entries);
+    app.time_zone = args
+        .tz
+        .or(config.timezone.as_ref().map(|Parsed(tz)| *tz))
+        .unwrap_or_default();
+    config.apply(&mut app)?;
+    // An explicit profile applies right away; otherwise the reader picks one
+    // whose signature matches the first lines.
+    let (fields, detectors) = match &args.profile {
+        Some(name) => {
+            let profile = config.find_profile(name)?;
+            profile
+                .apply(&mut app)
+                .with_context(|| format!("applying profile {name}"))?;
+            app.active_profile = Some(name.clone());
+            (config.fields.overlaid(&profile.fields), Vec::new())
+        }
+        None => (config.fields.clone(), config.detectors()),
+    };
+    // Files added with `:source add` come back with a session that also
+    // provides the first source.
+    let extra_sources: Vec<SourceSpec> = match &session {
+        Some(session)
+            if args.file.as_ref().is_some_and(|file| {
+                session.sources.first() == Some(&SourceSpec::File(file.clone()))
+            }) =>
+        {
+            session.sources[1..].to_vec()
+        }
+        _ => Vec::new(),
+    };
     let input_source = resolve_input_source(&args)?;
+    app.sources = vec![input_source.spec()];
+    app.session_path = args.session.clone();
+    if let (Some(session), Some(path)) = (&session, &args.session) {
+        let problems = session.restore(&mut app);
+        if !problems.is_empty() {
+            app.notify_error(format!("Session partly restored: {}", problems.join("; ")));
+        } else if matches!(input_source, InputSource::Stdin) {
+            app.notify_error("Session restored; its logs came from stdin, pipe them in again");
+        } else {
+            app.notify(format!("Restored session {}", path.display()));
+        }
+    }
 
     let (tx, rx) = std::sync::mpsc::channel();
-    spawn_reader(input_source, tx);
+    app.spawner = Some(SourceSpawner::new(tx.clone(), fields.to_mapping()));
+    spawn_reader(input_source, tx, fields.to_mapping(), detectors);
+    for source in extra_sources {
+        if let SourceSpec::File(path) = source
+            && let Err(err) = app.add_source(path)
+        {
+            app.notify_error(err);
+        }
+    }
 
     enable_raw_mode().context("enabling raw mode")?;
     let mut stdout = io::stdout();
@@ -34,7 +119,12 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).context("creating terminal")?;
 
-    let mut app = App::new(args.max_entries);
+    // Without a readable state file, history stays in memory rather than
+    // replacing the file on the next save.
+    match FilterHistory::load_default() {
+        Ok(history) => app.filter_history = history,
+        Err(err) => app.notify_error(format!("Filter history not loaded: {err:#}")),
+    }
     let res = app::run_app(&mut terminal, &mut app, rx);
 
     disable_raw_mode().context("disabling raw mode")?;
@@ -1,9 +1,86 @@
+use std::{borrow::Cow, sync::Arc};
+
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::timestamps::Timestamp;
+
 #[derive(Clone, Debug)]
 pub struct LogEntry {
+    /// The timestamp exactly as it appeared in the log line.
     pub timestamp: String,
+    /// `timestamp` parsed, when it is in a recognised format.
+    pub time: Option<Timestamp>,
     pub level: String,
     pub message: String,
-    pub raw: Value,
+    /// Shared so background filter jobs can take a snapshot of it cheaply.
+    pub raw: Arc<Value>,
+    /// The JSON line `raw` was parsed from, byte for byte, so exports can
+    /// write it back unchanged. Text lines are `raw` itself.
+    pub line: Option<Arc<str>>,
+    /// Text the regex filters run against, built once so rebuilds and
+    /// background filter jobs don't re-serialize `raw`.
+    pub search_text: Arc<str>,
+    /// Hash of the serialized `raw` for [`LogEntry::key`], taken while
+    /// `search_text` is built so restoring a session doesn't re-serialize
+    /// the buffer.
+    raw_hash: u64,
+}
+
+impl LogEntry {
+    pub fn new(timestamp: String, level: String, message: String, raw: Value) -> Self {
+        let raw_text = raw.to_string();
+        let raw_hash = fnv1a(raw_text.as_bytes());
+        let search_text = format!("{timestamp} {level} {message} {raw_text}").into();
+        let time = Timestamp::parse(&timestamp);
+        Self {
+            timestamp,
+            time,
+            level,
+            message,
+            raw: Arc::new(raw),
+            line: None,
+            search_text,
+            raw_hash,
+        }
+    }
+
+    pub fn with_line(mut self, line: String) -> Self {
+        self.line = Some(line.into());
+        self
+    }
+
+    /// The entry as it was read: the original line when there is one.
+    pub fn original_line(&self) -> Cow<'_, str> {
+        match (&self.line, &*self.raw) {
+            (Some(line), _) => Cow::Borrowed(line),
+            (None, Value::String(text)) => Cow::Borrowed(text),
+            (None, raw) => Cow::Owned(raw.to_string()),
+        }
+    }
+}
+
+/// Identifies an entry across runs and buffer eviction: its timestamp plus a
+/// hash of the raw line, so identical lines at different times stay distinct.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EntryKey {
+    pub timestamp: String,
+    pub hash: u64,
+}
+
+impl LogEntry {
+    pub fn key(&self) -> EntryKey {
+        EntryKey {
+            timestamp: self.timestamp.clone(),
+            hash: self.raw_hash,
+        }
+    }
+}
+
+/// FNV-1a: stable across builds and platforms, unlike `DefaultHasher`, so
+/// keys in saved sessions stay valid.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use ratatui::style::{Color, Style};
+
+/// Colors that can be changed from the config file.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Border of the focused pane.
+    pub accent: Color,
+    /// Per-level overrides, keyed by upper-case level name.
+    pub levels: BTreeMap<String, Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            accent: Color::Cyan,
+            levels: BTreeMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn level_style(&self, level: &str) -> Style {
+        let level = level.to_ascii_uppercase();
+        if let Some(&color) = self.levels.get(&level) {
+            return Style::default().fg(color);
+        }
+        match level.as_str() {
+            "TRACE" => Style::default().fg(Color::LightGreen),
+            "DEBUG" => Style::default().fg(Color::LightMagenta),
+            "INFO" => Style::default().fg(Color::LightBlue),
+            "WARN" | "WARNING" => Style::default().fg(Color::Yellow),
+            "ERROR" => Style::default().fg(Color::Red),
+            "CRITICAL" => Style::default().fg(Color::LightRed),
+            "PARSE" => Style::default().fg(Color::Magenta),
+            "TEXT" => Style::default().fg(Color::Gray),
+            _ => Style::default(),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Parsed log timestamps and the display modes of the timestamp column.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{
+    DateTime, FixedOffset, Local, NaiveDateTime, Offset, SecondsFormat, TimeDelta, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// A timestamp as written by the producer. Offset-less values are kept naive
+/// and resolved in the display zone (`--tz`), which is also what the producer
+/// most likely meant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    Zoned(DateTime<FixedOffset>),
+    Naive(NaiveDateTime),
+}
+
+const NAIVE_FORMATS: [&str; 3] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+];
+
+const ZONED_FORMATS: [&str; 3] = [
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+];
+
+impl Timestamp {
+    /// Recognises RFC 3339 / ISO 8601 (with or without offset, `T` or space),
+    /// RFC 2822, log4j-style `,mmm` fractions, and epoch seconds or
+    /// milliseconds.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if raw.is_empty() || raw == "-" {
+            return None;
+        }
+        if let Some(ts) = parse_epoch(raw) {
+            return Some(ts);
+        }
+        if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+            return Some(Timestamp::Zoned(dt));
+        }
+        if let Ok(dt) = DateTime::parse_from_rfc3339(&raw.replacen(' ', "T", 1)) {
+            return Some(Timestamp::Zoned(dt));
+        }
+        for fmt in ZONED_FORMATS {
+            if let Ok(dt) = DateTime::parse_from_str(raw, fmt) {
+                return Some(Timestamp::Zoned(dt));
+            }
+        }
+        // log4j and Python logging write `12:00:00,250`.
+        let dotted = raw.replacen(',', ".", 1);
+        for fmt in NAIVE_FORMATS {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(&dotted, fmt) {
+                return Some(Timestamp::Naive(dt));
+            }
+        }
+        DateTime::parse_from_rfc2822(raw).ok().map(Timestamp::Zoned)
+    }
+
+    /// The instant this timestamp denotes, reading naive values in `zone`.
+    pub fn instant(&self, zone: &TimeZoneSpec) -> Option<DateTime<Utc>> {
+        match self {
+            Timestamp::Zoned(dt) => Some(dt.with_timezone(&Utc)),
+            Timestamp::Naive(naive) => zone.resolve(naive),
+        }
+    }
+}
+
+fn parse_epoch(raw: &str) -> Option<Timestamp> {
+    let (int, frac) = raw.split_once('.').unwrap_or((raw, ""));
+    if int.is_empty()
+        || !int.bytes().all(|b| b.is_ascii_digit())
+        || !frac.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let value: f64 = raw.parse().ok()?;
+    // 10 digits are seconds until 2286; 13 digits are milliseconds.
+    let millis = match int.len() {
+        9 | 10 => value * 1000.0,
+        12 | 13 => value,
+        _ => return None,
+    };
+    let dt = DateTime::from_timestamp_millis(millis.round() as i64)?;
+    Some(Timestamp::Zoned(dt.fixed_offset()))
+}
+
+/// The zone timestamps are shown in (`--tz`): the system zone, UTC, an IANA
+/// name such as `Europe/Berlin`, or a fixed offset such as `+05:30`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeZoneSpec {
+    #[default]
+    Local,
+    Utc,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl FromStr for TimeZoneSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "local" => return Ok(TimeZoneSpec::Local),
+            "utc" | "z" => return Ok(TimeZoneSpec::Utc),
+            _ => {}
+        }
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(TimeZoneSpec::Named(tz));
+        }
+        parse_offset(s).map(TimeZoneSpec::Fixed).ok_or_else(|| {
+            format!(
+                "unknown time zone '{s}' (use local, UTC, an IANA name or an offset like +02:00)"
+            )
+        })
+    }
+}
+
+impl fmt::Display for TimeZoneSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeZoneSpec::Local => f.write_str("local"),
+            TimeZoneSpec::Utc => f.write_str("UTC"),
+            TimeZoneSpec::Named(tz) => write!(f, "{}", tz.name()),
+            TimeZoneSpec::Fixed(offset) => write!(f, "{offset}"),
+        }
+    }
+}
+
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl TimeZoneSpec {
+    /// Wall-clock time in this zone as an instant.
+    pub fn resolve(&self, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        fn earliest<Z: TimeZone>(zone: &Z, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+            zone.from_local_datetime(naive)
+                .earliest()
+                .map(|dt| dt.with_timezone(&Utc))
+        }
+        match self {
+            TimeZoneSpec::Local => earliest(&Local, naive),
+            TimeZoneSpec::Utc => Some(naive.and_utc()),
+            TimeZoneSpec::Named(tz) => earliest(tz, naive),
+            TimeZoneSpec::Fixed(offset) => earliest(offset, naive),
+        }
+    }
+
+    /// `instant` as wall-clock time in this zone.
+    pub fn localize(&self, instant: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            TimeZoneSpec::Local => instant.with_timezone(&Local).fixed_offset(),
+            TimeZoneSpec::Utc => instant.fixed_offset(),
+            TimeZoneSpec::Named(tz) => {
+                let offset = tz.offset_from_utc_datetime(&instant.naive_utc()).fix();
+                instant.with_timezone(&offset)
+            }
+            TimeZoneSpec::Fixed(offset) => instant.with_timezone(offset),
+        }
+    }
+}
+
+/// How the timestamp column is rendered; cycled with `t`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampMode {
+    /// Exactly as the producer wrote it.
+    #[default]
+    Original,
+    /// ISO 8601 in the display zone.
+    IsoLocal,
+    IsoUtc,
+    /// `HH:MM:SS.mmm` in the display zone.
+    TimeOnly,
+    /// Age relative to now, e.g. `3s ago`.
+    Relative,
+    /// Difference to the previous row, or to the reference row when one is set.
+    Delta,
+}
+
+impl TimestampMode {
+    pub const ALL: [TimestampMode; 6] = [
+        TimestampMode::Original,
+        TimestampMode::IsoLocal,
+        TimestampMode::IsoUtc,
+        TimestampMode::TimeOnly,
+        TimestampMode::Relative,
+        TimestampMode::Delta,
+    ];
+
+    pub fn cycled(self) -> Self {
+        match self {
+            TimestampMode::Original => TimestampMode::IsoLocal,
+            TimestampMode::IsoLocal => TimestampMode::IsoUtc,
+            TimestampMode::IsoUtc => TimestampMode::TimeOnly,
+            TimestampMode::TimeOnly => TimestampMode::Relative,
+            TimestampMode::Relative => TimestampMode::Delta,
+            TimestampMode::Delta => TimestampMode::Original,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimestampMode::Original => "original",
+            TimestampMode::IsoLocal => "local",
+            TimestampMode::IsoUtc => "UTC",
+            TimestampMode::TimeOnly => "time",
+            TimestampMode::Relative => "relative",
+            TimestampMode::Delta => "delta",
+        }
+    }
+}
+
+impl FromStr for TimestampMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.label().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown timestamp format '{s}' (original, local, UTC, time, relative or delta)"
+                )
+            })
+    }
+}
+
+/// Everything needed to render one timestamp cell.
+pub struct TimeContext<'a> {
+    pub mode: TimestampMode,
+    pub zone: &'a TimeZoneSpec,
+    pub now: DateTime<Utc>,
+    /// The instant deltas are measured against (previous or reference row).
+    pub base: Option<DateTime<Utc>>,
+}
+
+impl TimeContext<'_> {
+    /// Formats `raw`/`parsed` per the mode, falling back to the original text
+    /// when the timestamp could not be parsed.
+    pub fn format(&self, raw: &str, parsed: Option<&Timestamp>) -> String {
+        let instant = parsed.and_then(|ts| ts.instant(self.zone));
+        let Some(instant) = instant.filter(|_| self.mode != TimestampMode::Original) else {
+            return raw.to_string();
+        };
+        match self.mode {
+            TimestampMode::Original => raw.to_string(),
+            TimestampMode::IsoLocal => self
+                .zone
+                .localize(instant)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+            TimestampMode::IsoUtc => instant.to_rfc3339_opts(SecondsFormat::Millis, true),
+            TimestampMode::TimeOnly => self
+                .zone
+                .localize(instant)
+                .format("%H:%M:%S%.3f")
+                .to_string(),
+            TimestampMode::Relative => {
+                let age = self.now - instant;
+                if age < TimeDelta::zero() {
+                    format!("in {}", human_duration(-age))
+                } else {
+                    format!("{} ago", human_duration(age))
+                }
+            }
+            TimestampMode::Delta => match self.base {
+                Some(base) => signed_delta(instant - base),
+                None => "+0.000s".to_string(),
+            },
+        }
+    }
+}
+
+/// Coarse age for relative mode: `45s`, `3m`, `2h5m`, `4d`.
+fn human_duration(d: TimeDelta) -> String {
+    let secs = d.num_seconds();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86_400 if secs % 3600 >= 60 => format!("{}h{}m", secs / 3600, secs % 3600 / 60),
+        3600..86_400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// Precise difference for delta mode: `+0.125s`, `-12.000s`, `+3m04.500s`.
+fn signed_delta(d: TimeDelta) -> String {
+    let sign = if d < TimeDelta::zero() { '-' } else { '+' };
+    let millis = d.num_milliseconds().unsigned_abs();
+    let (mins, rest) = (millis / 60_000, millis % 60_000);
+    if mins == 0 {
+        format!("{sign}{}.{:03}s", rest / 1000, rest % 1000)
+    } else {
+        format!("{sign}{mins}m{:02}.{:03}s", rest / 1000, rest % 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_parse_common_formats_and_convert_zones() {
+        let utc = TimeZoneSpec::Utc;
+        let expected = "2024-03-01T12:00:00.250Z";
+        for raw in [
+            "2024-03-01T12:00:00.250Z",
+            "2024-03-01 13:00:00.250+01:00",
+            "2024-03-01T07:00:00.250-0500",
+            "2024-03-01 12:00:00,250",
+            "1709294400250",
+            "1709294400.25",
+        ] {
+            let ts = Timestamp::parse(raw).unwrap_or_else(|| panic!("{raw} should parse"));
+            let ctx = TimeContext {
+                mode: TimestampMode::IsoUtc,
+                zone: &utc,
+                now: Utc::now(),
+                base: None,
+            };
+            assert_eq!(ctx.format(raw, Some(&ts)), expected, "{raw}");
+        }
+        assert!(Timestamp::parse("-").is_none());
+        assert!(Timestamp::parse("yesterday").is_none());
+
+        // Naive timestamps are read in the display zone.
+        let berlin: TimeZoneSpec = "Europe/Berlin".parse().unwrap();
+        let naive = Timestamp::parse("2024-07-01T10:00:00").unwrap();
+        let ctx = TimeContext {
+            mode: TimestampMode::IsoUtc,
+            zone: &berlin,
+            now: Utc::now(),
+            base: None,
+        };
+        assert_eq!(ctx.format("", Some(&naive)), "2024-07-01T08:00:00.000Z");
+        let ctx = TimeContext {
+            mode: TimestampMode::TimeOnly,
+            ..ctx
+        };
+        let zoned = Timestamp::parse("2024-07-01T10:00:00Z").unwrap();
+        assert_eq!(ctx.format("", Some(&zoned)), "12:00:00.000");
+        assert_eq!(
+            "+05:30".parse::<TimeZoneSpec>().unwrap().to_string(),
+            "+05:30"
+        );
+        assert!("Mars/Olympus".parse::<TimeZoneSpec>().is_err());
+
+        // Unparseable values are shown as written in every mode.
+        let ctx = TimeContext {
+            mode: TimestampMode::Relative,
+            ..ctx
+        };
+        assert_eq!(ctx.format("boot+3", None), "boot+3");
+    }
+
+    #[test]
+    fn relative_and_delta_modes() {
+        let utc = TimeZoneSpec::Utc;
+        let at = |raw: &str| Timestamp::parse(raw).and_then(|t| t.instant(&utc)).unwrap();
+        let ts = Timestamp::parse("2024-03-01T12:00:00Z").unwrap();
+        let mut ctx = TimeContext {
+            mode: TimestampMode::Relative,
+            zone: &utc,
+            now: at("2024-03-01T12:00:03Z"),
+            base: None,
+        };
+        assert_eq!(ctx.format("", Some(&ts)), "3s ago");
+        ctx.now = at("2024-03-01T14:05:00Z");
+        assert_eq!(ctx.format("", Some(&ts)), "2h5m ago");
+
+        ctx.mode = TimestampMode::Delta;
+        assert_eq!(ctx.format("", Some(&ts)), "+0.000s");
+        ctx.base = Some(at("2024-03-01T11:56:55.500Z"));
+        assert_eq!(ctx.format("", Some(&ts)), "+3m04.500s");
+        ctx.base = Some(at("2024-03-01T12:00:00.125Z"));
+        assert_eq!(ctx.format("", Some(&ts)), "-0.125s");
+    }
+}
@@ -6,8 +6,13 @@ use ratatui::{
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::{
-    app::{App, ColumnDef, FieldEntry, FieldViewState, FieldZoom, Focus, InputMode},
+    app::{
+        App, ColumnDef, ColumnWidth, Dedupe, FieldEntry, FieldViewState, FieldZoom, FilterKind,
+        Focus, InputMode, KeyMode, Keymap, TraceRow, TraceView, format_duration, row_cells_for,
+    },
     model::LogEntry,
+    theme::Theme,
+    timestamps::{TimeContext, TimestampMode},
 };
 
 pub fn render(f: &mut Frame, app: &mut App) {
@@ -15,15 +20,32 @@ pub fn render(f: &mut Frame, app: &mut App) {
         render_field_view(f, app);
         return;
     }
+    if matches!(app.input_mode, InputMode::TraceView) {
+        render_trace_view(f, app);
+        return;
+    }
+    if matches!(app.input_mode, InputMode::Clusters) {
+        render_clusters(f, app);
+        return;
+    }
 
     let full_area = f.size();
     f.render_widget(Clear, full_area);
 
-    let show_status = matches!(app.input_mode, InputMode::FilterInput)
-        || app.filter_error.is_some()
+    let show_status = matches!(
+        app.input_mode,
+        InputMode::FilterInput | InputMode::Command | InputMode::NoteInput
+    ) || app.filter_error.is_some()
         || !app.filter_query.is_empty()
+        || app.active_filter_layers() > 0
+        || !app.correlation_trail.is_empty()
+        || matches!(
+            app.input_mode,
+            InputMode::FilterStack | InputMode::SavedFilters | InputMode::LevelSelect
+        )
         || !app.autoscroll
-        || app.input_paused;
+        || app.input_paused
+        || app.notice.is_some();
 
     let status_lines = if show_status {
         Some(status_lines(app))
@@ -56,48 +78,193 @@ pub fn render(f: &mut Frame, app: &mut App) {
             .to_vec(),
     };
 
-    app.last_list_height = chunks[0].height.saturating_sub(2) as usize;
-    let list_width = chunks[0].width.saturating_sub(2) as usize;
-    app.last_list_width = list_width;
-    let enabled_columns: Vec<&ColumnDef> = app.columns.iter().filter(|c| c.enabled).collect();
-    let mut max_full_width = 0usize;
-    let mut rows: Vec<(String, Style)> = Vec::new();
+    // One line of the list block goes to the column header.
+    app.last_list_height = chunks[0].height.saturating_sub(3) as usize;
+    // Once anything is marked or annotated, a gutter shows the bookmark (or
+    // mark letter) and note glyphs.
+    let gutter = if app.marks.is_empty() { 0 } else { 3 };
+    // Folded repeats show their count and the time they span in front of
+    // the table.
+    let repeats: Vec<String> = match &app.dedupe {
+        Some(dedupe) => (0..app.filtered_indices.len())
+            .map(|row| repeat_label(app, dedupe, row))
+            .collect(),
+        None => Vec::new(),
+    };
+    let repeat_width = repeats
+        .iter()
+        .map(|label| cell_width(label))
+        .max()
+        .unwrap_or(0);
+    let repeat_gutter = if repeat_width == 0 {
+        0
+    } else {
+        repeat_width + 1
+    };
+    // The highlight symbol takes two cells in front of every row.
+    let table_width = chunks[0]
+        .width
+        .saturating_sub(2 + 2 + gutter + repeat_gutter as u16) as usize;
+    app.last_list_width = table_width;
+    let enabled: Vec<(usize, &ColumnDef)> = app
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.enabled)
+        .collect();
+    let enabled_columns: Vec<&ColumnDef> = enabled.iter().map(|&(_, c)| c).collect();
+    let reference = app
+        .time_reference_index()
+        .and_then(|idx| app.entries[idx].time)
+        .and_then(|t| t.instant(&app.time_zone));
+    let mut time = TimeContext {
+        mode: app.timestamp_mode,
+        zone: &app.time_zone,
+        now: chrono::Utc::now(),
+        base: None,
+    };
+    let mut previous = None;
+    let mut row_cells: Vec<(Vec<String>, Style)> = Vec::new();
     for &idx in &app.filtered_indices {
         if let Some(entry) = app.entries.get(idx) {
-            let full = render_row(entry, &enabled_columns);
-            max_full_width = max_full_width.max(full.width());
-            let view = slice_row(&full, app.horiz_offset, list_width);
-            rows.push((view, level_style(&entry.level)));
+            time.base = reference.or(previous);
+            row_cells.push((
+                row_cells_for(entry, &enabled_columns, &time),
+                app.theme.level_style(&entry.level),
+            ));
+            previous = entry
+                .time
+                .and_then(|t| t.instant(&app.time_zone))
+                .or(previous);
         }
     }
+    let table = TableLayout::new(&enabled_columns, &row_cells, table_width);
+    let header = if enabled_columns.is_empty() {
+        String::new()
+    } else {
+        let names: Vec<String> = enabled_columns
+            .iter()
+            .map(|c| match &app.sort {
+                Some(order) if order.column == c.name => format!("{} {}", c.name, order.arrow()),
+                _ => c.name.clone(),
+            })
+            .collect();
+        table.render(&names, app.horiz_offset, table_width)
+    };
+    let rows: Vec<(String, Style)> = row_cells
+        .iter()
+        .zip(&app.filtered_indices)
+        .enumerate()
+        .map(|(row, ((cells, style), &idx))| {
+            let mut text = if gutter == 0 {
+                String::new()
+            } else {
+                let seq = app.evicted_entries + idx as u64;
+                let mark = match app.marks.name_of(seq) {
+                    Some(name) => name,
+                    None if app.marks.is_bookmarked(seq) => '★',
+                    None => ' ',
+                };
+                let note = if app.marks.note(seq).is_some() {
+                    '✎'
+                } else {
+                    ' '
+                };
+                format!("{mark}{note} ")
+            };
+            if repeat_gutter > 0 {
+                text.push_str(&fit_cell(&repeats[row], repeat_width));
+                text.push(' ');
+            }
+            if enabled_columns.is_empty() {
+                text.push_str("[no columns selected]");
+            } else {
+                text.push_str(&table.render(cells, app.horiz_offset, table_width));
+            }
+            (text, *style)
+        })
+        .collect();
+    app.last_column_widths = vec![0; app.columns.len()];
+    for (pos, &col) in table.order.iter().enumerate() {
+        app.last_column_widths[enabled[col].0] = table.widths[pos];
+    }
     let items: Vec<ListItem> = rows
         .iter()
         .map(|(text, style)| ListItem::new(text.clone()).style(*style))
         .collect();
-    app.max_row_width = max_full_width;
+    app.max_row_width = table.total_width();
     app.clamp_offset();
 
-    let list_title = if app.filter_query.is_empty() {
-        "Logs".to_string()
-    } else {
-        format!("Logs [/{}]", app.filter_query)
+    let mut list_title = match &app.live_preview {
+        Some(preview) if preview.pattern.is_empty() => "Logs [live: all]".to_string(),
+        Some(preview) => format!("Logs [live: /{}]", preview.pattern),
+        None if app.filter_query.is_empty() => "Logs".to_string(),
+        None => format!("Logs [/{}]", app.filter_query),
     };
+    if let Some(step) = app.correlation_trail.last() {
+        let followed = &step.correlation;
+        list_title.push_str(&format!(" [{}={}]", followed.field, followed.id));
+    }
+    if let Some(template) = &app.template_filter {
+        list_title.push_str(&format!(" [template {template}]"));
+    }
+    if let Some(dedupe) = &app.dedupe {
+        list_title.push_str(&format!(" [dedupe {}]", dedupe.by.describe()));
+    }
+    if let Some(profile) = &app.active_profile {
+        list_title.push_str(&format!(" [profile {profile}]"));
+    }
+    let active_layers = app.active_filter_layers();
+    if active_layers > 0 {
+        list_title.push_str(&format!(" [+{active_layers} filter layers]"));
+    }
+    if let Some(levels) = app.level_filter.describe() {
+        list_title.push_str(&format!(" [level {levels}]"));
+    }
+    if let Some(order) = &app.sort {
+        list_title.push_str(&format!(" [sort {} {}]", order.column, order.arrow()));
+    }
+    match app.timestamp_mode {
+        TimestampMode::Original => {}
+        TimestampMode::Delta if reference.is_some() => {
+            list_title.push_str(" [time delta from mark]")
+        }
+        mode => list_title.push_str(&format!(" [time {}]", mode.label())),
+    }
 
     let list_block = Block::default()
         .title(list_title)
         .borders(Borders::ALL)
         .border_style(match app.focus {
-            Focus::List => Style::default().fg(Color::Cyan),
+            Focus::List => Style::default().fg(app.theme.accent),
             Focus::Detail => Style::default(),
         });
+    let list_inner = list_block.inner(chunks[0]);
+    f.render_widget(list_block, chunks[0]);
+    let header_area = Rect {
+        height: list_inner.height.min(1),
+        ..list_inner
+    };
+    let rows_area = Rect {
+        y: list_inner.y + header_area.height,
+        height: list_inner.height - header_area.height,
+        ..list_inner
+    };
+    f.render_widget(
+        Paragraph::new(format!("  {}{header}", " ".repeat(gutter as usize))).style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::UNDERLINED),
+        ),
+        header_area,
+    );
 
     if app.autoscroll {
         app.list_scroll_offset = app.list_state.offset();
         let list = List::new(items)
-            .block(list_block)
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
             .highlight_symbol("▸ ");
-        f.render_stateful_widget(list, chunks[0], &mut app.list_state);
+        f.render_stateful_widget(list, rows_area, &mut app.list_state);
         app.list_scroll_offset = app.list_state.offset();
     } else {
         let view_height = app.last_list_height.max(1);
@@ -133,8 +300,8 @@ pub fn render(f: &mut Frame, app: &mut App) {
 
         let mut render_state = ListState::default();
         render_state.select(None); // keep offset fixed; selection handled manually
-        let list = List::new(slice_items).block(list_block);
-        f.render_stateful_widget(list, chunks[0], &mut render_state);
+        let list = List::new(slice_items);
+        f.render_stateful_widget(list, rows_area, &mut render_state);
         app.list_scroll_offset = start;
     }
     if chunks[1].width > 0 && chunks[1].height > 0 {
@@ -144,17 +311,25 @@ pub fn render(f: &mut Frame, app: &mut App) {
             .and_then(|i| app.filtered_indices.get(i))
             .and_then(|&idx| app.entries.get(idx))
             .cloned();
-        let detail_title = if app.detail_wrap {
+        let mut detail_title = if app.detail_wrap {
             "Details".to_string()
         } else {
             "Details (wrap off)".to_string()
         };
+        let note = app
+            .list_state
+            .selected()
+            .and_then(|i| app.filtered_indices.get(i))
+            .and_then(|&idx| app.marks.note(app.evicted_entries + idx as u64));
+        if let Some(note) = note {
+            detail_title.push_str(&format!(" ✎ {note}"));
+        }
         let detail_block = Block::default()
             .title(detail_title)
             .borders(Borders::ALL)
             .padding(Padding::uniform(1))
             .border_style(match app.focus {
-                Focus::Detail => Style::default().fg(Color::Cyan),
+                Focus::Detail => Style::default().fg(app.theme.accent),
                 Focus::List => Style::default(),
             });
 
@@ -162,7 +337,7 @@ pub fn render(f: &mut Frame, app: &mut App) {
         app.last_detail_height = inner.height as usize;
         app.last_detail_width = inner.width as usize;
 
-        let detail_text = selected_details(selected_entry);
+        let detail_text = selected_details(&app.theme, selected_entry);
         let inner_width = inner.width as usize;
         app.detail_max_line_width = text_max_width(&detail_text);
         app.detail_total_lines = if app.detail_wrap {
@@ -195,14 +370,35 @@ pub fn render(f: &mut Frame, app: &mut App) {
     }
 
     if app.show_help {
-        render_help(f, full_area);
+        render_help(f, full_area, &app.keymap);
     } else if let Some(lines) = status_lines {
         render_status(f, vertical[vertical.len() - 1], lines);
     }
 
-    if matches!(app.input_mode, InputMode::ColumnSelect) {
+    if matches!(
+        app.input_mode,
+        InputMode::ColumnSelect | InputMode::ColumnInput
+    ) {
         render_column_selector(f, full_area, app);
     }
+    if matches!(app.input_mode, InputMode::FilterStack) {
+        render_filter_stack(f, full_area, app);
+    }
+    if matches!(app.input_mode, InputMode::SavedFilters) {
+        render_saved_filters(f, full_area, app);
+    }
+    if matches!(app.input_mode, InputMode::LevelSelect) {
+        render_level_select(f, full_area, app);
+    }
+    if matches!(app.input_mode, InputMode::FieldFilter) {
+        render_field_filter(f, full_area, app);
+    }
+    if matches!(app.input_mode, InputMode::Palette) {
+        render_palette(f, full_area, app);
+    }
+    if matches!(app.input_mode, InputMode::Bookmarks) {
+        render_bookmarks(f, full_area, app);
+    }
 }
 
 fn render_field_view(f: &mut Frame, app: &mut App) {
@@ -302,7 +498,163 @@ fn render_field_view(f: &mut Frame, app: &mut App) {
     }
 }
 
-fn selected_details(entry: Option<LogEntry>) -> Text<'static> {
+/// The span tree with a waterfall: each span's bar covers its share of the
+/// trace's time, and each entry is a dot at its own time.
+fn render_trace_view(f: &mut Frame, app: &mut App) {
+    let area = f.size();
+    f.render_widget(Clear, area);
+    let Some(mut view) = app.trace_view.take() else {
+        return;
+    };
+
+    let inner = (area.width as usize).saturating_sub(4);
+    let bar_width = (inner / 3).clamp(10, 60).min(inner);
+    let duration_width = 8;
+    let label_width = inner.saturating_sub(bar_width + duration_width + 2);
+    let total_ms = view.duration().map_or(0, |d| d.num_milliseconds()).max(1);
+    let column = |at: Option<chrono::DateTime<chrono::Utc>>| -> Option<usize> {
+        let offset = (at? - view.start?).num_milliseconds().clamp(0, total_ms);
+        Some(((offset * bar_width as i64 / total_ms) as usize).min(bar_width.saturating_sub(1)))
+    };
+    let dim = Style::default().add_modifier(Modifier::DIM);
+
+    let items: Vec<ListItem> = view
+        .rows
+        .iter()
+        .map(|row| match *row {
+            TraceRow::Span { span: pos, depth } => {
+                let span = &view.spans[pos];
+                let marker = if view.is_collapsed(pos) { "▸" } else { "▾" };
+                let label = format!("{}{marker} {}", "  ".repeat(depth), span.name);
+                let duration = span.duration().map(format_duration).unwrap_or_default();
+                let mut bar = vec![' '; bar_width];
+                if let (Some(from), Some(to)) = (column(span.start), column(span.end)) {
+                    bar[from..=to].fill('█');
+                }
+                Line::from(vec![
+                    Span::raw(fit_cell(&label, label_width)),
+                    Span::raw(" "),
+                    Span::raw(format!("{duration:>duration_width$}")),
+                    Span::raw(" "),
+                    Span::styled(
+                        bar.into_iter().collect::<String>(),
+                        Style::default().fg(app.theme.accent),
+                    ),
+                ])
+            }
+            TraceRow::Entry { seq, depth } => {
+                let indent = format!("{}· ", "  ".repeat(depth));
+                let Some(idx) = app.index_of_seq(seq) else {
+                    let label = format!("{indent}(evicted)");
+                    return Line::styled(fit_cell(&label, label_width), dim);
+                };
+                let entry = &app.entries[idx];
+                let level = level_span(&app.theme, &entry.level);
+                let rest = label_width
+                    .saturating_sub(cell_width(&indent) + cell_width(&level.content) + 1);
+                let mut bar = vec![' '; bar_width];
+                let at = entry.time.and_then(|t| t.instant(&app.time_zone));
+                if let Some(col) = column(at) {
+                    bar[col] = '•';
+                }
+                Line::from(vec![
+                    Span::raw(indent),
+                    level,
+                    Span::raw(" "),
+                    Span::raw(fit_cell(&entry.message, rest)),
+                    Span::raw(" ".repeat(duration_width + 2)),
+                    Span::styled(bar.into_iter().collect::<String>(), dim),
+                ])
+            }
+        })
+        .map(ListItem::new)
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(trace_title(&view))
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+    f.render_stateful_widget(list, area, &mut view.list_state);
+    app.trace_view = Some(view);
+}
+
+/// The mined templates, one per row: count, levels, first and last time.
+/// Templates that appeared since the list was last closed stand out.
+fn render_clusters(f: &mut Frame, app: &mut App) {
+    let area = f.size();
+    f.render_widget(Clear, area);
+    let Some(clusters) = app.clusters.as_ref() else {
+        return;
+    };
+    let time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+        at.map_or_else(
+            || "-".to_string(),
+            |at| app.time_zone.localize(at).format("%H:%M:%S").to_string(),
+        )
+    };
+    let new_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let items: Vec<ListItem> = app
+        .cluster_rows
+        .iter()
+        .map(|id| {
+            let cluster = &clusters.clusters[id];
+            let mut spans = vec![
+                Span::styled(if cluster.new { "new " } else { "    " }, new_style),
+                Span::raw(format!("{:>7}  ", cluster.count)),
+            ];
+            let mut levels_width = 0;
+            for (level, count) in &cluster.levels {
+                let text = format!("{level} {count} ");
+                levels_width += cell_width(&text);
+                spans.push(Span::styled(text, app.theme.level_style(level)));
+            }
+            spans.push(Span::raw(" ".repeat(24usize.saturating_sub(levels_width))));
+            spans.push(Span::raw(format!(
+                "{}–{}  ",
+                time(cluster.first),
+                time(cluster.last)
+            )));
+            let template = cluster.template.to_string();
+            spans.push(if cluster.new {
+                Span::styled(template, new_style)
+            } else {
+                Span::raw(template)
+            });
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let title = format!(
+        "Templates · {} from {} entries (Enter shows a template's entries, Esc closes)",
+        clusters.clusters.len(),
+        clusters.clusters.values().map(|c| c.count).sum::<usize>()
+    );
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+    f.render_stateful_widget(list, area, &mut app.cluster_state);
+}
+
+fn trace_title(view: &TraceView) -> String {
+    let spans = view.spans.iter().filter(|s| !s.id.is_empty()).count();
+    let duration = view
+        .duration()
+        .map(format_duration)
+        .unwrap_or_else(|| "?".to_string());
+    format!(
+        "Trace {} · {spans} spans · {} entries · {duration} (Enter to fold or jump, h/l to collapse/expand, Esc to close)",
+        view.trace_id,
+        view.entry_count()
+    )
+}
+
+fn selected_details(theme: &Theme, entry: Option<LogEntry>) -> Text<'static> {
     let Some(entry) = entry else {
         return Text::from("Waiting for logs...");
     };
@@ -310,7 +662,7 @@ fn selected_details(entry: Option<LogEntry>) -> Text<'static> {
     lines.push(Line::from(format!("timestamp: {}", entry.timestamp)));
     lines.push(Line::from(vec![
         Span::raw("level: "),
-        level_span(&entry.level),
+        level_span(theme, &entry.level),
     ]));
     lines.push(Line::from(format!("message: {}", entry.message)));
     lines.push(Line::from(""));
@@ -344,35 +696,171 @@ fn field_title(field_view: &FieldViewState) -> String {
     }
 }
 
-fn level_style(level: &str) -> Style {
-    match level.to_ascii_uppercase().as_str() {
-        "TRACE" => Style::default().fg(Color::LightGreen),
-        "DEBUG" => Style::default().fg(Color::LightMagenta),
-        "INFO" => Style::default().fg(Color::LightBlue),
-        "WARN" | "WARNING" => Style::default().fg(Color::Yellow),
-        "ERROR" => Style::default().fg(Color::Red),
-        "CRITICAL" => Style::default().fg(Color::LightRed),
-        "PARSE" => Style::default().fg(Color::Magenta),
-        "TEXT" => Style::default().fg(Color::Gray),
-        _ => Style::default(),
+/// `×12 10:00:01–10:00:09` on a folded run, `▾×12 …` on the first row of an
+/// expanded one and `│` on its other rows; empty for entries that do not
+/// repeat.
+fn repeat_label(app: &App, dedupe: &Dedupe, row: usize) -> String {
+    let members = dedupe.members(row);
+    let (Some(&first), Some(&last)) = (members.first(), members.last()) else {
+        return String::new();
+    };
+    if members.len() < 2 {
+        return String::new();
     }
+    let expanded = dedupe.is_expanded(dedupe.row_runs[row], app.evicted_entries);
+    if expanded && app.filtered_indices[row] != first {
+        return "│".to_string();
+    }
+    let time = |idx: usize| {
+        let entry = &app.entries[idx];
+        entry
+            .time
+            .and_then(|t| t.instant(&app.time_zone))
+            .map(|at| app.time_zone.localize(at).format("%H:%M:%S").to_string())
+            .unwrap_or_else(|| entry.timestamp.clone())
+    };
+    format!(
+        "{}×{} {}–{}",
+        if expanded { "▾" } else { "" },
+        members.len(),
+        time(first),
+        time(last)
+    )
 }
 
-fn level_span(level: &str) -> Span<'static> {
-    Span::styled(level.to_ascii_uppercase(), level_style(level))
+fn level_span(theme: &Theme, level: &str) -> Span<'static> {
+    Span::styled(level.to_ascii_uppercase(), theme.level_style(level))
 }
 
-fn render_row(entry: &LogEntry, cols: &[&ColumnDef]) -> String {
-    if cols.is_empty() {
-        return "[no columns selected]".to_string();
+/// Cells between table columns.
+const COLUMN_GAP: usize = 2;
+/// Auto-sized columns never grow beyond this many cells.
+const AUTO_WIDTH_MAX: usize = 40;
+/// Auto widths are measured over this many of the newest rows.
+const AUTO_WIDTH_SAMPLE: usize = 500;
+const FLEX_WIDTH_MIN: usize = 10;
+const FLEX_WIDTH_MAX: usize = 400;
+
+/// Column widths and draw order for the list table. Sticky columns come first
+/// and are not affected by the horizontal offset.
+struct TableLayout {
+    /// Indices into the enabled columns, sticky ones first.
+    order: Vec<usize>,
+    /// Width of each column in `order`.
+    widths: Vec<usize>,
+    sticky: usize,
+}
+
+impl TableLayout {
+    fn new(cols: &[&ColumnDef], rows: &[(Vec<String>, Style)], view_width: usize) -> Self {
+        let mut order: Vec<usize> = (0..cols.len()).filter(|&i| cols[i].sticky).collect();
+        let sticky = order.len();
+        order.extend((0..cols.len()).filter(|&i| !cols[i].sticky));
+
+        let sample = &rows[rows.len().saturating_sub(AUTO_WIDTH_SAMPLE)..];
+        let natural = |i: usize| {
+            sample
+                .iter()
+                .filter_map(|(cells, _)| cells.get(i))
+                .map(|cell| cell_width(cell))
+                .fold(cols[i].name.width(), usize::max)
+        };
+
+        let mut widths: Vec<usize> = order
+            .iter()
+            .map(|&i| match cols[i].width {
+                ColumnWidth::Auto => natural(i).min(AUTO_WIDTH_MAX),
+                ColumnWidth::Fixed(n) => n as usize,
+                ColumnWidth::Flex => 0,
+            })
+            .collect();
+        let flex: Vec<usize> = (0..order.len())
+            .filter(|&pos| cols[order[pos]].width == ColumnWidth::Flex)
+            .collect();
+        if !flex.is_empty() {
+            let used = widths.iter().sum::<usize>() + COLUMN_GAP * order.len().saturating_sub(1);
+            let share = view_width.saturating_sub(used) / flex.len();
+            for pos in flex {
+                widths[pos] = share
+                    .max(natural(order[pos]).min(FLEX_WIDTH_MAX))
+                    .max(FLEX_WIDTH_MIN);
+            }
+        }
+        Self {
+            order,
+            widths,
+            sticky,
+        }
+    }
+
+    /// Width of the sticky block including the gap after it.
+    fn sticky_width(&self) -> usize {
+        self.widths[..self.sticky]
+            .iter()
+            .map(|w| w + COLUMN_GAP)
+            .sum()
     }
-    let separator = " | ";
-    let mut parts = Vec::with_capacity(cols.len());
-    for col in cols {
-        let val = entry_field_or_raw(entry, &col.path).unwrap_or_default();
-        parts.push(val);
+
+    fn total_width(&self) -> usize {
+        self.widths.iter().sum::<usize>() + COLUMN_GAP * self.widths.len().saturating_sub(1)
     }
-    parts.join(separator)
+
+    /// Lays out one row (cells in column order) for a viewport `view_width`
+    /// cells wide, panned `offset` cells past the sticky columns.
+    fn render(&self, cells: &[String], offset: usize, view_width: usize) -> String {
+        let gap = " ".repeat(COLUMN_GAP);
+        let cell = |pos: usize| {
+            let text = cells.get(self.order[pos]).map_or("", String::as_str);
+            fit_cell(text, self.widths[pos])
+        };
+        let mut sticky = String::new();
+        for pos in 0..self.sticky {
+            sticky.push_str(&cell(pos));
+            sticky.push_str(&gap);
+        }
+        let scrolling: Vec<String> = (self.sticky..self.order.len()).map(cell).collect();
+        let sticky_width = self.sticky_width().min(view_width);
+        let mut out = slice_row(&sticky, 0, sticky_width);
+        let rest = view_width - sticky_width;
+        out.push_str(&slice_row(&scrolling.join(&gap), offset, rest));
+        out
+    }
+}
+
+fn cell_width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| {
+            if ch.is_control() {
+                1
+            } else {
+                ch.width().unwrap_or(0)
+            }
+        })
+        .sum()
+}
+
+/// Pads `text` to exactly `width` cells, or truncates it with `…`. Line breaks
+/// and tabs become spaces so a row stays on one line.
+fn fit_cell(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0usize;
+    let fits = cell_width(text) <= width;
+    let budget = if fits { width } else { width.saturating_sub(1) };
+    for ch in text.chars() {
+        let ch = if ch.is_control() { ' ' } else { ch };
+        let w = ch.width().unwrap_or(0);
+        if used + w > budget {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    if !fits && width > 0 {
+        out.push('…');
+        used += 1;
+    }
+    out.push_str(&" ".repeat(width.saturating_sub(used)));
+    out
 }
 
 fn slice_row(s: &str, offset: usize, width: usize) -> String {
@@ -401,47 +889,10 @@ fn slice_row(s: &str, offset: usize, width: usize) -> String {
     out
 }
 
-fn extract_field_string(value: &serde_json::Value, path: &[String]) -> Option<String> {
-    if let serde_json::Value::String(s) = value
-        && path.len() == 1
-    {
-        match path[0].as_str() {
-            "timestamp" => return Some("-".into()),
-            "level" => return Some("TEXT".into()),
-            "message" => return Some(s.clone()),
-            _ => {}
-        }
-    }
-
-    let mut current = value;
-    for key in path {
-        current = current.get(key)?;
-    }
-    match current {
-        serde_json::Value::String(s) => Some(s.clone()),
-        serde_json::Value::Number(n) => Some(n.to_string()),
-        serde_json::Value::Bool(b) => Some(b.to_string()),
-        serde_json::Value::Null => Some("null".into()),
-        other => Some(other.to_string()),
-    }
-}
-
-fn entry_field_or_raw(entry: &LogEntry, path: &[String]) -> Option<String> {
-    if path.len() == 1 {
-        match path[0].as_str() {
-            "timestamp" => return Some(entry.timestamp.clone()),
-            "level" => return Some(entry.level.clone()),
-            "message" => return Some(entry.message.clone()),
-            _ => {}
-        }
-    }
-    extract_field_string(&entry.raw, path)
-}
-
-fn render_help(f: &mut Frame, area: Rect) {
+fn render_help(f: &mut Frame, area: Rect, keymap: &Keymap) {
     let mut lines: Vec<Line> = Vec::new();
-    let mut entries = all_shortcuts();
-    entries.sort_by(|a, b| a.context.cmp(b.context));
+    let mut entries = all_shortcuts(keymap);
+    entries.sort_by_key(|sc| HELP_CONTEXTS.iter().position(|&c| c == sc.context));
     let mut current_context: Option<&str> = None;
     for sc in entries {
         if current_context != Some(sc.context) {
@@ -493,7 +944,19 @@ fn render_column_selector(f: &mut Frame, area: Rect, app: &mut App) {
         .iter()
         .map(|c| {
             let prefix = if c.enabled { "[x]" } else { "[ ]" };
-            let text = format!("{prefix} {}", c.name);
+            let pin = if c.sticky { "pin" } else { "   " };
+            let name = match &c.expr {
+                Some(expr) if expr.source() != c.name => {
+                    format!("{} = {}", c.name, expr.source())
+                }
+                Some(_) => format!("{} (jq)", c.name),
+                None => c.name.clone(),
+            };
+            let sorted = match &app.sort {
+                Some(order) if order.column == c.name => order.arrow(),
+                _ => " ",
+            };
+            let text = format!("{prefix} {pin} {sorted} {:<6} {name}", c.width.label());
             ListItem::new(text)
         })
         .collect();
@@ -511,28 +974,466 @@ fn render_column_selector(f: &mut Frame, area: Rect, app: &mut App) {
     f.render_stateful_widget(list, popup, &mut app.column_select_state);
 }
 
+fn render_filter_stack(f: &mut Frame, area: Rect, app: &mut App) {
+    let width = (area.width.saturating_sub(10)).clamp(40, 90);
+    let height = (app.filter_stack.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let items: Vec<ListItem> = if app.filter_stack.is_empty() {
+        vec![ListItem::new("(empty: i to add include, x to add exclude)")]
+    } else {
+        app.filter_stack
+            .iter()
+            .enumerate()
+            .map(|(pos, layer)| {
+                let prefix = if layer.enabled { "[x]" } else { "[ ]" };
+                let sign = match layer.kind {
+                    FilterKind::Include => "+",
+                    FilterKind::Exclude => "-",
+                };
+                let style = match layer.kind {
+                    FilterKind::Include => Style::default().fg(Color::Green),
+                    FilterKind::Exclude => Style::default().fg(Color::Red),
+                };
+                let drops = match app.layer_drops.get(pos) {
+                    Some(&count) if layer.enabled => format!("  −{count}"),
+                    _ => String::new(),
+                };
+                ListItem::new(format!(
+                    "{prefix} {sign} /{}/{}{drops}",
+                    layer.pattern,
+                    modes_suffix(&layer.modes.labels())
+                ))
+                .style(style)
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Filter stack (space to toggle, J/K to move, Esc to close; −N: entries hidden first by the layer)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.filter_stack_state);
+}
+
+fn render_level_select(f: &mut Frame, area: Rect, app: &mut App) {
+    let rows = app.level_rows();
+    let width = (area.width.saturating_sub(10)).clamp(40, 60);
+    let height = (rows.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let items: Vec<ListItem> = if rows.is_empty() {
+        vec![ListItem::new("(no levels seen yet)")]
+    } else {
+        rows.iter()
+            .map(|(level, count)| {
+                let shown = app.level_filter.allows(level);
+                let prefix = if shown { "[x]" } else { "[ ]" };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{prefix} ")),
+                    Span::styled(format!("{level:10}"), app.theme.level_style(level)),
+                    Span::raw(format!("{count:>8}")),
+                ]))
+            })
+            .collect()
+    };
+
+    let title = match app.level_filter.min_label() {
+        Some(min) => format!("Levels (≥{min}; space to toggle, v threshold, Esc to close)"),
+        None => "Levels (space to toggle, v threshold, Esc to close)".to_string(),
+    };
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.level_select_state);
+}
+
+fn render_field_filter(f: &mut Frame, area: Rect, app: &mut App) {
+    let width = (area.width.saturating_sub(10)).clamp(40, 90);
+    let height = (app.field_filter_fields.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let items: Vec<ListItem> = app
+        .field_filter_fields
+        .iter()
+        .map(|field| {
+            ListItem::new(Line::from(vec![
+                Span::styled(field.path.clone(), Style::default().fg(Color::Cyan)),
+                Span::raw(format!(" = {}", field.value)),
+            ]))
+        })
+        .collect();
+
+    let title = match app.field_filter_kind {
+        FilterKind::Include => "Show only entries where (Enter/= include, ! exclude, Esc to close)",
+        FilterKind::Exclude => "Hide entries where (Enter/! exclude, = include, Esc to close)",
+    };
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.field_filter_state);
+}
+
+fn render_saved_filters(f: &mut Frame, area: Rect, app: &mut App) {
+    let saved = &app.filter_history.saved;
+    let width = (area.width.saturating_sub(10)).clamp(40, 90);
+    let height = (saved.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let name_width = saved.iter().map(|f| f.name.width()).max().unwrap_or(0);
+    let items: Vec<ListItem> = if saved.is_empty() {
+        vec![ListItem::new(
+            "(none yet: type :save <name> in the / prompt)",
+        )]
+    } else {
+        saved
+            .iter()
+            .map(|f| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:name_width$}", f.name),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw(format!(
+                        "  /{}/{}",
+                        f.pattern,
+                        modes_suffix(&f.modes.labels())
+                    )),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Saved filters (Enter to apply, d to delete, Esc to close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.saved_filter_state);
+}
+
+fn render_bookmarks(f: &mut Frame, area: Rect, app: &mut App) {
+    let width = (area.width.saturating_sub(10)).clamp(40, 90);
+    let height = (app.bookmark_rows.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let items: Vec<ListItem> = if app.bookmark_rows.is_empty() {
+        vec![ListItem::new(
+            "(none yet: b bookmarks the selected entry, m<letter> names it, n adds a note)",
+        )]
+    } else {
+        app.bookmark_rows
+            .iter()
+            .map(|row| {
+                let mut label: String = row.names.iter().collect();
+                if row.bookmarked {
+                    label.insert(0, '★');
+                }
+                if row.note.is_some() {
+                    label.push('✎');
+                }
+                let label = Span::styled(format!("{label:4}"), Style::default().fg(Color::Yellow));
+                let Some(idx) = app.index_of_seq(row.seq) else {
+                    // Evicted while the popup is open.
+                    return ListItem::new(Line::from(vec![label, Span::raw("(evicted)")]));
+                };
+                let entry = &app.entries[idx];
+                let mut text = format!("{} {} {}", entry.timestamp, entry.level, entry.message);
+                if let Some(note) = &row.note {
+                    text = format!("{note} — {text}");
+                }
+                let (text, style) = if app.matched_indices().contains(&idx) {
+                    (text, app.theme.level_style(&entry.level))
+                } else {
+                    (
+                        format!("{text} (filtered out)"),
+                        Style::default().add_modifier(Modifier::DIM),
+                    )
+                };
+                ListItem::new(Line::from(vec![label, Span::styled(text, style)]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Bookmarks and notes (Enter to jump, d to delete, Esc to close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.bookmark_state);
+}
+
+fn render_palette(f: &mut Frame, area: Rect, app: &mut App) {
+    let width = (area.width.saturating_sub(10)).clamp(40, 90);
+    let height = (app.palette_matches.len() as u16 + 4)
+        .min(area.height.saturating_sub(2))
+        .max(6);
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let name_width = app
+        .palette_matches
+        .iter()
+        .map(|a| a.name().len())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = if app.palette_matches.is_empty() {
+        vec![ListItem::new("(no matching action)")]
+    } else {
+        app.palette_matches
+            .iter()
+            .map(|&action| {
+                let keys = app
+                    .keymap
+                    .help(KeyMode::Normal)
+                    .into_iter()
+                    .find(|(a, _)| *a == action)
+                    .map(|(_, keys)| {
+                        keys.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .unwrap_or_default();
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:name_width$}", action.name()),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw(format!("  {}", action.description(KeyMode::Normal))),
+                    Span::styled(format!("  {keys}"), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(
+                    "Actions: {}_ (Enter to run, Esc to close)",
+                    app.palette_query
+                ))
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▸ ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.palette_state);
+}
+
 fn status_lines(app: &App) -> Vec<Line<'static>> {
     let mut lines: Vec<Line> = Vec::new();
+    if matches!(app.input_mode, InputMode::Command) {
+        lines.push(Line::from(format!(":{}_", app.command_buffer)));
+        match &app.command_completion {
+            Some(completion) if completion.candidates.len() > 1 => {
+                let spans: Vec<Span> = completion
+                    .candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candidate)| {
+                        let style = if i == completion.index {
+                            Style::default().add_modifier(Modifier::REVERSED)
+                        } else {
+                            Style::default()
+                        };
+                        Span::styled(format!("{candidate} "), style)
+                    })
+                    .collect();
+                lines.push(Line::from(spans));
+            }
+            _ => lines.push(Line::styled(
+                "Tab completes, Enter runs, Esc cancels: filter, columns add <path>, goto <time>, set wrap, source add <file>, mark, or any action name",
+                Style::default().fg(Color::DarkGray),
+            )),
+        }
+        return lines;
+    }
     if matches!(app.input_mode, InputMode::ColumnSelect) {
         lines.push(Line::from(
-            "Columns: j/k or arrows to move cursor, space/enter to toggle, J/K to move column, Esc to close",
+            "Columns: j/k to move, space/enter to toggle, J/K to reorder, w width mode, </> resize, p pin, o sort, + add jq column, d delete it, Esc to close",
         ));
         return lines;
     }
-    if matches!(app.input_mode, InputMode::FilterInput) {
+    if matches!(app.input_mode, InputMode::NoteInput) {
+        lines.push(Line::from(format!("Note: {}_", app.note_buffer)));
+        lines.push(Line::styled(
+            "Enter to save (empty removes the note), Esc to cancel",
+            Style::default().fg(Color::DarkGray),
+        ));
+        return lines;
+    }
+    if matches!(app.input_mode, InputMode::ColumnInput) {
         lines.push(Line::from(format!(
-            "Filter (regex): {}_",
-            app.filter_buffer
+            "Computed column (name = jq expression): {}_",
+            app.column_buffer
         )));
+        lines.push(Line::styled(
+            "e.g. latency = .durationMs / 1000, items = .data.items | length; Enter to add, Esc to cancel",
+            Style::default().fg(Color::DarkGray),
+        ));
+        if let Some(err) = &app.filter_error {
+            lines.push(Line::styled(
+                format!("Column error: {err}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        return lines;
+    }
+    if matches!(app.input_mode, InputMode::FieldFilter) {
+        lines.push(Line::from(
+            "Filter by field: j/k to move, = to show only this value, ! to hide it, Esc to close",
+        ));
+        return lines;
+    }
+    if matches!(app.input_mode, InputMode::LevelSelect) {
+        lines.push(Line::from(
+            "Levels: j/k to move, space/enter to show/hide, v to cycle minimum severity, r to reset, Esc to close",
+        ));
+        return lines;
+    }
+    if matches!(app.input_mode, InputMode::FilterStack) {
+        lines.push(Line::from(
+            "Filter stack: i/x to add include/exclude, space to toggle, t to flip kind, d to delete, J/K to reorder, Esc to close",
+        ));
+        if let Some(err) = &app.filter_error {
+            lines.push(Line::styled(
+                format!("Filter error: {err}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        return lines;
+    }
+    if matches!(app.input_mode, InputMode::FilterInput) {
+        let label = match app.filter_input_target {
+            None => "Filter",
+            Some(FilterKind::Include) => "Include layer",
+            Some(FilterKind::Exclude) => "Exclude layer",
+        };
+        let mut kinds = vec![if app.filter_modes.jq {
+            "jq"
+        } else if app.filter_modes.literal {
+            "literal"
+        } else {
+            "regex"
+        }];
+        if !app.filter_modes.jq {
+            if app.filter_modes.smart_case {
+                kinds.push("smart-case");
+            }
+            if app.filter_modes.whole_word {
+                kinds.push("word");
+            }
+        }
+        let kinds = kinds.join(", ");
+        if let Some(query) = &app.history_search {
+            lines.push(Line::from(format!(
+                "(reverse-i-search)`{query}': {}",
+                app.history_search_hit().unwrap_or("")
+            )));
+        } else {
+            let mut spans = vec![Span::raw(format!(
+                "{label} ({kinds}): {}_",
+                app.filter_buffer
+            ))];
+            if let Some(err) = &app.live_filter_error {
+                spans.push(Span::styled(
+                    format!("  ✗ {}", first_line(err)),
+                    Style::default().fg(Color::Red),
+                ));
+            } else if app.live_filter_pending() {
+                spans.push(Span::styled("  …", Style::default().fg(Color::DarkGray)));
+            } else if app.live_preview.is_some() {
+                spans.push(Span::styled(
+                    format!("  ({} matches)", app.matched_indices().len()),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::styled(
+            "Up/Down history, Ctrl+R search, Ctrl+F saved filters, :save <name> stores the active filter, Alt+C/L/W smart-case/literal/word, Alt+J jq",
+            Style::default().fg(Color::DarkGray),
+        ));
     } else if !app.filter_query.is_empty() {
         lines.push(Line::from(format!(
-            "Filter: /{}/ ({})",
+            "Filter: /{}/{} ({})",
             app.filter_query,
-            app.filtered_indices.len()
+            modes_suffix(&app.filter_query_modes.labels()),
+            app.matched_indices().len()
         )));
     } else {
         lines.push(Line::from("Filter: (none)"));
     }
+    if let Some(first) = app.correlation_trail.first() {
+        let start = match first.view.filter_query.as_str() {
+            "" => "all entries".to_string(),
+            query => format!("/{query}"),
+        };
+        let crumbs: Vec<String> = std::iter::once(start)
+            .chain(
+                app.correlation_trail
+                    .iter()
+                    .map(|step| format!("{}={}", step.correlation.field, step.correlation.id)),
+            )
+            .collect();
+        lines.push(Line::from(format!(
+            "Following: {} (Backspace to go back)",
+            crumbs.join(" › ")
+        )));
+    }
+    if !app.filter_stack.is_empty() {
+        lines.push(Line::from(format!(
+            "Filter stack: {}/{} layers active (F to edit)",
+            app.active_filter_layers(),
+            app.filter_stack.len()
+        )));
+    }
 
     let input_line = if app.input_paused {
         Line::styled(
@@ -555,9 +1456,36 @@ fn status_lines(app: &App) -> Vec<Line<'static>> {
             Style::default().fg(Color::Red),
         ));
     }
+    if let Some(notice) = &app.notice {
+        let color = if notice.is_error {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        lines.push(Line::styled(
+            notice.text.clone(),
+            Style::default().fg(color),
+        ));
+    }
     lines
 }
 
+fn modes_suffix(labels: &[&str]) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", labels.join(", "))
+    }
+}
+
+/// Regex compile errors span several lines; the prompt only has room for the summary.
+fn first_line(err: &str) -> &str {
+    err.lines()
+        .rev()
+        .find(|line| line.starts_with("error:"))
+        .unwrap_or_else(|| err.lines().next().unwrap_or(err))
+}
+
 fn render_status(f: &mut Frame, area: Rect, lines: Vec<Line<'static>>) {
     let block = Block::default().borders(Borders::ALL);
     let status = Paragraph::new(Text::from(lines))
@@ -713,194 +1641,64 @@ fn wrapped_height(text: &Text<'_>, width: usize) -> usize {
     if text.lines.is_empty() { 0 } else { total }
 }
 
-#[derive(Clone, Copy)]
 struct Shortcut {
     context: &'static str,
-    keys: &'static str,
+    keys: String,
     description: &'static str,
 }
 
-fn all_shortcuts() -> Vec<Shortcut> {
-    vec![
-        Shortcut {
-            context: "Global",
-            keys: "q",
-            description: "Quit",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+C",
-            description: "Quit",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "?",
-            description: "Toggle help",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "/",
-            description: "Filter logs (regex)",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+L",
-            description: "Force redraw",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+T",
-            description: "Open field viewer",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+Z",
-            description: "Zoom focused pane (list/detail/field viewer)",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "s",
-            description: "Toggle input stream (pause/resume)",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+E",
-            description: "Open entry/field in $EDITOR",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+N",
-            description: "Next log (any pane)",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "Ctrl+P",
-            description: "Previous log (any pane)",
-        },
-        Shortcut {
-            context: "List",
-            keys: "j/k, Up/Down, h/l",
-            description: "Move selection",
-        },
-        Shortcut {
-            context: "List",
-            keys: "Ctrl+d / Ctrl+u",
-            description: "Half-page down/up",
-        },
-        Shortcut {
-            context: "List",
-            keys: "a",
-            description: "Toggle autoscroll",
-        },
-        Shortcut {
-            context: "List",
-            keys: "g / G",
-            description: "Jump to top/bottom",
-        },
-        Shortcut {
-            context: "List",
-            keys: "Enter, Tab, Right",
-            description: "Focus details",
-        },
-        Shortcut {
-            context: "List",
-            keys: "z or Ctrl+Z",
-            description: "Toggle zoom (list)",
-        },
-        Shortcut {
-            context: "List",
-            keys: "Ctrl+E",
-            description: "Open entry in $EDITOR",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "j/k, Up/Down",
-            description: "Scroll details vertically",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "Ctrl+d / Ctrl+u",
-            description: "Half-page down/up",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "g / G",
-            description: "Jump to top/bottom",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "h / l (wrap off)",
-            description: "Pan horizontally",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "0 / $ (wrap off)",
-            description: "Jump to start/end horizontally",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "w",
-            description: "Toggle wrap (details)",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "z or Ctrl+Z",
-            description: "Toggle zoom (details)",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "Tab, Left, Esc",
-            description: "Back to list",
-        },
-        Shortcut {
-            context: "Detail",
-            keys: "Ctrl+E",
-            description: "Open entry in $EDITOR",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "Ctrl+E",
-            description: "Open selected field in $EDITOR",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "Ctrl+d / Ctrl+u",
-            description: "Half-page down/up (list) or scroll detail when zoomed",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "h / l (wrap off)",
-            description: "Pan field detail horizontally",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "0 / $ (wrap off)",
-            description: "Jump to start/end horizontally",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "w",
-            description: "Toggle wrap (field detail)",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "Ctrl+j / Ctrl+n",
-            description: "Next field",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "Ctrl+k / Ctrl+p",
-            description: "Previous field",
-        },
-        Shortcut {
-            context: "Field viewer",
-            keys: "/",
-            description: "Close and prefill / filter with selected value",
-        },
-        Shortcut {
-            context: "Global",
-            keys: "c",
-            description: "Toggle column selector",
-        },
-    ]
+/// Help sections, in display order.
+const HELP_CONTEXTS: [&str; 13] = [
+    "Global",
+    "List and detail",
+    "Filter prompt",
+    "Column selector",
+    "Field viewer",
+    "Level checklist",
+    "Field filter picker",
+    "Saved filters",
+    "Filter stack",
+    "Action palette",
+    "Bookmarks",
+    "Trace view",
+    "Templates",
+];
+
+/// The shortcuts of the active keymap, plus the keys no keymap covers.
+fn all_shortcuts(keymap: &Keymap) -> Vec<Shortcut> {
+    let mut shortcuts: Vec<Shortcut> = KeyMode::ALL
+        .into_iter()
+        .flat_map(|mode| {
+            keymap
+                .help(mode)
+                .into_iter()
+                .map(move |(action, keys)| Shortcut {
+                    context: mode.title(),
+                    keys: keys
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    description: action.description(mode),
+                })
+        })
+        .collect();
+    let fixed = [
+        ("Global", "Ctrl+C", "Quit"),
+        (
+            "Filter prompt",
+            ":save <name>",
+            "Save the active filter under a name",
+        ),
+    ];
+    shortcuts.extend(
+        fixed
+            .into_iter()
+            .map(|(context, keys, description)| Shortcut {
+                context,
+                keys: keys.to_string(),
+                description,
+            }),
+    );
+    shortcuts
 }